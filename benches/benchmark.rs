@@ -1,7 +1,6 @@
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
-use sol::{lexer::Lexer, parser::Parser, typechecker::Typechecker};
-
-// FIXME: errors are causing the benchmark to basically increase in memory forever
+use sol::{compiler::Compiler, lexer::Lexer, parser::Parser, typechecker::Typechecker, vm::VM};
+use std::io;
 
 fn criterion_benchmark(c: &mut Criterion) {
     let input = include_str!("./input.sol");
@@ -22,10 +21,8 @@ fn criterion_benchmark(c: &mut Criterion) {
                 let lexer = Lexer::new(0, input);
                 Parser::new(lexer, input)
             },
-            move |parser| {
-                // parser returning result means error builds infinitely and ooms :)
-                let _ = parser.collect::<Vec<_>>();
-            },
+            // stops at the first error instead of collecting every one after it
+            move |parser| parser.collect::<Result<Vec<_>, _>>().map(|_| ()),
             BatchSize::SmallInput,
         )
     });
@@ -38,10 +35,105 @@ fn criterion_benchmark(c: &mut Criterion) {
                     .map(|s| s.unwrap())
                     .collect::<Vec<_>>()
             },
-            move |statements| Typechecker::default().check(&statements),
+            move |statements| Typechecker::default().check(&statements).map(|_| ()),
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("compiler", |b| {
+        b.iter_batched(
+            || {
+                let lexer = Lexer::new(0, input);
+                Parser::new(lexer, input)
+                    .map(|s| s.unwrap())
+                    .collect::<Vec<_>>()
+            },
+            move |statements| Compiler::new().compile(&statements).map(|_| ()),
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("run", |b| {
+        b.iter_batched(
+            || {
+                let lexer = Lexer::new(0, input);
+                let statements = Parser::new(lexer, input)
+                    .map(|s| s.unwrap())
+                    .collect::<Vec<_>>();
+
+                Compiler::new().compile(&statements).unwrap()
+            },
+            move |program| VM::new(program).with_output(io::sink()).run(),
+            BatchSize::SmallInput,
+        )
+    });
+
+    // object fields come back as owned values, so every argument copy is a real string copy
+    let call_input = format!(
+        r#"
+fn consume(s: string) -> int {{
+    return 1;
+}}
+
+let data = {{ payload: "{}" }};
+let payload = data.payload;
+let mut i = 0;
+loop {{
+    if i > 1000 {{
+        break;
+    }}
+
+    consume(payload);
+    i = i + 1;
+}}
+"#,
+        "x".repeat(64 * 1024)
+    );
+
+    c.bench_function("call_with_large_string", |b| {
+        b.iter_batched(
+            || {
+                let lexer = Lexer::new(0, &call_input);
+                let statements = Parser::new(lexer, &call_input)
+                    .map(|s| s.unwrap())
+                    .collect::<Vec<_>>();
+
+                Compiler::new().compile(&statements).unwrap()
+            },
+            move |program| VM::new(program).run(),
+            BatchSize::SmallInput,
+        )
+    });
+
+    let counting_input = r#"
+let mut i = 0;
+loop {
+    if i >= 1000000 {
+        break;
+    }
+
+    i = i + 1;
+}
+"#;
+
+    // each iteration is a few million instructions, keep the sample count down
+    let mut group = c.benchmark_group("vm");
+    group.sample_size(10);
+    group.bench_function("counting_loop", |b| {
+        b.iter_batched(
+            || {
+                let lexer = Lexer::new(0, counting_input);
+                let statements = Parser::new(lexer, counting_input)
+                    .map(|s| s.unwrap())
+                    .collect::<Vec<_>>();
+
+                Compiler::new().compile(&statements).unwrap()
+            },
+            move |program| VM::new(program).run(),
             BatchSize::SmallInput,
         )
     });
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,4 +1,7 @@
-use crate::types::{self};
+use crate::{
+    lexer::Span,
+    types::{self},
+};
 use ordermap::OrderMap;
 
 #[derive(Debug, PartialEq)]
@@ -6,13 +9,33 @@ pub struct Function {
     pub name: String,
     pub parameters: Vec<FunctionParameter>,
     pub body: Box<Statement>,
-    pub return_type_name: Option<String>,
+    pub return_type_name: Option<TypeName>,
+    // the function's name
+    pub span: Span,
+}
+
+// a type annotation, `int?` in `let x: int? = nil;`
+#[derive(Debug, PartialEq)]
+pub struct TypeName {
+    pub name: String,
+    pub span: Span,
+}
+
+impl std::fmt::Display for TypeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct FunctionParameter {
     pub name: String,
-    pub type_name: String,
+    pub type_name: TypeName,
+    // trailing arguments are collected into an array
+    pub is_variadic: bool,
+    // filled in at the call site when the argument is left out
+    pub default_value: Option<types::Literal>,
+    pub span: Span,
 }
 
 impl Function {
@@ -20,13 +43,15 @@ impl Function {
         name: String,
         parameters: Vec<FunctionParameter>,
         body: Box<Statement>,
-        return_type_name: Option<String>,
+        return_type_name: Option<TypeName>,
+        span: Span,
     ) -> Self {
         Self {
             name,
             parameters,
             body,
             return_type_name,
+            span,
         }
     }
 }
@@ -36,21 +61,29 @@ pub enum Statement {
     Const {
         name: String,
         value: Expression,
-        type_name: Option<String>,
+        type_name: Option<TypeName>,
+        // the name being declared
+        span: Span,
     },
     Let {
         name: String,
         value: Box<Expression>,
         is_mutable: bool,
-        type_name: Option<String>,
+        type_name: Option<TypeName>,
+        // the name being declared
+        span: Span,
     },
     Reassignment {
         name: String,
         value: Box<Expression>,
+        // the name being assigned to
+        span: Span,
     },
     ObjectMutation {
         path: Expression,
         value: Box<Expression>,
+        // the object whose field is assigned to
+        span: Span,
     },
     If {
         condition: Box<Expression>,
@@ -59,14 +92,50 @@ pub enum Statement {
     },
     Block {
         body: Vec<Statement>,
+        // one per statement in body
+        spans: Vec<Span>,
     },
     Loop {
         body: Box<Statement>,
     },
+    For {
+        binding: String,
+        iterable: Iterable,
+        body: Box<Statement>,
+        // the loop variable
+        span: Span,
+    },
+    // arms are tried in order, the first pattern equal to the value runs
+    Match {
+        value: Expression,
+        arms: Vec<MatchArm>,
+    },
     Return(Expression),
     Function(Function),
     Expression(Expression),
-    Break,
+    // the value ends up in the loop's result register
+    Break(Option<Expression>),
+    Continue,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Statement,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Pattern {
+    Literal(types::Literal),
+    // `_`, matches anything
+    Wildcard,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Iterable {
+    Array(Expression),
+    // `start..end`, the end is excluded
+    Range { start: Expression, end: Expression },
 }
 
 #[derive(Debug, PartialEq)]
@@ -74,6 +143,7 @@ pub enum Operator {
     Plus,
     Minus,
     Multiply,
+    Modulo,
     Not,
     Divide,
     GreaterThan,
@@ -82,37 +152,76 @@ pub enum Operator {
     LessThanOrEqual,
     Equal,
     NotEqual,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
 }
 
+// binds tighter than any infix operator, looser than prefix
+pub const CAST_BINDING_POWER: u8 = 21;
+
 impl Operator {
     // https://domenicquirl.github.io/blog/parsing-basics/
     // ... :)
     pub fn prefix_binding_power(&self) -> ((), u8) {
         match self {
-            Self::Minus | Self::Plus | Self::Not => ((), 51),
+            Self::Minus | Self::Plus | Self::Not | Self::BitNot => ((), 51),
             _ => unreachable!(),
         }
     }
 
     pub fn infix_binding_power(&self) -> Option<(u8, u8)> {
         match self {
+            Self::Or => Some((1, 2)),
+            Self::And => Some((3, 4)),
             Self::Equal | Self::NotEqual => Some((5, 6)),
             Self::GreaterThan
             | Self::GreaterThanOrEqual
             | Self::LessThan
             | Self::LessThanOrEqual => Some((7, 8)),
-            Self::Plus | Self::Minus => Some((9, 10)),
-            Self::Multiply | Self::Divide => Some((11, 12)),
+            // same ordering as rust, bitwise binds tighter than comparisons
+            Self::BitOr => Some((9, 10)),
+            Self::BitXor => Some((11, 12)),
+            Self::BitAnd => Some((13, 14)),
+            Self::ShiftLeft | Self::ShiftRight => Some((15, 16)),
+            Self::Plus | Self::Minus => Some((17, 18)),
+            Self::Multiply | Self::Divide | Self::Modulo => Some((19, 20)),
             _ => None,
         }
     }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Self::Equal
+                | Self::NotEqual
+                | Self::GreaterThan
+                | Self::GreaterThanOrEqual
+                | Self::LessThan
+                | Self::LessThanOrEqual
+        )
+    }
 }
 
+#[allow(unused)]
 #[derive(Debug, PartialEq)]
 pub struct ObjectExpression {
     pub fields: OrderMap<String, Expression>,
 }
 
+// entries in an object literal, later ones win when names repeat
+#[derive(Debug, PartialEq)]
+pub enum ObjectField {
+    Named(String, Expression),
+    // `...expr`, copies every field of another object
+    Spread(Expression),
+}
+
 // TODO: we need spans...
 #[derive(Debug, PartialEq)]
 pub enum Expression {
@@ -130,9 +239,11 @@ pub enum Expression {
     FunctionCall {
         name: String,
         args: Vec<Expression>,
+        // one per argument
+        spans: Vec<Span>,
     },
     Object {
-        fields: OrderMap<String, Expression>,
+        fields: Vec<ObjectField>,
     },
     Array {
         this: Vec<Expression>,
@@ -144,4 +255,21 @@ pub enum Expression {
         name: String,
         index: Box<Expression>,
     },
+    // `name[start..end]`, end is exclusive
+    ArraySlice {
+        name: String,
+        start: Box<Expression>,
+        end: Box<Expression>,
+    },
+    // `expr as type_name`
+    Cast {
+        expr: Box<Expression>,
+        type_name: String,
+    },
+    // each arm is a single expression in braces, the typechecker insists on the else
+    If {
+        condition: Box<Expression>,
+        body: Box<Expression>,
+        else_body: Option<Box<Expression>>,
+    },
 }
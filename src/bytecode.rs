@@ -0,0 +1,721 @@
+use crate::{
+    compiler::{CompiledProgram, Function},
+    instructions::{CastTarget, FunctionId, Instruction, JumpOffset, LiteralId, Register},
+    types::Literal,
+};
+use thiserror::Error;
+
+// layout, all integers little endian:
+// magic, version, literal count, literals, function count, functions,
+// global code, global register count, global result, global symbol count, global symbols
+const MAGIC: &[u8; 4] = b"SOLC";
+const VERSION: u16 = 4;
+const INSTRUCTION_SIZE: usize = 7;
+
+const LITERAL_STRING: u8 = 0;
+const LITERAL_FLOAT: u8 = 1;
+const LITERAL_INTEGER: u8 = 2;
+const LITERAL_BOOLEAN: u8 = 3;
+const LITERAL_NIL: u8 = 4;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum BytecodeError {
+    #[error("not a compiled sol program")]
+    InvalidMagic,
+    #[error("unsupported bytecode version {version}, expected {VERSION}")]
+    UnsupportedVersion { version: u16 },
+    #[error("unexpected end of bytecode")]
+    UnexpectedEof,
+    #[error("invalid literal tag {tag}")]
+    InvalidLiteralTag { tag: u8 },
+    #[error("invalid opcode {opcode}")]
+    InvalidOpcode { opcode: u8 },
+    #[error("invalid cast target {target}")]
+    InvalidCastTarget { target: u16 },
+    #[error("operand {operand} is out of range")]
+    OperandOutOfRange { operand: u16 },
+    #[error("register r{register} is out of range")]
+    InvalidRegister { register: Register },
+    #[error("literal {literal} does not exist")]
+    InvalidLiteral { literal: LiteralId },
+    #[error("function {function} does not exist")]
+    InvalidFunction { function: FunctionId },
+    #[error("jump {offset:+} at {ip} lands outside the code")]
+    InvalidJump { ip: usize, offset: JumpOffset },
+    #[error("function name is not valid utf-8")]
+    InvalidUtf8,
+    #[error("{count} trailing bytes after program")]
+    TrailingBytes { count: usize },
+}
+
+impl CompiledProgram {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.literals.len() as u32).to_le_bytes());
+        for literal in &self.literals {
+            write_literal(&mut bytes, literal);
+        }
+
+        bytes.extend_from_slice(&(self.functions.len() as u32).to_le_bytes());
+        for function in &self.functions {
+            write_str(&mut bytes, &function.name);
+            bytes.extend_from_slice(&function.register_count.to_le_bytes());
+            write_code(&mut bytes, &function.code);
+        }
+
+        write_code(&mut bytes, &self.global_code);
+        bytes.extend_from_slice(&self.global_register_count.to_le_bytes());
+
+        match self.global_result {
+            Some(register) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&register.to_le_bytes());
+            }
+            None => bytes.extend_from_slice(&[0, 0, 0]),
+        }
+
+        bytes.extend_from_slice(&(self.global_symbols.len() as u32).to_le_bytes());
+        for (name, register) in &self.global_symbols {
+            write_str(&mut bytes, name);
+            bytes.extend_from_slice(&register.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BytecodeError> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(BytecodeError::InvalidMagic);
+        }
+
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(BytecodeError::UnsupportedVersion { version });
+        }
+
+        let literal_count = reader.u32()?;
+        let literals = (0..literal_count)
+            .map(|_| reader.literal())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let function_count = reader.u32()?;
+        let functions = (0..function_count)
+            .map(|_| {
+                Ok(Function {
+                    name: reader.string()?,
+                    register_count: reader.u16()?,
+                    code: reader.code()?,
+                })
+            })
+            .collect::<Result<Vec<_>, BytecodeError>>()?;
+
+        let global_code = reader.code()?;
+        let global_register_count = reader.u16()?;
+        let global_result = match (reader.u8()?, reader.u16()?) {
+            (0, _) => None,
+            (_, register) => Some(register),
+        };
+
+        let global_symbol_count = reader.u32()?;
+        let global_symbols = (0..global_symbol_count)
+            .map(|_| Ok((reader.string()?, reader.u16()?)))
+            .collect::<Result<_, BytecodeError>>()?;
+
+        if !reader.bytes.is_empty() {
+            return Err(BytecodeError::TrailingBytes {
+                count: reader.bytes.len(),
+            });
+        }
+
+        let program = Self {
+            functions,
+            global_code,
+            global_register_count,
+            literals,
+            global_result,
+            global_symbols,
+        };
+        program.validate()?;
+
+        Ok(program)
+    }
+
+    // the vm indexes straight into registers, literals and functions, a corrupt
+    // file has to be caught here rather than panic halfway through a run
+    fn validate(&self) -> Result<(), BytecodeError> {
+        for function in &self.functions {
+            self.validate_code(&function.code, function.register_count)?;
+        }
+        self.validate_code(&self.global_code, self.global_register_count)?;
+
+        let globals = self
+            .global_result
+            .iter()
+            .chain(self.global_symbols.values());
+        for register in globals {
+            check_register(*register, self.global_register_count)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_code(
+        &self,
+        code: &[Instruction],
+        register_count: Register,
+    ) -> Result<(), BytecodeError> {
+        for (ip, instruction) in code.iter().enumerate() {
+            match *instruction {
+                Instruction::LoadLiteral { src, .. } if src as usize >= self.literals.len() => {
+                    return Err(BytecodeError::InvalidLiteral { literal: src })
+                }
+                Instruction::LoadFunction { src, .. } if src as usize >= self.functions.len() => {
+                    return Err(BytecodeError::InvalidFunction { function: src })
+                }
+                // arguments sit in the registers just before the callee
+                Instruction::CallFunction { src, arg_count, .. }
+                | Instruction::CallNativeFunction { src, arg_count, .. }
+                    if src < arg_count as Register =>
+                {
+                    return Err(BytecodeError::InvalidRegister { register: src })
+                }
+                // landing just past the end finishes the function
+                Instruction::JumpIfFalse { offset, .. }
+                | Instruction::JumpIfTrue { offset, .. }
+                | Instruction::Jump { offset }
+                    if ip
+                        .checked_add_signed(offset as isize)
+                        .is_none_or(|target| target > code.len()) =>
+                {
+                    return Err(BytecodeError::InvalidJump { ip, offset })
+                }
+                _ => {}
+            }
+
+            for register in registers(instruction) {
+                check_register(register, register_count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn check_register(register: Register, register_count: Register) -> Result<(), BytecodeError> {
+    if register >= register_count {
+        return Err(BytecodeError::InvalidRegister { register });
+    }
+
+    Ok(())
+}
+
+// every register an instruction reads or writes
+fn registers(instruction: &Instruction) -> Vec<Register> {
+    match *instruction {
+        Instruction::Copy { dest, src }
+        | Instruction::ArrayLength { dest, src }
+        | Instruction::TypeOf { dest, src }
+        | Instruction::IntToFloat { dest, src }
+        | Instruction::FloatToInt { dest, src }
+        | Instruction::Cast { dest, src, .. }
+        | Instruction::PrefixNot { dest, rhs: src }
+        | Instruction::PrefixSub { dest, rhs: src }
+        | Instruction::BitNot { dest, rhs: src }
+        | Instruction::PopArray { dest, array: src }
+        | Instruction::SpreadObject { object: dest, src }
+        | Instruction::AppendArray {
+            array: dest,
+            value: src,
+        } => vec![dest, src],
+        Instruction::LoadFunction { dest, .. }
+        | Instruction::LoadLiteral { dest, .. }
+        | Instruction::LoadNil { dest }
+        | Instruction::AllocateObject { dest }
+        | Instruction::AllocateArray { dest }
+        | Instruction::Return { val: dest }
+        | Instruction::Increment { reg: dest }
+        | Instruction::Decrement { reg: dest }
+        | Instruction::JumpIfFalse { src: dest, .. }
+        | Instruction::JumpIfTrue { src: dest, .. } => vec![dest],
+        Instruction::CallNativeFunction {
+            src, return_val, ..
+        }
+        | Instruction::CallFunction {
+            src, return_val, ..
+        } => vec![src, return_val],
+        Instruction::SetObjectField {
+            object: a,
+            field: b,
+            value: c,
+        }
+        | Instruction::GetObjectField {
+            object: a,
+            field: b,
+            return_val: c,
+        }
+        | Instruction::SetArrayIndex {
+            array: a,
+            index: b,
+            value: c,
+        }
+        | Instruction::GetArrayIndex {
+            array: a,
+            index: b,
+            return_val: c,
+        }
+        | Instruction::Add {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::Sub {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::Mul {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::Div {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::Modulo {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::StringConcat {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::Equals {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::NotEquals {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::GreaterThan {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::GreaterThanOrEquals {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::LessThan {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::LessThanOrEquals {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::BitAnd {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::BitOr {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::BitXor {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::Shl {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        }
+        | Instruction::Shr {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        } => vec![a, b, c],
+        // the end of the slice is in the register after the start
+        Instruction::SliceArray { dest, array, start } => {
+            vec![dest, array, start, start.saturating_add(1)]
+        }
+        Instruction::Jump { .. } | Instruction::FunctionReturn => vec![],
+    }
+}
+
+fn write_str(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+fn write_literal(bytes: &mut Vec<u8>, literal: &Literal) {
+    match literal {
+        Literal::String(s) => {
+            bytes.push(LITERAL_STRING);
+            write_str(bytes, s);
+        }
+        Literal::Float(f) => {
+            bytes.push(LITERAL_FLOAT);
+            bytes.extend_from_slice(&f.to_le_bytes());
+        }
+        Literal::Integer(i) => {
+            bytes.push(LITERAL_INTEGER);
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+        Literal::Boolean(b) => bytes.extend_from_slice(&[LITERAL_BOOLEAN, *b as u8]),
+        Literal::Nil => bytes.push(LITERAL_NIL),
+    }
+}
+
+fn write_code(bytes: &mut Vec<u8>, code: &[Instruction]) {
+    bytes.extend_from_slice(&(code.len() as u32).to_le_bytes());
+    for instruction in code {
+        bytes.extend_from_slice(&encode(instruction));
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], BytecodeError> {
+        if self.bytes.len() < count {
+            return Err(BytecodeError::UnexpectedEof);
+        }
+
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BytecodeError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, BytecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, BytecodeError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, BytecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String, BytecodeError> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| BytecodeError::InvalidUtf8)
+    }
+
+    fn literal(&mut self) -> Result<Literal, BytecodeError> {
+        match self.u8()? {
+            LITERAL_STRING => Ok(Literal::String(self.string()?)),
+            LITERAL_FLOAT => Ok(Literal::Float(f64::from_le_bytes(self.array()?))),
+            LITERAL_INTEGER => Ok(Literal::Integer(i64::from_le_bytes(self.array()?))),
+            LITERAL_BOOLEAN => Ok(Literal::Boolean(self.u8()? != 0)),
+            LITERAL_NIL => Ok(Literal::Nil),
+            tag => Err(BytecodeError::InvalidLiteralTag { tag }),
+        }
+    }
+
+    fn code(&mut self) -> Result<Vec<Instruction>, BytecodeError> {
+        let count = self.u32()?;
+        (0..count).map(|_| decode(self.array()?)).collect()
+    }
+}
+
+fn wide(value: [u8; 2]) -> u16 {
+    u16::from_le_bytes(value)
+}
+
+// opcode followed by three 16 bit operands, 8 bit operands are widened
+fn pack(opcode: u8, a: u16, b: u16, c: u16) -> [u8; INSTRUCTION_SIZE] {
+    let [a0, a1] = a.to_le_bytes();
+    let [b0, b1] = b.to_le_bytes();
+    let [c0, c1] = c.to_le_bytes();
+
+    [opcode, a0, a1, b0, b1, c0, c1]
+}
+
+// jump offsets are 32 bits, split over the last two operands
+fn pack_jump(opcode: u8, src: u16, offset: JumpOffset) -> [u8; INSTRUCTION_SIZE] {
+    let offset = offset as u32;
+    pack(opcode, src, offset as u16, (offset >> 16) as u16)
+}
+
+fn narrow(operand: u16) -> Result<u8, BytecodeError> {
+    operand
+        .try_into()
+        .map_err(|_| BytecodeError::OperandOutOfRange { operand })
+}
+
+pub fn encode(instruction: &Instruction) -> [u8; INSTRUCTION_SIZE] {
+    match *instruction {
+        Instruction::Copy { dest, src } => pack(0, dest, src, 0),
+        Instruction::LoadFunction { dest, src } => pack(1, dest, src, 0),
+        Instruction::CallNativeFunction {
+            src,
+            arg_count,
+            return_val,
+        } => pack(2, src, arg_count.into(), return_val),
+        Instruction::CallFunction {
+            src,
+            arg_count,
+            return_val,
+        } => pack(3, src, arg_count.into(), return_val),
+        Instruction::AllocateObject { dest } => pack(4, dest, 0, 0),
+        Instruction::AllocateArray { dest } => pack(5, dest, 0, 0),
+        Instruction::SetObjectField {
+            object,
+            field,
+            value,
+        } => pack(6, object, field, value),
+        Instruction::GetObjectField {
+            object,
+            field,
+            return_val,
+        } => pack(7, object, field, return_val),
+        Instruction::SetArrayIndex {
+            array,
+            index,
+            value,
+        } => pack(8, array, index, value),
+        Instruction::GetArrayIndex {
+            array,
+            index,
+            return_val,
+        } => pack(9, array, index, return_val),
+        Instruction::ArrayLength { dest, src } => pack(10, dest, src, 0),
+        Instruction::AppendArray { array, value } => pack(11, array, value, 0),
+        Instruction::PopArray { dest, array } => pack(12, dest, array, 0),
+        Instruction::TypeOf { dest, src } => pack(13, dest, src, 0),
+        Instruction::Cast {
+            dest,
+            src,
+            target_type,
+        } => pack(14, dest, src, target_type as u16),
+        Instruction::LoadLiteral { dest, src } => pack(15, dest, src, 0),
+        Instruction::LoadNil { dest } => pack(16, dest, 0, 0),
+        Instruction::PrefixNot { dest, rhs } => pack(17, dest, rhs, 0),
+        Instruction::PrefixSub { dest, rhs } => pack(18, dest, rhs, 0),
+        Instruction::JumpIfFalse { src, offset } => pack_jump(19, src, offset),
+        Instruction::JumpIfTrue { src, offset } => pack_jump(20, src, offset),
+        Instruction::Jump { offset } => pack_jump(21, 0, offset),
+        Instruction::Add { dest, lhs, rhs } => pack(22, dest, lhs, rhs),
+        Instruction::Sub { dest, lhs, rhs } => pack(23, dest, lhs, rhs),
+        Instruction::Mul { dest, lhs, rhs } => pack(24, dest, lhs, rhs),
+        Instruction::Div { dest, lhs, rhs } => pack(25, dest, lhs, rhs),
+        Instruction::Modulo { dest, lhs, rhs } => pack(26, dest, lhs, rhs),
+        Instruction::StringConcat { dest, lhs, rhs } => pack(27, dest, lhs, rhs),
+        Instruction::Equals { dest, lhs, rhs } => pack(28, dest, lhs, rhs),
+        Instruction::NotEquals { dest, lhs, rhs } => pack(29, dest, lhs, rhs),
+        Instruction::GreaterThan { dest, lhs, rhs } => pack(30, dest, lhs, rhs),
+        Instruction::GreaterThanOrEquals { dest, lhs, rhs } => pack(31, dest, lhs, rhs),
+        Instruction::LessThan { dest, lhs, rhs } => pack(32, dest, lhs, rhs),
+        Instruction::LessThanOrEquals { dest, lhs, rhs } => pack(33, dest, lhs, rhs),
+        Instruction::BitAnd { dest, lhs, rhs } => pack(34, dest, lhs, rhs),
+        Instruction::BitOr { dest, lhs, rhs } => pack(35, dest, lhs, rhs),
+        Instruction::BitXor { dest, lhs, rhs } => pack(36, dest, lhs, rhs),
+        Instruction::BitNot { dest, rhs } => pack(37, dest, rhs, 0),
+        Instruction::Shl { dest, lhs, rhs } => pack(38, dest, lhs, rhs),
+        Instruction::Shr { dest, lhs, rhs } => pack(39, dest, lhs, rhs),
+        Instruction::Return { val } => pack(40, val, 0, 0),
+        Instruction::FunctionReturn => pack(41, 0, 0, 0),
+        Instruction::Increment { reg } => pack(42, reg, 0, 0),
+        Instruction::Decrement { reg } => pack(43, reg, 0, 0),
+        Instruction::SliceArray { dest, array, start } => pack(44, dest, array, start),
+        Instruction::IntToFloat { dest, src } => pack(45, dest, src, 0),
+        Instruction::FloatToInt { dest, src } => pack(46, dest, src, 0),
+        Instruction::SpreadObject { object, src } => pack(47, object, src, 0),
+    }
+}
+
+pub fn decode(bytes: [u8; INSTRUCTION_SIZE]) -> Result<Instruction, BytecodeError> {
+    let [opcode, a0, a1, b0, b1, c0, c1] = bytes;
+    let (a, b, c) = (wide([a0, a1]), wide([b0, b1]), wide([c0, c1]));
+    let offset = (u32::from(b) | u32::from(c) << 16) as JumpOffset;
+
+    let instruction = match opcode {
+        0 => Instruction::Copy { dest: a, src: b },
+        1 => Instruction::LoadFunction { dest: a, src: b },
+        2 => Instruction::CallNativeFunction {
+            src: a,
+            arg_count: narrow(b)?,
+            return_val: c,
+        },
+        3 => Instruction::CallFunction {
+            src: a,
+            arg_count: narrow(b)?,
+            return_val: c,
+        },
+        4 => Instruction::AllocateObject { dest: a },
+        5 => Instruction::AllocateArray { dest: a },
+        6 => Instruction::SetObjectField {
+            object: a,
+            field: b,
+            value: c,
+        },
+        7 => Instruction::GetObjectField {
+            object: a,
+            field: b,
+            return_val: c,
+        },
+        8 => Instruction::SetArrayIndex {
+            array: a,
+            index: b,
+            value: c,
+        },
+        9 => Instruction::GetArrayIndex {
+            array: a,
+            index: b,
+            return_val: c,
+        },
+        10 => Instruction::ArrayLength { dest: a, src: b },
+        11 => Instruction::AppendArray { array: a, value: b },
+        12 => Instruction::PopArray { dest: a, array: b },
+        13 => Instruction::TypeOf { dest: a, src: b },
+        14 => Instruction::Cast {
+            dest: a,
+            src: b,
+            target_type: match c {
+                0 => CastTarget::Int,
+                1 => CastTarget::Float,
+                2 => CastTarget::String,
+                3 => CastTarget::Bool,
+                target => return Err(BytecodeError::InvalidCastTarget { target }),
+            },
+        },
+        15 => Instruction::LoadLiteral { dest: a, src: b },
+        16 => Instruction::LoadNil { dest: a },
+        17 => Instruction::PrefixNot { dest: a, rhs: b },
+        18 => Instruction::PrefixSub { dest: a, rhs: b },
+        19 => Instruction::JumpIfFalse { src: a, offset },
+        20 => Instruction::JumpIfTrue { src: a, offset },
+        21 => Instruction::Jump { offset },
+        22 => Instruction::Add {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        23 => Instruction::Sub {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        24 => Instruction::Mul {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        25 => Instruction::Div {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        26 => Instruction::Modulo {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        27 => Instruction::StringConcat {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        28 => Instruction::Equals {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        29 => Instruction::NotEquals {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        30 => Instruction::GreaterThan {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        31 => Instruction::GreaterThanOrEquals {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        32 => Instruction::LessThan {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        33 => Instruction::LessThanOrEquals {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        34 => Instruction::BitAnd {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        35 => Instruction::BitOr {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        36 => Instruction::BitXor {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        37 => Instruction::BitNot { dest: a, rhs: b },
+        38 => Instruction::Shl {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        39 => Instruction::Shr {
+            dest: a,
+            lhs: b,
+            rhs: c,
+        },
+        40 => Instruction::Return { val: a },
+        41 => Instruction::FunctionReturn,
+        42 => Instruction::Increment { reg: a },
+        43 => Instruction::Decrement { reg: a },
+        44 => Instruction::SliceArray {
+            dest: a,
+            array: b,
+            start: c,
+        },
+        45 => Instruction::IntToFloat { dest: a, src: b },
+        46 => Instruction::FloatToInt { dest: a, src: b },
+        47 => Instruction::SpreadObject { object: a, src: b },
+        opcode => return Err(BytecodeError::InvalidOpcode { opcode }),
+    };
+
+    Ok(instruction)
+}
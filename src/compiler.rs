@@ -1,12 +1,21 @@
 use crate::{
     ast::{self, Expression, Statement},
     error::DiagnosticEmitted,
-    instructions::{FunctionId, Instruction, JumpOffset, LiteralId, Register},
+    instructions::{CastTarget, FunctionId, Instruction, JumpOffset, LiteralId, Register},
     scope::{Scope, ScopeType},
     types::Literal,
 };
 use codespan_reporting::{diagnostic::Diagnostic, files::Files, term::termcolor::StandardStream};
-use std::{cell::RefCell, fmt::Display};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+// offset left on a break's jump until the enclosing loop knows where it ends
+const BREAK_PLACEHOLDER: JumpOffset = JumpOffset::MIN;
+// same for continue, which goes to wherever the loop steps
+const CONTINUE_PLACEHOLDER: JumpOffset = JumpOffset::MIN + 1;
 
 #[derive(Debug, thiserror::Error)]
 pub enum CompilerError {
@@ -14,19 +23,23 @@ pub enum CompilerError {
     Diagnostic(Diagnostic<usize>),
 }
 
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, PartialEq, Hash)]
 pub struct CompiledProgram {
     pub functions: Vec<Function>,
     pub global_code: Vec<Instruction>,
-    pub global_register_count: u8,
+    pub global_register_count: Register,
     pub literals: Vec<Literal>,
+    // register holding the value of the last top level expression, if the program ends in one
+    pub global_result: Option<Register>,
+    // register of each top level variable, so the host can get at them
+    pub global_symbols: BTreeMap<String, Register>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Hash)]
 pub struct Function {
     pub name: String,
     pub code: Vec<Instruction>,
-    pub register_count: u8,
+    pub register_count: Register,
 }
 
 impl Display for Function {
@@ -44,10 +57,37 @@ impl Display for Function {
 pub struct Compiler {
     scope_stack: Vec<Scope>,
     next_available_register: Register,
+    // blocks hand their registers back, this is the most in use at once
+    register_count: Register,
     functions: Vec<Function>,
     literals: Vec<Literal>,
     // FIXME: probably doesn't need to be a RefCell
     bytecode: RefCell<Vec<Instruction>>,
+    // result register of each enclosing loop, allocated by the first `break value;`
+    loop_results: Vec<Option<Register>>,
+    // fixed parameter count of each variadic function, by index into functions
+    variadic_functions: HashMap<usize, usize>,
+    // default value of each parameter, for functions that have any
+    default_arguments: HashMap<usize, Vec<Option<Literal>>>,
+    // slots taken up front for top level functions, so they can be called before they're defined
+    declared_functions: HashMap<String, usize>,
+    // whether each enclosing function is declared to return a float
+    float_returns: Vec<bool>,
+}
+
+// we don't have types here, only catches what is obviously a string
+// anything else goes through Add which handles strings at runtime
+fn is_string_expression(expr: &ast::Expression) -> bool {
+    match expr {
+        ast::Expression::Literal(Literal::String(_)) => true,
+        ast::Expression::Infix {
+            op: ast::Operator::Plus,
+            lhs,
+            rhs,
+        } => is_string_expression(lhs) || is_string_expression(rhs),
+        ast::Expression::Cast { type_name, .. } => type_name == "string",
+        _ => false,
+    }
 }
 
 impl Default for Compiler {
@@ -56,6 +96,34 @@ impl Default for Compiler {
     }
 }
 
+// ints are widened to floats wherever a float is written down, the typechecker allows it there
+fn is_float_annotation(type_name: Option<&ast::TypeName>) -> bool {
+    type_name.is_some_and(|t| matches!(t.name.as_str(), "float" | "float?"))
+}
+
+// `x = x + 1`, `x = 1 + x` and `x = x - 1` update the register in place, `x += 1` parses as the first
+fn counter_step(name: &str, value: &Expression, reg: Register) -> Option<Instruction> {
+    let Expression::Infix { op, lhs, rhs } = value else {
+        return None;
+    };
+
+    let is_counter = |expr: &Expression| matches!(expr, Expression::Variable(v) if v == name);
+    let is_one = |expr: &Expression| matches!(expr, Expression::Literal(Literal::Integer(1)));
+
+    match op {
+        ast::Operator::Plus if is_counter(lhs) && is_one(rhs) => {
+            Some(Instruction::Increment { reg })
+        }
+        ast::Operator::Plus if is_one(lhs) && is_counter(rhs) => {
+            Some(Instruction::Increment { reg })
+        }
+        ast::Operator::Minus if is_counter(lhs) && is_one(rhs) => {
+            Some(Instruction::Decrement { reg })
+        }
+        _ => None,
+    }
+}
+
 impl Compiler {
     pub fn new() -> Self {
         let bytecode = Vec::new().into();
@@ -63,8 +131,14 @@ impl Compiler {
             scope_stack: vec![Scope::new(ScopeType::Global)],
             literals: vec![],
             next_available_register: 1,
+            register_count: 1,
             functions: Default::default(),
             bytecode,
+            loop_results: Default::default(),
+            variadic_functions: Default::default(),
+            default_arguments: Default::default(),
+            declared_functions: Default::default(),
+            float_returns: Default::default(),
         }
     }
 
@@ -73,21 +147,42 @@ impl Compiler {
         statements: &Vec<Statement>,
     ) -> Result<CompiledProgram, CompilerError> {
         for statement in statements {
-            self.compile_statement(statement)?;
+            if let Statement::Function(func) = statement {
+                if !self.declared_functions.contains_key(&func.name) {
+                    let function_id = self.declare_function(func);
+                    self.declared_functions
+                        .insert(func.name.to_owned(), function_id);
+                }
+            }
         }
 
-        let global_register_count = self.next_available_register;
+        let mut global_result = None;
+        for statement in statements {
+            global_result = match statement {
+                Statement::Expression(expr) => Some(self.compile_expression(expr)?),
+                Statement::Loop { body } => self.compile_loop(body)?,
+                statement => {
+                    self.compile_statement(statement)?;
+                    None
+                }
+            };
+        }
+
+        let global_register_count = self.register_count;
+        let global_symbols = self.scope_stack[0].registers();
 
         Ok(CompiledProgram {
             functions: self.functions,
             global_code: self.bytecode.into_inner(),
             global_register_count,
             literals: self.literals,
+            global_result,
+            global_symbols,
         })
     }
 
     pub fn compile_and_emit_diagnostics<'a, T>(
-        mut self,
+        self,
         statements: &Vec<Statement>,
         writer: &StandardStream,
         config: &codespan_reporting::term::Config,
@@ -96,25 +191,15 @@ impl Compiler {
     where
         T: Files<'a, FileId = usize> + 'a,
     {
-        for statement in statements {
-            let statement = self.compile_statement(statement);
-            if let Err(e) = statement {
-                let CompilerError::Diagnostic(diagnostic) = e;
+        match self.compile(statements) {
+            Ok(program) => Ok(program),
+            Err(CompilerError::Diagnostic(diagnostic)) => {
                 codespan_reporting::term::emit(&mut writer.lock(), config, files, &diagnostic)?;
 
                 // cause statuscode to be set
-                return Err(DiagnosticEmitted.into());
+                Err(DiagnosticEmitted.into())
             }
         }
-
-        let global_register_count = self.next_available_register;
-
-        Ok(CompiledProgram {
-            functions: self.functions,
-            global_code: self.bytecode.into_inner(),
-            global_register_count,
-            literals: self.literals,
-        })
     }
 
     fn add_scope(&mut self) {
@@ -136,63 +221,163 @@ impl Compiler {
         current_scope.define_function(name);
     }
 
-    fn can_mutate_variable(&mut self, name: &str) -> bool {
-        let scope_stack = &mut self.scope_stack.iter().rev();
-        for v in scope_stack {
-            if v.get_register_for(name).is_some() {
-                return v.is_mutable(name).is_some_and(|m| m);
-            }
-        }
-
-        false
+    // the closest variable with this name decides, a `let mut` can shadow a `let`
+    fn can_mutate_variable(&self, name: &str) -> bool {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find_map(|scope| scope.is_mutable(name))
+            .unwrap_or(false)
     }
 
-    fn resolve(&mut self, name: &str) -> Option<Register> {
-        let scope_stack = &mut self.scope_stack.iter().rev();
-        for v in scope_stack {
-            if let Some(reg) = v.get_register_for(name) {
-                return Some(reg);
-            }
-        }
+    fn resolve(&self, name: &str) -> Option<Register> {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get_register_for(name))
+    }
 
-        None
+    // the folded value of the closest variable with this name, if that's a const
+    fn resolve_constant(&self, name: &str) -> Option<Literal> {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find(|scope| scope.get_register_for(name).is_some())
+            .and_then(|scope| scope.get_constant(name))
     }
 
-    fn resolve_function(&mut self, name: &str) -> bool {
-        let scope_stack = &mut self.scope_stack.iter().rev();
-        for v in scope_stack {
-            if v.contains_function(name) {
-                return true;
+    // works out a const at compile time, anything the vm might do differently
+    // (overflow, dividing by zero, ...) is left for the vm
+    fn fold_constant(&self, expression: &Expression) -> Option<Literal> {
+        match expression {
+            Expression::Literal(literal) => Some(literal.clone()),
+            Expression::Variable(name) => self.resolve_constant(name),
+            Expression::Prefix {
+                op: ast::Operator::Minus,
+                expr,
+            } => match self.fold_constant(expr)? {
+                Literal::Integer(n) => n.checked_neg().map(Literal::Integer),
+                Literal::Float(n) => Some(Literal::Float(-n)),
+                _ => None,
+            },
+            Expression::Infix { op, lhs, rhs } => {
+                let lhs = self.fold_constant(lhs)?;
+                let rhs = self.fold_constant(rhs)?;
+
+                match (op, lhs, rhs) {
+                    (ast::Operator::Plus, Literal::String(lhs), Literal::String(rhs)) => {
+                        Some(Literal::String(lhs + &rhs))
+                    }
+                    (op, Literal::Integer(lhs), Literal::Integer(rhs)) => match op {
+                        ast::Operator::Plus => lhs.checked_add(rhs),
+                        ast::Operator::Minus => lhs.checked_sub(rhs),
+                        ast::Operator::Multiply => lhs.checked_mul(rhs),
+                        ast::Operator::Divide => lhs.checked_div(rhs),
+                        _ => None,
+                    }
+                    .map(Literal::Integer),
+                    (op, lhs, rhs) => {
+                        let (lhs, rhs) = match (lhs, rhs) {
+                            (Literal::Float(lhs), Literal::Float(rhs)) => (lhs, rhs),
+                            (Literal::Float(lhs), Literal::Integer(rhs)) => (lhs, rhs as f64),
+                            (Literal::Integer(lhs), Literal::Float(rhs)) => (lhs as f64, rhs),
+                            _ => return None,
+                        };
+
+                        match op {
+                            ast::Operator::Plus => Some(lhs + rhs),
+                            ast::Operator::Minus => Some(lhs - rhs),
+                            ast::Operator::Multiply => Some(lhs * rhs),
+                            ast::Operator::Divide => Some(lhs / rhs),
+                            _ => None,
+                        }
+                        .map(Literal::Float)
+                    }
+                }
             }
+            _ => None,
         }
-        false
+    }
+
+    fn resolve_function(&self, name: &str) -> bool {
+        self.scope_stack
+            .iter()
+            .rev()
+            .any(|scope| scope.contains_function(name))
     }
 
     fn remove_scope(&mut self) {
         self.scope_stack.pop();
     }
 
-    fn get_register(&mut self) -> Register {
+    fn get_register(&mut self) -> Result<Register, CompilerError> {
         let reg = self.next_available_register;
-        self.next_available_register += 1;
-        reg
+        self.next_available_register = reg.checked_add(1).ok_or_else(|| {
+            let diagnostic = Diagnostic::error()
+                .with_message(format!("more than {} registers required", Register::MAX));
+            CompilerError::Diagnostic(diagnostic)
+        })?;
+        self.register_count = self.register_count.max(self.next_available_register);
+
+        Ok(reg)
+    }
+
+    // the slot is taken before the body is compiled so the function can be called recursively
+    fn declare_function(&mut self, func: &ast::Function) -> usize {
+        self.define_function_current_scope(&func.name);
+
+        let function_id = self.functions.len();
+        self.functions.push(Function {
+            name: func.name.to_owned(),
+            code: Vec::new(),
+            register_count: 0,
+        });
+
+        if let Some(fixed) = func.parameters.iter().position(|p| p.is_variadic) {
+            self.variadic_functions.insert(function_id, fixed);
+        }
+
+        if func.parameters.iter().any(|p| p.default_value.is_some()) {
+            let defaults = func.parameters.iter().map(|p| p.default_value.clone());
+            self.default_arguments
+                .insert(function_id, defaults.collect());
+        }
+
+        function_id
     }
 
     fn compile_function(&mut self, func: &ast::Function) -> Result<(), CompilerError> {
-        let prev_register_count = self.next_available_register;
+        let prev_next_register = self.next_available_register;
+        let prev_register_count = self.register_count;
         self.next_available_register = 1;
+        self.register_count = 1;
+
+        let declared = match self.scope_stack.len() {
+            1 => self.declared_functions.remove(&func.name),
+            _ => None,
+        };
+        let function_id = declared.unwrap_or_else(|| self.declare_function(func));
 
-        self.define_function_current_scope(&func.name);
         self.add_scope();
         let prev_code = self.bytecode.replace(Vec::new());
 
         for param in &func.parameters {
-            self.define_immutable_current_scope(&param.name, self.next_available_register);
-            self.next_available_register += 1;
+            let register = self.get_register()?;
+            self.define_immutable_current_scope(&param.name, register);
+
+            if !param.is_variadic && is_float_annotation(Some(&param.type_name)) {
+                self.bytecode.borrow_mut().push(Instruction::IntToFloat {
+                    dest: register,
+                    src: register,
+                });
+            }
         }
 
+        self.float_returns
+            .push(is_float_annotation(func.return_type_name.as_ref()));
+
         match *func.body {
-            Statement::Block { ref body } => {
+            Statement::Block { ref body, .. } => {
                 for statement in body {
                     self.compile_statement(statement)?;
                 }
@@ -204,30 +389,86 @@ impl Compiler {
             }
         }
 
+        self.float_returns.pop();
         self.bytecode.borrow_mut().push(Instruction::FunctionReturn);
 
         let function_code = self.bytecode.replace(prev_code);
-        let used_registers = self.next_available_register;
 
-        self.functions.push(Function {
-            name: func.name.to_owned(),
-            code: function_code,
-            register_count: used_registers,
-        });
+        let function = &mut self.functions[function_id];
+        function.code = function_code;
+        function.register_count = self.register_count;
 
         self.remove_scope();
-        self.next_available_register = prev_register_count;
+        self.next_available_register = prev_next_register;
+        self.register_count = prev_register_count;
 
         Ok(())
     }
 
+    fn compile_array(
+        &mut self,
+        values: &[ast::Expression],
+        dest: Option<Register>,
+    ) -> Result<Register, CompilerError> {
+        let reg = self.register_or_new(dest)?;
+
+        let instruction = Instruction::AllocateArray { dest: reg };
+        self.bytecode.borrow_mut().push(instruction);
+
+        for (i, value) in values.iter().enumerate() {
+            let index =
+                self.compile_expression(&Expression::Literal(Literal::Integer(i as i64)))?;
+            let value = self.compile_expression(value)?;
+
+            let instruction = Instruction::SetArrayIndex {
+                array: reg,
+                index,
+                value,
+            };
+
+            self.bytecode.borrow_mut().push(instruction);
+        }
+
+        Ok(reg)
+    }
+
     fn compile_let(
         &mut self,
         name: &str,
         value: &ast::Expression,
         is_mutable: bool,
+        type_name: Option<&ast::TypeName>,
     ) -> Result<(), CompilerError> {
-        let expression_value_register = self.compile_expression(value)?;
+        if is_float_annotation(type_name) {
+            let src = self.compile_expression(value)?;
+            let dest = self.get_register()?;
+            self.bytecode
+                .borrow_mut()
+                .push(Instruction::IntToFloat { dest, src });
+
+            if is_mutable {
+                self.define_mutable_current_scope(name, dest);
+            } else {
+                self.define_immutable_current_scope(name, dest);
+            }
+
+            return Ok(());
+        }
+
+        // a new binding is a new value, sharing a register with a variable that
+        // can change would let writes through one show up in the other
+        let aliases_mutable = match value {
+            Expression::Variable(source) => is_mutable || self.can_mutate_variable(source),
+            _ => false,
+        };
+
+        let expression_value_register = if aliases_mutable {
+            let dest = self.get_register()?;
+            self.compile_expression_with_dest(value, Some(dest))?
+        } else {
+            self.compile_expression(value)?
+        };
+
         if is_mutable {
             self.define_mutable_current_scope(name, expression_value_register);
         } else {
@@ -244,8 +485,13 @@ impl Compiler {
     ) -> Result<(), CompilerError> {
         let can_mutate = self.can_mutate_variable(name);
         if can_mutate {
-            let expression_value_register = self.compile_expression(value)?;
             let mutable_value_register = self.resolve(name).unwrap();
+            if let Some(instruction) = counter_step(name, value, mutable_value_register) {
+                self.bytecode.borrow_mut().push(instruction);
+                return Ok(());
+            }
+
+            let expression_value_register = self.compile_expression(value)?;
 
             let instruction = Instruction::Copy {
                 dest: mutable_value_register,
@@ -253,8 +499,6 @@ impl Compiler {
             };
 
             self.bytecode.borrow_mut().push(instruction);
-            // ???
-            self.define_mutable_current_scope(name, mutable_value_register);
 
             Ok(())
         } else {
@@ -265,18 +509,33 @@ impl Compiler {
     }
 
     fn compile_expression(&mut self, expr: &ast::Expression) -> Result<Register, CompilerError> {
+        self.compile_expression_with_dest(expr, None)
+    }
+
+    fn register_or_new(&mut self, dest: Option<Register>) -> Result<Register, CompilerError> {
+        dest.map_or_else(|| self.get_register(), Ok)
+    }
+
+    // with a dest the result is written straight into it, saves a copy when the
+    // caller needs the value in a specific register, e.g. call arguments
+    fn compile_expression_with_dest(
+        &mut self,
+        expr: &ast::Expression,
+        dest: Option<Register>,
+    ) -> Result<Register, CompilerError> {
         // FIXME: potentially wasting registers
         match expr {
             ast::Expression::Prefix { op, expr } => {
                 let rhs = self.compile_expression(expr)?;
-                let dest = self.get_register();
+                let dest = self.register_or_new(dest)?;
 
                 let instruction = match op {
                     ast::Operator::Minus => Instruction::PrefixSub { dest, rhs },
                     ast::Operator::Not => Instruction::PrefixNot { dest, rhs },
+                    ast::Operator::BitNot => Instruction::BitNot { dest, rhs },
                     _ => {
                         let diagnostic = Diagnostic::error()
-                            .with_message("prefix expression only works for '-' and '!'");
+                            .with_message("prefix expression only works for '-', '!' and '~'");
 
                         return Err(CompilerError::Diagnostic(diagnostic));
                     }
@@ -286,18 +545,30 @@ impl Compiler {
 
                 Ok(dest)
             }
+            ast::Expression::Infix {
+                op: op @ (ast::Operator::And | ast::Operator::Or),
+                lhs,
+                rhs,
+            } => self.compile_short_circuit(op, lhs, rhs, dest),
             ast::Expression::Infix { op, lhs, rhs } => {
+                let is_concat = *op == ast::Operator::Plus
+                    && (is_string_expression(lhs) || is_string_expression(rhs));
+
                 let lhs = self.compile_expression(lhs)?;
                 let rhs = self.compile_expression(rhs)?;
 
-                let dest = self.get_register();
+                let dest = self.register_or_new(dest)?;
 
                 let instruction = match op {
+                    ast::Operator::Plus if is_concat => {
+                        Instruction::StringConcat { dest, lhs, rhs }
+                    }
                     // FIXME: isn't this dumb?
                     ast::Operator::Plus => Instruction::Add { dest, lhs, rhs },
                     ast::Operator::Minus => Instruction::Sub { dest, lhs, rhs },
                     ast::Operator::Divide => Instruction::Div { dest, lhs, rhs },
                     ast::Operator::Multiply => Instruction::Mul { dest, lhs, rhs },
+                    ast::Operator::Modulo => Instruction::Modulo { dest, lhs, rhs },
                     ast::Operator::Equal => Instruction::Equals { dest, lhs, rhs },
                     ast::Operator::NotEqual => Instruction::NotEquals { dest, lhs, rhs },
                     ast::Operator::GreaterThan => Instruction::GreaterThan { dest, lhs, rhs },
@@ -308,9 +579,15 @@ impl Compiler {
                     ast::Operator::LessThanOrEqual => {
                         Instruction::LessThanOrEquals { dest, lhs, rhs }
                     }
+                    ast::Operator::BitAnd => Instruction::BitAnd { dest, lhs, rhs },
+                    ast::Operator::BitOr => Instruction::BitOr { dest, lhs, rhs },
+                    ast::Operator::BitXor => Instruction::BitXor { dest, lhs, rhs },
+                    ast::Operator::ShiftLeft => Instruction::Shl { dest, lhs, rhs },
+                    ast::Operator::ShiftRight => Instruction::Shr { dest, lhs, rhs },
                     _ => {
-                        let diagnostic = Diagnostic::error()
-                            .with_message("infix expression only works for '+', '-', '/', '*'");
+                        let diagnostic = Diagnostic::error().with_message(
+                            "infix expression only works for '+', '-', '/', '*', '%'",
+                        );
                         return Err(CompilerError::Diagnostic(diagnostic));
                     }
                 };
@@ -319,8 +596,17 @@ impl Compiler {
 
                 Ok(dest)
             }
+            // nil doesn't need a slot in the literal table
+            ast::Expression::Literal(Literal::Nil) => {
+                let dest = self.register_or_new(dest)?;
+                self.bytecode
+                    .borrow_mut()
+                    .push(Instruction::LoadNil { dest });
+
+                Ok(dest)
+            }
             ast::Expression::Literal(lit) => {
-                let reg = self.get_register();
+                let reg = self.register_or_new(dest)?;
                 let literal_list = self.literals.iter().enumerate();
                 let mut found_id = None;
                 for (index, literal) in literal_list {
@@ -348,14 +634,28 @@ impl Compiler {
 
                 Ok(reg)
             }
-            ast::Expression::Variable(name) => self.resolve(name).ok_or_else(|| {
-                let diagnostic = Diagnostic::error()
-                    .with_message(format!("variable `{}` not found in scope", name));
-                CompilerError::Diagnostic(diagnostic)
-            }),
+            ast::Expression::Variable(name) => {
+                let src = self.resolve(name).ok_or_else(|| {
+                    let diagnostic = Diagnostic::error()
+                        .with_message(format!("variable `{}` not found in scope", name));
+                    CompilerError::Diagnostic(diagnostic)
+                })?;
+
+                match dest {
+                    Some(dest) if dest != src => {
+                        self.bytecode
+                            .borrow_mut()
+                            .push(Instruction::Copy { dest, src });
+
+                        Ok(dest)
+                    }
+                    _ => Ok(src),
+                }
+            }
             ast::Expression::FunctionCall {
                 name: function_to_call,
                 args,
+                ..
             } => {
                 // if it exists in scope we'll get the id
                 // if we don't have the id for the function, then we'll act like its a
@@ -371,27 +671,128 @@ impl Compiler {
                     None
                 };
 
-                let mut regs = vec![];
-                for arg in args {
-                    regs.push(self.compile_expression(arg)?);
+                // len has its own instruction, unless someone defined their own
+                if found_id.is_none() && function_to_call == "len" && args.len() == 1 {
+                    let src = self.compile_expression(&args[0])?;
+                    let dest = self.register_or_new(dest)?;
+                    self.bytecode
+                        .borrow_mut()
+                        .push(Instruction::ArrayLength { dest, src });
+
+                    return Ok(dest);
+                }
+
+                // int and float are casts between numbers, the typechecker keeps anything else out
+                if found_id.is_none()
+                    && matches!(function_to_call.as_str(), "int" | "float")
+                    && args.len() == 1
+                {
+                    let src = self.compile_expression(&args[0])?;
+                    let dest = self.register_or_new(dest)?;
+                    self.bytecode
+                        .borrow_mut()
+                        .push(if function_to_call == "int" {
+                            Instruction::FloatToInt { dest, src }
+                        } else {
+                            Instruction::IntToFloat { dest, src }
+                        });
+
+                    return Ok(dest);
                 }
 
+                if found_id.is_none() && function_to_call == "type_of" && args.len() == 1 {
+                    let src = self.compile_expression(&args[0])?;
+                    let dest = self.register_or_new(dest)?;
+                    self.bytecode
+                        .borrow_mut()
+                        .push(Instruction::TypeOf { dest, src });
+
+                    return Ok(dest);
+                }
+
+                // same for push and pop
+                if found_id.is_none() && function_to_call == "push" && args.len() == 2 {
+                    let array = self.compile_expression(&args[0])?;
+                    let value = self.compile_expression(&args[1])?;
+                    self.bytecode
+                        .borrow_mut()
+                        .push(Instruction::AppendArray { array, value });
+
+                    return self.register_or_new(dest);
+                }
+
+                if found_id.is_none() && function_to_call == "pop" && args.len() == 1 {
+                    let array = self.compile_expression(&args[0])?;
+                    let dest = self.register_or_new(dest)?;
+                    self.bytecode
+                        .borrow_mut()
+                        .push(Instruction::PopArray { dest, array });
+
+                    return Ok(dest);
+                }
+
+                // the vm expects the arguments directly before the function register
+                // and moves them out when calling, so the block is reserved up front
+                // and each argument is compiled straight into its slot
+                // trailing arguments to a variadic function are passed as one array
+                let variadic = found_id
+                    .map(|f| self.functions.len() - f - 1)
+                    .and_then(|f| self.variadic_functions.get(&f).copied());
+                let (args, trailing) = match variadic {
+                    Some(fixed) => {
+                        let (args, trailing) = args.split_at(fixed.min(args.len()));
+                        (args, Some(trailing))
+                    }
+                    None => (args.as_slice(), None),
+                };
+
+                // left out arguments are filled in from the parameter defaults
+                let defaults = found_id
+                    .map(|f| self.functions.len() - f - 1)
+                    .and_then(|f| self.default_arguments.get(&f))
+                    .map(|defaults| {
+                        defaults
+                            .iter()
+                            .skip(args.len())
+                            .map_while(|d| d.clone())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+
+                let passed = args.len() + defaults.len() + trailing.map_or(0, |_| 1);
+                let arg_count: u8 = passed.try_into().map_err(|_| {
+                    let diagnostic = Diagnostic::error()
+                        .with_message(format!("too many arguments to `{function_to_call}`"));
+                    CompilerError::Diagnostic(diagnostic)
+                })?;
+
                 let start_reg = self.next_available_register;
-                for reg in regs {
-                    let dest = self.get_register();
-                    let mut current_code = self.bytecode.borrow_mut();
-                    current_code.push(Instruction::Copy { dest, src: reg });
+                for _ in 0..=arg_count {
+                    self.get_register()?;
                 }
 
-                let last_reg = self.next_available_register;
+                let last_reg = start_reg + arg_count as Register;
+                for (i, arg) in args.iter().enumerate() {
+                    self.compile_expression_with_dest(arg, Some(start_reg + i as Register))?;
+                }
+
+                let filled = args.len() + defaults.len();
+                for (i, default) in defaults.into_iter().enumerate() {
+                    self.compile_expression_with_dest(
+                        &Expression::Literal(default),
+                        Some(start_reg + (args.len() + i) as Register),
+                    )?;
+                }
+
+                // the variadic array comes after any defaults
+                if let Some(trailing) = trailing {
+                    self.compile_array(trailing, Some(start_reg + filled as Register))?;
+                }
 
                 let found_id = match found_id {
                     Some(f) => (self.functions.len() - f - 1) as FunctionId,
                     _ => {
-                        // TODO: instead of assuming it's native, we can set a placeholder
-                        //       and figure out at runtime which function to call?
-                        //       right now, we can only call functions which we've parsed
-                        //       see 'call_before_declare_function.rl' test case
+                        // top level functions are declared up front, so anything unknown by now
                         //
                         // Note: We can check if stdlib functions exist, but not at runtime with VM
                         //       defined functions.... maybe we need to require definitions earlier
@@ -399,15 +800,16 @@ impl Compiler {
 
                         // if no existing function, assume there is a native function
                         // available in the VM, this is now a runtime error if it doesn't exist
-                        let register = self.compile_expression(&Expression::Literal(
-                            Literal::String(function_to_call.to_owned()),
-                        ))?;
+                        let register = self.compile_expression_with_dest(
+                            &Expression::Literal(Literal::String(function_to_call.to_owned())),
+                            Some(last_reg),
+                        )?;
 
-                        let return_value = self.get_register();
+                        let return_value = self.register_or_new(dest)?;
 
                         let instruction = Instruction::CallNativeFunction {
                             src: register,
-                            arg_count: last_reg - start_reg,
+                            arg_count,
                             return_val: return_value,
                         };
 
@@ -417,8 +819,8 @@ impl Compiler {
                     }
                 };
 
-                let reg = self.get_register();
-                let return_value = self.get_register();
+                let reg = last_reg;
+                let return_value = self.register_or_new(dest)?;
                 let instruction = Instruction::LoadFunction {
                     dest: reg,
                     src: found_id,
@@ -428,7 +830,7 @@ impl Compiler {
 
                 let instruction = Instruction::CallFunction {
                     src: reg,
-                    arg_count: last_reg - start_reg,
+                    arg_count,
                     return_val: return_value,
                 };
 
@@ -437,22 +839,31 @@ impl Compiler {
                 Ok(return_value)
             }
             Expression::Object { fields } => {
-                let reg = self.get_register();
+                let reg = self.register_or_new(dest)?;
 
                 let instruction = Instruction::AllocateObject { dest: reg };
                 self.bytecode.borrow_mut().push(instruction);
 
-                // FIXME: use a deterministic hashmap or something instead of sorting (cave man)
-                for (name, value) in fields.iter() {
-                    let name = self.compile_expression(&Expression::Literal(Literal::String(
-                        name.to_string(),
-                    )))?;
-                    let value = self.compile_expression(value)?;
-
-                    let instruction = Instruction::SetObjectField {
-                        object: reg,
-                        field: name,
-                        value,
+                // set in the order they were written, so later fields overwrite earlier ones
+                for field in fields {
+                    let instruction = match field {
+                        ast::ObjectField::Named(name, value) => {
+                            let name = self.compile_expression(&Expression::Literal(
+                                Literal::String(name.to_string()),
+                            ))?;
+                            let value = self.compile_expression(value)?;
+
+                            Instruction::SetObjectField {
+                                object: reg,
+                                field: name,
+                                value,
+                            }
+                        }
+                        ast::ObjectField::Spread(value) => {
+                            let src = self.compile_expression(value)?;
+
+                            Instruction::SpreadObject { object: reg, src }
+                        }
                     };
 
                     self.bytecode.borrow_mut().push(instruction);
@@ -461,7 +872,7 @@ impl Compiler {
                 Ok(reg)
             }
             Expression::ObjectAccess { path } => {
-                let register = self.get_register();
+                let register = self.register_or_new(dest)?;
                 let base_obj = path.first().unwrap();
                 let mut obj_reg =
                     self.compile_expression(&Expression::Variable(base_obj.to_string()))?;
@@ -484,31 +895,10 @@ impl Compiler {
 
                 Ok(register)
             }
-            Expression::Array { this } => {
-                let reg = self.get_register();
-
-                let instruction = Instruction::AllocateArray { dest: reg };
-                self.bytecode.borrow_mut().push(instruction);
-
-                for (i, value) in this.iter().enumerate() {
-                    let index =
-                        self.compile_expression(&Expression::Literal(Literal::Integer(i as i64)))?;
-                    let value = self.compile_expression(value)?;
-
-                    let instruction = Instruction::SetArrayIndex {
-                        array: reg,
-                        index,
-                        value,
-                    };
-
-                    self.bytecode.borrow_mut().push(instruction);
-                }
-
-                Ok(reg)
-            }
+            Expression::Array { this } => self.compile_array(this, dest),
             Expression::ArrayAccess { name, index } => {
                 let index = self.compile_expression(index)?;
-                let register = self.get_register();
+                let register = self.register_or_new(dest)?;
                 let array_reg = self.compile_expression(&Expression::Variable(name.to_string()))?;
 
                 let instruction = Instruction::GetArrayIndex {
@@ -521,10 +911,96 @@ impl Compiler {
 
                 Ok(register)
             }
+            Expression::If {
+                condition,
+                body,
+                else_body,
+            } => self.compile_if_expression(condition, body, else_body.as_deref(), dest),
+            Expression::ArraySlice { name, start, end } => {
+                let start_reg = self.get_register()?;
+                let end_reg = self.get_register()?;
+                self.compile_expression_with_dest(start, Some(start_reg))?;
+                self.compile_expression_with_dest(end, Some(end_reg))?;
+
+                let register = self.register_or_new(dest)?;
+                let array_reg = self.compile_expression(&Expression::Variable(name.to_string()))?;
+
+                let instruction = Instruction::SliceArray {
+                    dest: register,
+                    array: array_reg,
+                    start: start_reg,
+                };
+
+                self.bytecode.borrow_mut().push(instruction);
+
+                Ok(register)
+            }
+            Expression::Cast { expr, type_name } => {
+                let target_type = match type_name.as_str() {
+                    "int" => CastTarget::Int,
+                    "float" => CastTarget::Float,
+                    "string" => CastTarget::String,
+                    "bool" => CastTarget::Bool,
+                    _ => {
+                        let diagnostic = Diagnostic::error()
+                            .with_message(format!("cannot cast to unknown type `{type_name}`"));
+                        return Err(CompilerError::Diagnostic(diagnostic));
+                    }
+                };
+
+                let src = self.compile_expression(expr)?;
+                let dest = self.register_or_new(dest)?;
+                self.bytecode.borrow_mut().push(Instruction::Cast {
+                    dest,
+                    src,
+                    target_type,
+                });
+
+                Ok(dest)
+            }
         }
     }
 
+    // rhs is only evaluated when lhs doesn't already decide the result
+    fn compile_short_circuit(
+        &mut self,
+        op: &ast::Operator,
+        lhs: &Expression,
+        rhs: &Expression,
+        dest: Option<Register>,
+    ) -> Result<Register, CompilerError> {
+        let lhs = self.compile_expression(lhs)?;
+        let dest = self.register_or_new(dest)?;
+        self.bytecode
+            .borrow_mut()
+            .push(Instruction::Copy { dest, src: lhs });
+
+        let old_current_code = self.bytecode.replace(Vec::new());
+
+        let rhs = self.compile_expression(rhs)?;
+        self.bytecode
+            .borrow_mut()
+            .push(Instruction::Copy { dest, src: rhs });
+
+        let mut rhs_code = self.bytecode.replace(old_current_code);
+
+        // 1 for going past the rhs
+        let offset = Self::jump_offset(rhs_code.len() as isize + 1)?;
+
+        let instruction = match op {
+            ast::Operator::Or => Instruction::JumpIfTrue { src: dest, offset },
+            _ => Instruction::JumpIfFalse { src: dest, offset },
+        };
+
+        self.bytecode.borrow_mut().push(instruction);
+        self.bytecode.borrow_mut().append(&mut rhs_code);
+
+        Ok(dest)
+    }
+
+    // lets are dropped at the end of the block, so their registers can be reused
     pub fn compile_block(&mut self, body: &Vec<Statement>) -> Result<(), CompilerError> {
+        let first_register = self.next_available_register;
         self.add_scope();
 
         for statement in body {
@@ -532,10 +1008,18 @@ impl Compiler {
         }
 
         self.remove_scope();
+        self.release_registers(first_register);
 
         Ok(())
     }
 
+    fn release_registers(&mut self, first_register: Register) {
+        // a `break value;` in here writes to a register that outlives the block
+        let loop_result_end = self.loop_results.iter().flatten().map(|r| r + 1).max();
+        self.next_available_register =
+            loop_result_end.map_or(first_register, |end| end.max(first_register));
+    }
+
     pub fn compile_if(
         &mut self,
         condition: &Expression,
@@ -552,20 +1036,11 @@ impl Compiler {
 
         let mut if_statement_body = self.bytecode.replace(old_current_code);
 
-        let offset = if else_statement.is_none() { 0 } else { 1 };
+        // 1 for going after if statement and 1 for going after jump that's might be added below
+        let offset = if else_statement.is_none() { 1 } else { 2 };
         let instruction = Instruction::JumpIfFalse {
             src: expression_value_register,
-            // FIXME: size limit...
-            offset: if_statement_body
-                .len()
-                .try_into()
-                // 1 for going after if statement and 1 for going after jump that's might be added below
-                .map(|i: u16| i + 1u16 + offset)
-                .map_err(|e| {
-                    let diagnostic =
-                        Diagnostic::error().with_message(format!("integer conversion error: {e}"));
-                    CompilerError::Diagnostic(diagnostic)
-                })?,
+            offset: Self::jump_offset(if_statement_body.len() as isize + offset)?,
         };
 
         self.bytecode.borrow_mut().push(instruction);
@@ -585,15 +1060,14 @@ impl Compiler {
                 body,
                 else_statement: next_else,
             } => self.compile_if(condition, body, next_else),
-            Statement::Block { body } => self.compile_block(body),
+            Statement::Block { body, .. } => self.compile_block(body),
             _ => unreachable!(),
         }?;
 
         let mut else_statement_body = self.bytecode.replace(old_current_code);
 
         let instruction = Instruction::Jump {
-            // ?
-            offset: else_statement_body.len() as u16 + 1,
+            offset: Self::jump_offset(else_statement_body.len() as isize + 1)?,
         };
 
         self.bytecode.borrow_mut().push(instruction);
@@ -603,68 +1077,337 @@ impl Compiler {
         Ok(())
     }
 
+    // same shape as compile_if, but both arms write into one register
+    fn compile_if_expression(
+        &mut self,
+        condition: &Expression,
+        body: &Expression,
+        else_body: Option<&Expression>,
+        dest: Option<Register>,
+    ) -> Result<Register, CompilerError> {
+        let dest = self.register_or_new(dest)?;
+        let expression_value_register = self.compile_expression(condition)?;
+
+        let old_current_code = self.bytecode.replace(Vec::new());
+        self.compile_expression_with_dest(body, Some(dest))?;
+        let mut body_code = self.bytecode.replace(Vec::new());
+
+        // nil when there's no else, the typechecker rejects that but it can be skipped
+        let nil = Expression::Literal(Literal::Nil);
+        self.compile_expression_with_dest(else_body.unwrap_or(&nil), Some(dest))?;
+        let mut else_code = self.bytecode.replace(old_current_code);
+
+        let instruction = Instruction::JumpIfFalse {
+            src: expression_value_register,
+            offset: Self::jump_offset(body_code.len() as isize + 2)?,
+        };
+        self.bytecode.borrow_mut().push(instruction);
+        self.bytecode.borrow_mut().append(&mut body_code);
+
+        let instruction = Instruction::Jump {
+            offset: Self::jump_offset(else_code.len() as isize + 1)?,
+        };
+        self.bytecode.borrow_mut().push(instruction);
+        self.bytecode.borrow_mut().append(&mut else_code);
+
+        Ok(dest)
+    }
+
     pub fn compile_return(&mut self, expression: &Expression) -> Result<(), CompilerError> {
-        let expr_register = self.compile_expression(expression)?;
+        let mut expr_register = self.compile_expression(expression)?;
+        if self.float_returns.last() == Some(&true) {
+            let dest = self.get_register()?;
+            self.bytecode.borrow_mut().push(Instruction::IntToFloat {
+                dest,
+                src: expr_register,
+            });
+            expr_register = dest;
+        }
+
         let instruction = Instruction::Return { val: expr_register };
         self.bytecode.borrow_mut().push(instruction);
 
         Ok(())
     }
 
-    pub fn compile_loop(&mut self, body: &Statement) -> Result<(), CompilerError> {
-        let bytecode_size = self.bytecode.borrow().len();
+    // returns the register holding the value the loop broke with, if any break had one
+    pub fn compile_loop(&mut self, body: &Statement) -> Result<Option<Register>, CompilerError> {
+        let loop_start = self.bytecode.borrow().len();
 
-        match body {
-            Statement::Block { body } => self.compile_block(body)?,
+        self.loop_results.push(None);
+        let compiled_body = match body {
+            Statement::Block { body, .. } => self.compile_block(body),
             _ => unreachable!(),
         };
+        let loop_result = self.loop_results.pop().flatten();
+        compiled_body?;
 
-        let body_size = self.bytecode.borrow().len() - bytecode_size;
+        // nothing to step, a continue goes straight back to the top
+        self.close_loop(loop_start, loop_start)?;
 
-        let mut bytecode = self.bytecode.borrow_mut();
-        let mut i = 0;
-        loop {
-            let offset = body_size - i + 2;
-            if i >= body_size {
-                break;
+        Ok(loop_result)
+    }
+
+    // arrays walk an index and fetch each element, ranges count with the loop variable itself
+    pub fn compile_for(
+        &mut self,
+        binding: &str,
+        iterable: &ast::Iterable,
+        body: &Statement,
+    ) -> Result<(), CompilerError> {
+        let first_register = self.next_available_register;
+        self.add_scope();
+
+        // the counter is mutated every iteration, so it can't be a variable's register
+        let counter = self.get_register()?;
+        let end = self.get_register()?;
+        let array = match iterable {
+            ast::Iterable::Array(array) => {
+                let array = self.compile_expression(array)?;
+                self.compile_expression_with_dest(
+                    &Expression::Literal(Literal::Integer(0)),
+                    Some(counter),
+                )?;
+
+                Some(array)
             }
+            ast::Iterable::Range {
+                start,
+                end: range_end,
+            } => {
+                self.compile_expression_with_dest(start, Some(counter))?;
+                self.compile_expression_with_dest(range_end, Some(end))?;
 
-            let instruction = &mut bytecode[i];
-
-            if let Instruction::Jump {
-                offset: maybe_placeholder_offset,
-            } = instruction
-            {
-                if *maybe_placeholder_offset == 0xDEAD {
-                    bytecode[i] = Instruction::Jump {
-                        offset: offset.try_into().map(|o: JumpOffset| o + 1).map_err(|e| {
-                            let diagnostic = Diagnostic::error()
-                                .with_message(format!("integer conversion error: {e}"));
-                            CompilerError::Diagnostic(diagnostic)
-                        })?,
-                    }
-                }
+                None
             }
+        };
 
-            i += 1;
+        let condition = self.get_register()?;
+        let item = match array {
+            Some(_) => self.get_register()?,
+            None => counter,
+        };
+        self.define_immutable_current_scope(binding, item);
+
+        let loop_start = self.bytecode.borrow().len();
+        // the body can push to the array, so its length is read every time around
+        if let Some(array) = array {
+            self.bytecode.borrow_mut().push(Instruction::ArrayLength {
+                dest: end,
+                src: array,
+            });
         }
 
-        let instruction = Instruction::JumpReverse {
-            offset: body_size.try_into().map_err(|e| {
-                let diagnostic =
-                    Diagnostic::error().with_message(format!("integer conversion error: {e}"));
-                CompilerError::Diagnostic(diagnostic)
-            })?,
+        let exit_jump = {
+            let mut bytecode = self.bytecode.borrow_mut();
+            bytecode.push(Instruction::LessThan {
+                dest: condition,
+                lhs: counter,
+                rhs: end,
+            });
+            // patched once the end of the loop is known
+            bytecode.push(Instruction::JumpIfFalse {
+                src: condition,
+                offset: 0,
+            });
+
+            bytecode.len() - 1
         };
 
+        if let Some(array) = array {
+            self.bytecode.borrow_mut().push(Instruction::GetArrayIndex {
+                array,
+                index: counter,
+                return_val: item,
+            });
+        }
+
+        self.loop_results.push(None);
+        let compiled_body = match body {
+            Statement::Block { body, .. } => self.compile_block(body),
+            _ => unreachable!(),
+        };
+        self.loop_results.pop();
+        compiled_body?;
+
+        let step = self.bytecode.borrow().len();
+        self.bytecode
+            .borrow_mut()
+            .push(Instruction::Increment { reg: counter });
+        self.close_loop(loop_start, step)?;
+
+        let loop_end = self.bytecode.borrow().len();
+        self.bytecode.borrow_mut()[exit_jump] = Instruction::JumpIfFalse {
+            src: condition,
+            offset: Self::jump_offset((loop_end - exit_jump) as isize)?,
+        };
+
+        self.remove_scope();
+        self.release_registers(first_register);
+
+        Ok(())
+    }
+
+    // patterns are compared in order, the first arm that matches runs and skips the rest
+    pub fn compile_match(
+        &mut self,
+        value: &Expression,
+        arms: &[ast::MatchArm],
+    ) -> Result<(), CompilerError> {
+        let first_register = self.next_available_register;
+        let value = self.compile_expression(value)?;
+        let pattern = self.get_register()?;
+        let condition = self.get_register()?;
+
+        let mut end_jumps = Vec::with_capacity(arms.len());
+        for arm in arms {
+            let next_arm_jump = match &arm.pattern {
+                ast::Pattern::Literal(literal) => {
+                    self.compile_expression_with_dest(
+                        &Expression::Literal(literal.clone()),
+                        Some(pattern),
+                    )?;
+
+                    let mut bytecode = self.bytecode.borrow_mut();
+                    bytecode.push(Instruction::Equals {
+                        dest: condition,
+                        lhs: value,
+                        rhs: pattern,
+                    });
+                    // patched once the arm's body is in
+                    bytecode.push(Instruction::JumpIfFalse {
+                        src: condition,
+                        offset: 0,
+                    });
+
+                    Some(bytecode.len() - 1)
+                }
+                ast::Pattern::Wildcard => None,
+            };
+
+            match &arm.body {
+                Statement::Block { body, .. } => self.compile_block(body)?,
+                _ => unreachable!(),
+            }
+
+            // a wildcard always matches, so it can fall straight through to the end
+            let Some(next_arm_jump) = next_arm_jump else {
+                break;
+            };
+
+            let mut bytecode = self.bytecode.borrow_mut();
+            end_jumps.push(bytecode.len());
+            bytecode.push(Instruction::Jump { offset: 0 });
+
+            bytecode[next_arm_jump] = Instruction::JumpIfFalse {
+                src: condition,
+                offset: Self::jump_offset((bytecode.len() - next_arm_jump) as isize)?,
+            };
+        }
+
+        {
+            let mut bytecode = self.bytecode.borrow_mut();
+            let end = bytecode.len();
+            for end_jump in end_jumps {
+                bytecode[end_jump] = Instruction::Jump {
+                    offset: Self::jump_offset((end - end_jump) as isize)?,
+                };
+            }
+        }
+
+        self.release_registers(first_register);
+
+        Ok(())
+    }
+
+    // jumps back to loop_start, then points the breaks in the loop past that jump
+    // and the continues at continue_target, nested loops have already taken theirs
+    fn close_loop(
+        &mut self,
+        loop_start: usize,
+        continue_target: usize,
+    ) -> Result<(), CompilerError> {
+        let mut bytecode = self.bytecode.borrow_mut();
+        let back_jump = bytecode.len();
+        for (i, instruction) in bytecode.iter_mut().enumerate().skip(loop_start) {
+            let target = match instruction {
+                Instruction::Jump {
+                    offset: BREAK_PLACEHOLDER,
+                } => back_jump + 1,
+                Instruction::Jump {
+                    offset: CONTINUE_PLACEHOLDER,
+                } => continue_target,
+                _ => continue,
+            };
+
+            *instruction = Instruction::Jump {
+                offset: Self::jump_offset(target as isize - i as isize)?,
+            };
+        }
+
+        let instruction = Instruction::Jump {
+            offset: Self::jump_offset(loop_start as isize - back_jump as isize)?,
+        };
         bytecode.push(instruction);
 
         Ok(())
     }
 
-    pub fn compile_break(&mut self) -> Result<(), CompilerError> {
-        // breaks should only exist in loops, so we need to update this offset
-        let instruction = Instruction::Jump { offset: 0xDEAD };
+    // the placeholders are never valid offsets, so the range loses two on the negative side
+    fn jump_offset(distance: isize) -> Result<JumpOffset, CompilerError> {
+        match JumpOffset::try_from(distance) {
+            Ok(offset) if offset != BREAK_PLACEHOLDER && offset != CONTINUE_PLACEHOLDER => {
+                Ok(offset)
+            }
+            _ => {
+                let diagnostic = Diagnostic::error().with_message(format!(
+                    "body too large, cannot jump {} instructions (limit is {})",
+                    distance.abs(),
+                    JumpOffset::MAX
+                ));
+                Err(CompilerError::Diagnostic(diagnostic))
+            }
+        }
+    }
+
+    pub fn compile_break(&mut self, value: &Option<Expression>) -> Result<(), CompilerError> {
+        if let Some(value) = value {
+            let Some(loop_result) = self.loop_results.last().copied() else {
+                let diagnostic =
+                    Diagnostic::error().with_message("`break` with a value outside of a loop");
+                return Err(CompilerError::Diagnostic(diagnostic));
+            };
+
+            let dest = match loop_result {
+                Some(dest) => dest,
+                None => {
+                    let dest = self.get_register()?;
+                    *self.loop_results.last_mut().unwrap() = Some(dest);
+                    dest
+                }
+            };
+
+            self.compile_expression_with_dest(value, Some(dest))?;
+        }
+
+        // breaks should only exist in loops, the enclosing compile_loop patches this offset
+        let instruction = Instruction::Jump {
+            offset: BREAK_PLACEHOLDER,
+        };
+        self.bytecode.borrow_mut().push(instruction);
+
+        Ok(())
+    }
+
+    pub fn compile_continue(&mut self) -> Result<(), CompilerError> {
+        if self.loop_results.is_empty() {
+            let diagnostic = Diagnostic::error().with_message("`continue` outside of a loop");
+            return Err(CompilerError::Diagnostic(diagnostic));
+        }
+
+        let instruction = Instruction::Jump {
+            offset: CONTINUE_PLACEHOLDER,
+        };
         self.bytecode.borrow_mut().push(instruction);
 
         Ok(())
@@ -680,8 +1423,14 @@ impl Compiler {
             _ => unreachable!(),
         };
 
-        let register = self.get_register();
         let base_obj = path.first().unwrap();
+        if self.resolve(base_obj).is_some() && !self.can_mutate_variable(base_obj) {
+            let diagnostic = Diagnostic::error()
+                .with_message(format!("mutation not allowed for variable `{}`", base_obj));
+            return Err(CompilerError::Diagnostic(diagnostic));
+        }
+
+        let register = self.get_register()?;
         let mut obj_reg = self.compile_expression(&Expression::Variable(base_obj.to_string()))?;
 
         for path_value in path.iter().skip(1).take(path.len() - 2) {
@@ -721,26 +1470,60 @@ impl Compiler {
     pub fn compile_statement(&mut self, statement: &Statement) -> Result<(), CompilerError> {
         match statement {
             // kinda sus?
-            Statement::Const { name, value, .. } => self.compile_let(name, value, false),
+            Statement::Const {
+                name,
+                value,
+                type_name,
+                ..
+            } => match self.fold_constant(value) {
+                Some(folded) => {
+                    let folded = match folded {
+                        Literal::Integer(n) if is_float_annotation(type_name.as_ref()) => {
+                            Literal::Float(n as f64)
+                        }
+                        folded => folded,
+                    };
+
+                    self.compile_let(name, &Expression::Literal(folded.clone()), false, None)?;
+                    self.scope_stack
+                        .last()
+                        .unwrap()
+                        .define_constant(name, folded);
+
+                    Ok(())
+                }
+                None => self.compile_let(name, value, false, type_name.as_ref()),
+            },
             Statement::Let {
                 name,
                 value,
                 is_mutable,
+                type_name,
                 ..
-            } => self.compile_let(name, value, *is_mutable),
-            Statement::Reassignment { name, value } => self.compile_let_mutation(name, value),
+            } => self.compile_let(name, value, *is_mutable, type_name.as_ref()),
+            Statement::Reassignment { name, value, .. } => self.compile_let_mutation(name, value),
             Statement::If {
                 condition,
                 body,
                 else_statement,
             } => self.compile_if(condition, body, else_statement),
-            Statement::Block { body } => self.compile_block(body),
+            Statement::Block { body, .. } => self.compile_block(body),
             Statement::Function(func) => self.compile_function(func),
             Statement::Expression(expr) => self.compile_expression(expr).map(|_| ()),
             Statement::Return(expression) => self.compile_return(expression),
-            Statement::Loop { body } => self.compile_loop(body),
-            Statement::Break => self.compile_break(),
-            Statement::ObjectMutation { path, value } => self.compile_object_mutation(path, value),
+            Statement::Loop { body } => self.compile_loop(body).map(|_| ()),
+            Statement::For {
+                binding,
+                iterable,
+                body,
+                ..
+            } => self.compile_for(binding, iterable, body),
+            Statement::Match { value, arms } => self.compile_match(value, arms),
+            Statement::Break(value) => self.compile_break(value),
+            Statement::Continue => self.compile_continue(),
+            Statement::ObjectMutation { path, value, .. } => {
+                self.compile_object_mutation(path, value)
+            }
         }
     }
 }
@@ -0,0 +1,92 @@
+use crate::{compiler::CompiledProgram, instructions::Instruction, types::Literal};
+use std::fmt::Write;
+
+pub fn disassemble(program: &CompiledProgram) -> String {
+    let mut output = String::new();
+
+    output.push_str("literals:\n");
+    for (i, literal) in program.literals.iter().enumerate() {
+        let _ = writeln!(
+            output,
+            "    lit[{i}]: {} ({})",
+            literal_value(literal),
+            literal_type(literal)
+        );
+    }
+
+    for function in &program.functions {
+        let _ = writeln!(
+            output,
+            "\nfn {} (registers: {})",
+            function.name, function.register_count
+        );
+        code(&mut output, &function.code, program);
+    }
+
+    let _ = writeln!(
+        output,
+        "\nglobal (registers: {})",
+        program.global_register_count
+    );
+    code(&mut output, &program.global_code, program);
+
+    if let Some(result) = program.global_result {
+        let _ = writeln!(output, "    result: r{result}");
+    }
+
+    output
+}
+
+fn code(output: &mut String, code: &[Instruction], program: &CompiledProgram) {
+    for (offset, instruction) in code.iter().enumerate() {
+        let _ = writeln!(
+            output,
+            "    {offset:04}  {instruction}{}",
+            annotation(instruction, offset, program)
+        );
+    }
+}
+
+fn literal_value(literal: &Literal) -> String {
+    match literal {
+        Literal::String(s) => format!("{s:?}"),
+        other => other.to_string(),
+    }
+}
+
+fn literal_type(literal: &Literal) -> &'static str {
+    match literal {
+        Literal::String(_) => "string",
+        Literal::Float(_) => "float",
+        Literal::Integer(_) => "int",
+        Literal::Boolean(_) => "bool",
+        Literal::Nil => "nil",
+    }
+}
+
+// what the instruction's display leaves out, literal values, function names and jump targets
+fn annotation(instruction: &Instruction, offset: usize, program: &CompiledProgram) -> String {
+    match *instruction {
+        Instruction::LoadLiteral { src, .. } => {
+            let value = program
+                .literals
+                .get(src as usize)
+                .map_or("?".to_owned(), literal_value);
+            format!(" ({value})")
+        }
+        Instruction::LoadFunction { src, .. } => {
+            let name = program
+                .functions
+                .get(src as usize)
+                .map_or("?", |f| f.name.as_str());
+            format!(" ({name})")
+        }
+        // jumps are relative to themselves, show where they land
+        Instruction::JumpIfFalse { offset: jump, .. }
+        | Instruction::JumpIfTrue { offset: jump, .. }
+        | Instruction::Jump { offset: jump } => {
+            format!(" (-> {:04})", offset.wrapping_add_signed(jump as isize))
+        }
+        _ => String::new(),
+    }
+}
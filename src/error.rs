@@ -1,3 +1,7 @@
+use crate::{
+    compiler::CompilerError, parser::ParserError, typechecker::TypecheckerError, vm::ExecutionError,
+};
+
 // Marker type for when diagnostic was already emitted but we want to return with Error
 #[derive(Debug)]
 pub struct DiagnosticEmitted;
@@ -9,3 +13,18 @@ impl std::fmt::Display for DiagnosticEmitted {
         Ok(())
     }
 }
+
+// everything that can go wrong between source text and a finished run, see `crate::compile_source`
+#[allow(unused)]
+#[derive(Debug, thiserror::Error)]
+pub enum SolError {
+    // the parser recovers after an error, so there can be several
+    #[error("failed to parse, {} error(s)", .0.len())]
+    Parser(Vec<ParserError>),
+    #[error(transparent)]
+    Typechecker(#[from] TypecheckerError),
+    #[error(transparent)]
+    Compiler(#[from] CompilerError),
+    #[error(transparent)]
+    Execution(#[from] ExecutionError),
+}
@@ -0,0 +1,423 @@
+use crate::{
+    ast::{Expression, Function, Iterable, ObjectField, Operator, Pattern, Statement, TypeName},
+    types::Literal,
+};
+
+const INDENT: &str = "    ";
+// objects and arrays wider than this are laid out one entry per line
+const MAX_WIDTH: usize = 80;
+
+// comments aren't part of the ast, so they are dropped
+pub fn format(statements: &[Statement]) -> String {
+    let mut formatter = Formatter::default();
+    formatter.statements(statements, 0);
+
+    formatter.output
+}
+
+#[derive(Default)]
+struct Formatter {
+    output: String,
+}
+
+impl Formatter {
+    fn line(&mut self, indent: usize, text: &str) {
+        self.output.push_str(&INDENT.repeat(indent));
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    fn statements(&mut self, statements: &[Statement], indent: usize) {
+        for (i, statement) in statements.iter().enumerate() {
+            // functions get a blank line on either side
+            if i > 0
+                && (matches!(statement, Statement::Function(_))
+                    || matches!(statements[i - 1], Statement::Function(_)))
+            {
+                self.output.push('\n');
+            }
+
+            self.statement(statement, indent);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, indent: usize) {
+        match statement {
+            Statement::Const {
+                name,
+                value,
+                type_name,
+                ..
+            } => {
+                let value = expression(value, indent);
+                let line = format!("const {name}{} = {value};", type_annotation(type_name));
+                self.line(indent, &line);
+            }
+            Statement::Let {
+                name,
+                value,
+                is_mutable,
+                type_name,
+                ..
+            } => {
+                let value = expression(value, indent);
+                let mutable = if *is_mutable { "mut " } else { "" };
+                let line = format!(
+                    "let {mutable}{name}{} = {value};",
+                    type_annotation(type_name)
+                );
+                self.line(indent, &line);
+            }
+            Statement::Reassignment { name, value, .. } => {
+                let line = format!("{name} = {};", expression(value, indent));
+                self.line(indent, &line);
+            }
+            Statement::ObjectMutation { path, value, .. } => {
+                let line = format!(
+                    "{} = {};",
+                    expression(path, indent),
+                    expression(value, indent)
+                );
+                self.line(indent, &line);
+            }
+            Statement::If { .. } => {
+                self.output.push_str(&INDENT.repeat(indent));
+                self.if_statement(statement, indent);
+                self.output.push('\n');
+            }
+            Statement::Block { .. } => {
+                self.output.push_str(&INDENT.repeat(indent));
+                self.block(statement, indent);
+                self.output.push('\n');
+            }
+            Statement::Loop { body } => {
+                self.output.push_str(&INDENT.repeat(indent));
+                self.output.push_str("loop ");
+                self.block(body, indent);
+                self.output.push('\n');
+            }
+            Statement::For {
+                binding,
+                iterable,
+                body,
+                ..
+            } => {
+                let iterable = match iterable {
+                    Iterable::Array(array) => expression(array, indent),
+                    Iterable::Range { start, end } => {
+                        format!("{}..{}", expression(start, indent), expression(end, indent))
+                    }
+                };
+
+                self.output.push_str(&INDENT.repeat(indent));
+                self.output
+                    .push_str(&format!("for {binding} in {iterable} "));
+                self.block(body, indent);
+                self.output.push('\n');
+            }
+            Statement::Match { value, arms } => {
+                self.line(indent, &format!("match {} {{", expression(value, indent)));
+                for arm in arms {
+                    let pattern = match &arm.pattern {
+                        Pattern::Literal(l) => literal(l),
+                        Pattern::Wildcard => "_".to_owned(),
+                    };
+
+                    self.output.push_str(&INDENT.repeat(indent + 1));
+                    self.output.push_str(&format!("{pattern} => "));
+                    self.block(&arm.body, indent + 1);
+                    self.output.push('\n');
+                }
+                self.line(indent, "}");
+            }
+            Statement::Return(value) => {
+                let line = format!("return {};", expression(value, indent));
+                self.line(indent, &line);
+            }
+            Statement::Function(function) => self.function(function, indent),
+            Statement::Expression(value) => {
+                let text = expression(value, indent);
+                // a leading `{` is a block, `a.b` is a mutation and `if` is a statement
+                let line = if starts_with_object(value) {
+                    format!("({text});")
+                } else {
+                    format!("{text};")
+                };
+                self.line(indent, &line);
+            }
+            Statement::Break(None) => self.line(indent, "break;"),
+            Statement::Continue => self.line(indent, "continue;"),
+            Statement::Break(Some(value)) => {
+                let line = format!("break {};", expression(value, indent));
+                self.line(indent, &line);
+            }
+        }
+    }
+
+    // writes from `if` up to the closing brace, without a newline
+    fn if_statement(&mut self, statement: &Statement, indent: usize) {
+        let Statement::If {
+            condition,
+            body,
+            else_statement,
+        } = statement
+        else {
+            unreachable!()
+        };
+
+        self.output
+            .push_str(&format!("if {} ", expression(condition, indent)));
+        self.block(body, indent);
+
+        match else_statement.as_deref() {
+            Some(else_if @ Statement::If { .. }) => {
+                self.output.push_str(" else ");
+                self.if_statement(else_if, indent);
+            }
+            Some(block) => {
+                self.output.push_str(" else ");
+                self.block(block, indent);
+            }
+            None => {}
+        }
+    }
+
+    // writes a braced block, without a newline after the closing brace
+    fn block(&mut self, statement: &Statement, indent: usize) {
+        let Statement::Block { body, .. } = statement else {
+            unreachable!()
+        };
+
+        if body.is_empty() {
+            self.output.push_str("{}");
+            return;
+        }
+
+        self.output.push_str("{\n");
+        self.statements(body, indent + 1);
+        self.output.push_str(&INDENT.repeat(indent));
+        self.output.push('}');
+    }
+
+    fn function(&mut self, function: &Function, indent: usize) {
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|p| {
+                let ellipsis = if p.is_variadic { "..." } else { "" };
+                let default_value = match &p.default_value {
+                    Some(l) => format!(" = {}", literal(l)),
+                    None => "".to_owned(),
+                };
+                format!("{}: {}{ellipsis}{default_value}", p.name, p.type_name)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let return_type = match &function.return_type_name {
+            Some(type_name) => format!(" -> {type_name}"),
+            None => "".to_owned(),
+        };
+
+        self.output.push_str(&INDENT.repeat(indent));
+        self.output
+            .push_str(&format!("fn {}({parameters}){return_type} ", function.name));
+        self.block(&function.body, indent);
+        self.output.push('\n');
+    }
+}
+
+fn type_annotation(type_name: &Option<TypeName>) -> String {
+    match type_name {
+        Some(type_name) => format!(": {type_name}"),
+        None => "".to_owned(),
+    }
+}
+
+fn starts_with_object(expr: &Expression) -> bool {
+    match expr {
+        Expression::Object { .. } | Expression::ObjectAccess { .. } => true,
+        Expression::Infix { lhs, .. } => starts_with_object(lhs),
+        Expression::Cast { expr, .. } => starts_with_object(expr),
+        // would be read back as an if statement
+        Expression::If { .. } => true,
+        _ => false,
+    }
+}
+
+fn operator(op: &Operator) -> &'static str {
+    match op {
+        Operator::Plus => "+",
+        Operator::Minus => "-",
+        Operator::Multiply => "*",
+        Operator::Modulo => "%",
+        Operator::Not => "!",
+        Operator::Divide => "/",
+        Operator::GreaterThan => ">",
+        Operator::GreaterThanOrEqual => ">=",
+        Operator::LessThan => "<",
+        Operator::LessThanOrEqual => "<=",
+        Operator::Equal => "==",
+        Operator::NotEqual => "!=",
+        Operator::And => "&&",
+        Operator::Or => "||",
+        Operator::BitAnd => "&",
+        Operator::BitOr => "|",
+        Operator::BitXor => "^",
+        Operator::BitNot => "~",
+        Operator::ShiftLeft => "<<",
+        Operator::ShiftRight => ">>",
+    }
+}
+
+fn literal(literal: &Literal) -> String {
+    match literal {
+        Literal::String(s) => format!("\"{s}\""),
+        // keep the `.` so it lexes as a float again
+        Literal::Float(f) if f.fract() == 0.0 => format!("{f}.0"),
+        Literal::Float(f) => f.to_string(),
+        Literal::Integer(i) => i.to_string(),
+        Literal::Boolean(b) => b.to_string(),
+        Literal::Nil => "nil".to_owned(),
+    }
+}
+
+fn parenthesize(text: String, needs_parens: bool) -> String {
+    if needs_parens {
+        format!("({text})")
+    } else {
+        text
+    }
+}
+
+fn infix_binding_power(expr: &Expression) -> Option<u8> {
+    match expr {
+        Expression::Infix { op, .. } => op.infix_binding_power().map(|(left, _)| left),
+        _ => None,
+    }
+}
+
+fn expression(expr: &Expression, indent: usize) -> String {
+    match expr {
+        Expression::Prefix { op, expr } => {
+            let needs_parens = matches!(**expr, Expression::Infix { .. } | Expression::Cast { .. });
+            let operand = parenthesize(expression(expr, indent), needs_parens);
+            format!("{}{operand}", operator(op))
+        }
+        Expression::Infix { op, lhs, rhs } => {
+            let (left, _) = op.infix_binding_power().unwrap();
+            // operators are left associative, so only the right needs parens on a tie
+            let lhs_parens = infix_binding_power(lhs).is_some_and(|bp| bp < left);
+            let rhs_parens = infix_binding_power(rhs).is_some_and(|bp| bp <= left);
+
+            format!(
+                "{} {} {}",
+                parenthesize(expression(lhs, indent), lhs_parens),
+                operator(op),
+                parenthesize(expression(rhs, indent), rhs_parens)
+            )
+        }
+        Expression::Literal(l) => literal(l),
+        Expression::Variable(name) => name.clone(),
+        Expression::FunctionCall { name, args, .. } => {
+            let args = args
+                .iter()
+                .map(|arg| expression(arg, indent))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!("{name}({args})")
+        }
+        Expression::Object { fields } => {
+            let entries = fields
+                .iter()
+                .map(|field| match field {
+                    ObjectField::Named(key, value) => {
+                        format!("{key}: {}", expression(value, indent + 1))
+                    }
+                    ObjectField::Spread(value) => format!("...{}", expression(value, indent + 1)),
+                })
+                .collect::<Vec<_>>();
+
+            delimited(&entries, ('{', '}'), " ", indent)
+        }
+        Expression::Array { this } => {
+            let entries = this
+                .iter()
+                .map(|value| expression(value, indent + 1))
+                .collect::<Vec<_>>();
+
+            delimited(&entries, ('[', ']'), "", indent)
+        }
+        Expression::ObjectAccess { path } => path.join("."),
+        Expression::ArrayAccess { name, index } => {
+            format!("{name}[{}]", expression(index, indent))
+        }
+        Expression::ArraySlice { name, start, end } => {
+            format!(
+                "{name}[{}..{}]",
+                expression(start, indent),
+                expression(end, indent)
+            )
+        }
+        Expression::Cast { expr, type_name } => {
+            let needs_parens = matches!(**expr, Expression::Infix { .. });
+            format!(
+                "{} as {type_name}",
+                parenthesize(expression(expr, indent), needs_parens)
+            )
+        }
+        Expression::If {
+            condition,
+            body,
+            else_body,
+        } => {
+            let mut text = format!(
+                "if {} {{ {} }}",
+                expression(condition, indent),
+                expression(body, indent)
+            );
+
+            match else_body.as_deref() {
+                Some(else_if @ Expression::If { .. }) => {
+                    text.push_str(&format!(" else {}", expression(else_if, indent)));
+                }
+                Some(else_body) => {
+                    text.push_str(&format!(" else {{ {} }}", expression(else_body, indent)));
+                }
+                None => {}
+            }
+
+            text
+        }
+    }
+}
+
+// inline if it fits, otherwise one entry per line with a trailing comma
+fn delimited(
+    entries: &[String],
+    (open, close): (char, char),
+    padding: &str,
+    indent: usize,
+) -> String {
+    if entries.is_empty() {
+        return format!("{open}{close}");
+    }
+
+    let inline = format!("{open}{padding}{}{padding}{close}", entries.join(", "));
+    let fits = INDENT.len() * indent + inline.len() <= MAX_WIDTH;
+    if fits && !inline.contains('\n') {
+        return inline;
+    }
+
+    let mut text = format!("{open}\n");
+    for entry in entries {
+        text.push_str(&INDENT.repeat(indent + 1));
+        text.push_str(entry);
+        text.push_str(",\n");
+    }
+    text.push_str(&INDENT.repeat(indent));
+    text.push(close);
+
+    text
+}
@@ -1,9 +1,30 @@
-pub type Register = u8;
+pub type Register = u16;
 pub type LiteralId = u16;
 pub type FunctionId = u16;
-pub type JumpOffset = u16;
+// relative to the jump itself, negative for backwards
+pub type JumpOffset = i32;
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum CastTarget {
+    Int = 0,
+    Float = 1,
+    String = 2,
+    Bool = 3,
+}
+
+impl std::fmt::Display for CastTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int => write!(f, "int"),
+            Self::Float => write!(f, "float"),
+            Self::String => write!(f, "string"),
+            Self::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Instruction {
     Copy {
         dest: Register,
@@ -34,6 +55,11 @@ pub enum Instruction {
         field: Register,
         value: Register,
     },
+    // copies every field of `src` into `object`
+    SpreadObject {
+        object: Register,
+        src: Register,
+    },
     GetObjectField {
         object: Register,
         field: Register,
@@ -49,10 +75,34 @@ pub enum Instruction {
         index: Register,
         return_val: Register,
     },
+    ArrayLength {
+        dest: Register,
+        src: Register,
+    },
+    AppendArray {
+        array: Register,
+        value: Register,
+    },
+    PopArray {
+        dest: Register,
+        array: Register,
+    },
+    TypeOf {
+        dest: Register,
+        src: Register,
+    },
+    Cast {
+        dest: Register,
+        src: Register,
+        target_type: CastTarget,
+    },
     LoadLiteral {
         dest: Register,
         src: LiteralId,
     },
+    LoadNil {
+        dest: Register,
+    },
     PrefixNot {
         dest: Register,
         rhs: Register,
@@ -65,10 +115,11 @@ pub enum Instruction {
         src: Register,
         offset: JumpOffset,
     },
-    Jump {
+    JumpIfTrue {
+        src: Register,
         offset: JumpOffset,
     },
-    JumpReverse {
+    Jump {
         offset: JumpOffset,
     },
     Add {
@@ -91,6 +142,16 @@ pub enum Instruction {
         lhs: Register,
         rhs: Register,
     },
+    Modulo {
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    StringConcat {
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+    },
     Equals {
         dest: Register,
         lhs: Register,
@@ -121,20 +182,224 @@ pub enum Instruction {
         lhs: Register,
         rhs: Register,
     },
+    BitAnd {
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    BitOr {
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    BitXor {
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    BitNot {
+        dest: Register,
+        rhs: Register,
+    },
+    Shl {
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    Shr {
+        dest: Register,
+        lhs: Register,
+        rhs: Register,
+    },
     Return {
         val: Register,
     },
     FunctionReturn,
+    Increment {
+        reg: Register,
+    },
+    // the end bound is always in `start + 1`
+    SliceArray {
+        dest: Register,
+        array: Register,
+        start: Register,
+    },
+    Decrement {
+        reg: Register,
+    },
+    // ints become floats, anything else is copied as is
+    IntToFloat {
+        dest: Register,
+        src: Register,
+    },
+    // floats are truncated toward zero, anything else is copied as is
+    FloatToInt {
+        dest: Register,
+        src: Register,
+    },
+}
+
+impl Instruction {
+    // variant name without operands, used for stats and tracing
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Copy { .. } => "Copy",
+            Self::LoadFunction { .. } => "LoadFunction",
+            Self::CallNativeFunction { .. } => "CallNativeFunction",
+            Self::CallFunction { .. } => "CallFunction",
+            Self::AllocateObject { .. } => "AllocateObject",
+            Self::AllocateArray { .. } => "AllocateArray",
+            Self::SetObjectField { .. } => "SetObjectField",
+            Self::SpreadObject { .. } => "SpreadObject",
+            Self::GetObjectField { .. } => "GetObjectField",
+            Self::SetArrayIndex { .. } => "SetArrayIndex",
+            Self::GetArrayIndex { .. } => "GetArrayIndex",
+            Self::ArrayLength { .. } => "ArrayLength",
+            Self::AppendArray { .. } => "AppendArray",
+            Self::PopArray { .. } => "PopArray",
+            Self::TypeOf { .. } => "TypeOf",
+            Self::Cast { .. } => "Cast",
+            Self::LoadLiteral { .. } => "LoadLiteral",
+            Self::LoadNil { .. } => "LoadNil",
+            Self::PrefixNot { .. } => "PrefixNot",
+            Self::PrefixSub { .. } => "PrefixSub",
+            Self::JumpIfFalse { .. } => "JumpIfFalse",
+            Self::JumpIfTrue { .. } => "JumpIfTrue",
+            Self::Jump { .. } => "Jump",
+            Self::Add { .. } => "Add",
+            Self::Sub { .. } => "Sub",
+            Self::Mul { .. } => "Mul",
+            Self::Div { .. } => "Div",
+            Self::Modulo { .. } => "Modulo",
+            Self::StringConcat { .. } => "StringConcat",
+            Self::Equals { .. } => "Equals",
+            Self::NotEquals { .. } => "NotEquals",
+            Self::GreaterThan { .. } => "GreaterThan",
+            Self::GreaterThanOrEquals { .. } => "GreaterThanOrEquals",
+            Self::LessThan { .. } => "LessThan",
+            Self::LessThanOrEquals { .. } => "LessThanOrEquals",
+            Self::BitAnd { .. } => "BitAnd",
+            Self::BitOr { .. } => "BitOr",
+            Self::BitXor { .. } => "BitXor",
+            Self::BitNot { .. } => "BitNot",
+            Self::Shl { .. } => "Shl",
+            Self::Shr { .. } => "Shr",
+            Self::Return { .. } => "Return",
+            Self::FunctionReturn => "FunctionReturn",
+            Self::Increment { .. } => "Increment",
+            Self::SliceArray { .. } => "SliceArray",
+            Self::Decrement { .. } => "Decrement",
+            Self::IntToFloat { .. } => "IntToFloat",
+            Self::FloatToInt { .. } => "FloatToInt",
+        }
+    }
+}
+
+// mnemonic and operands, registers as r<n>, used by tracing and the disassembler
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.name();
+        match *self {
+            Self::Copy { dest, src }
+            | Self::ArrayLength { dest, src }
+            | Self::TypeOf { dest, src }
+            | Self::IntToFloat { dest, src }
+            | Self::FloatToInt { dest, src }
+            | Self::PrefixNot { dest, rhs: src }
+            | Self::PrefixSub { dest, rhs: src }
+            | Self::BitNot { dest, rhs: src }
+            | Self::PopArray { dest, array: src }
+            | Self::SpreadObject { object: dest, src }
+            | Self::AppendArray {
+                array: dest,
+                value: src,
+            } => write!(f, "{name} r{dest}, r{src}"),
+            Self::LoadFunction { dest, src } => write!(f, "{name} r{dest}, fn[{src}]"),
+            Self::LoadLiteral { dest, src } => write!(f, "{name} r{dest}, lit[{src}]"),
+            Self::CallNativeFunction {
+                src,
+                arg_count,
+                return_val,
+            }
+            | Self::CallFunction {
+                src,
+                arg_count,
+                return_val,
+            } => write!(f, "{name} r{src}, args: {arg_count}, r{return_val}"),
+            Self::AllocateObject { dest }
+            | Self::AllocateArray { dest }
+            | Self::LoadNil { dest } => {
+                write!(f, "{name} r{dest}")
+            }
+            Self::SetObjectField {
+                object: a,
+                field: b,
+                value: c,
+            }
+            | Self::GetObjectField {
+                object: a,
+                field: b,
+                return_val: c,
+            }
+            | Self::SetArrayIndex {
+                array: a,
+                index: b,
+                value: c,
+            }
+            | Self::GetArrayIndex {
+                array: a,
+                index: b,
+                return_val: c,
+            }
+            | Self::SliceArray {
+                dest: a,
+                array: b,
+                start: c,
+            } => write!(f, "{name} r{a}, r{b}, r{c}"),
+            Self::JumpIfFalse { src, offset } | Self::JumpIfTrue { src, offset } => {
+                write!(f, "{name} r{src}, {offset:+}")
+            }
+            Self::Jump { offset } => write!(f, "{name} {offset:+}"),
+            Self::Add { dest, lhs, rhs }
+            | Self::Sub { dest, lhs, rhs }
+            | Self::Mul { dest, lhs, rhs }
+            | Self::Div { dest, lhs, rhs }
+            | Self::Modulo { dest, lhs, rhs }
+            | Self::StringConcat { dest, lhs, rhs }
+            | Self::Equals { dest, lhs, rhs }
+            | Self::NotEquals { dest, lhs, rhs }
+            | Self::GreaterThan { dest, lhs, rhs }
+            | Self::GreaterThanOrEquals { dest, lhs, rhs }
+            | Self::LessThan { dest, lhs, rhs }
+            | Self::LessThanOrEquals { dest, lhs, rhs }
+            | Self::BitAnd { dest, lhs, rhs }
+            | Self::BitOr { dest, lhs, rhs }
+            | Self::BitXor { dest, lhs, rhs }
+            | Self::Shl { dest, lhs, rhs }
+            | Self::Shr { dest, lhs, rhs } => write!(f, "{name} r{dest}, r{lhs}, r{rhs}"),
+            Self::Cast {
+                dest,
+                src,
+                target_type,
+            } => write!(f, "{name} r{dest}, r{src}, {target_type}"),
+            Self::Return { val } | Self::Increment { reg: val } | Self::Decrement { reg: val } => {
+                write!(f, "{name} r{val}")
+            }
+            Self::FunctionReturn => write!(f, "{name}"),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::Instruction;
-    use pretty_assertions::assert_eq;
     use std::mem::size_of;
 
+    // the run loop copies an instruction per step, keep them to a word so adding
+    // an opcode doesn't quietly slow every other one down, operands are at most
+    // three 16 bit registers, anything wider belongs in the literal table
     #[test]
-    fn test_instruction_is_32_bits() {
-        assert_eq!(size_of::<Instruction>(), 4);
+    fn test_instruction_fits_in_64_bits() {
+        assert!(size_of::<Instruction>() <= 8);
     }
 }
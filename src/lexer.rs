@@ -10,6 +10,7 @@ use std::{
 pub enum TokenKind {
     Comment,
     Identifier,
+    Keyword(Keyword),
     Literal,
     OpenParen,
     CloseParen,
@@ -20,8 +21,12 @@ pub enum TokenKind {
     Add,
     Subtract,
     Multiply,
+    Modulo,
     Comma,
     Assignment,
+    AddAssign,
+    SubtractAssign,
+    FatArrow,
     Divide,
     GreaterThan,
     LessThan,
@@ -29,27 +34,126 @@ pub enum TokenKind {
     LessThanOrEquals,
     Equal,
     NotEqual,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
     Whitespace,
     Colon,
+    Question,
     Dot,
+    DotDot,
+    Ellipsis,
     EndOfLine,
     Not,
+    // the lexer doesn't fail, the parser reports these
+    Unknown,
+    UnterminatedString,
 
     EndOfFile,
 }
 
 impl Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            TokenKind::Keyword(keyword) => write!(f, "{}", keyword),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// reserved words, these can't be used as names
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub enum Keyword {
+    Let,
+    Mut,
+    Const,
+    Fn,
+    Return,
+    If,
+    Else,
+    Loop,
+    For,
+    In,
+    Break,
+    Continue,
+    Match,
+    As,
+    True,
+    False,
+    Nil,
+}
+
+const KEYWORDS: [(&str, Keyword); 17] = [
+    ("let", Keyword::Let),
+    ("mut", Keyword::Mut),
+    ("const", Keyword::Const),
+    ("fn", Keyword::Fn),
+    ("return", Keyword::Return),
+    ("if", Keyword::If),
+    ("else", Keyword::Else),
+    ("loop", Keyword::Loop),
+    ("for", Keyword::For),
+    ("in", Keyword::In),
+    ("break", Keyword::Break),
+    ("continue", Keyword::Continue),
+    ("match", Keyword::Match),
+    ("as", Keyword::As),
+    ("true", Keyword::True),
+    ("false", Keyword::False),
+    ("nil", Keyword::Nil),
+];
+
+impl Keyword {
+    pub fn from_identifier(identifier: &str) -> Option<Self> {
+        KEYWORDS
+            .iter()
+            .find(|(text, _)| *text == identifier)
+            .map(|(_, keyword)| *keyword)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        KEYWORDS
+            .iter()
+            .find(|(_, keyword)| keyword == self)
+            .map(|(text, _)| *text)
+            .unwrap()
+    }
+}
+
+impl Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Span {
     pub file_id: usize,
     pub start: usize,
     pub end: usize,
     pub line: usize,
+    // 1 based, in bytes from the start of the line
+    pub column: usize,
+}
+
+impl Span {
+    // an empty span just past the last character of `input`
+    pub fn end_of(file_id: usize, input: &str) -> Self {
+        let line_start = input.rfind('\n').map_or(0, |newline| newline + 1);
+
+        Self {
+            file_id,
+            start: input.len(),
+            end: input.len(),
+            line: input.matches('\n').count() + 1,
+            column: input.len() - line_start + 1,
+        }
+    }
 }
 
 impl From<Span> for Range<usize> {
@@ -76,12 +180,8 @@ impl fmt::Debug for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{:?} - <{}, {}>, line: {}",
-            // "Token::new(TokenKind::{:?}, Span {{ start: {}, end: {}, line: {} }})",
-            self.kind,
-            self.span.start,
-            self.span.end,
-            self.span.line
+            "{:?} - <{}, {}>, line: {}, column: {}",
+            self.kind, self.span.start, self.span.end, self.span.line, self.span.column
         )
     }
 }
@@ -132,6 +232,8 @@ pub struct Cursor<'a> {
     chars: Peekable<Chars<'a>>,
     current_consumed: usize,
     line: usize,
+    // byte offset the current line starts at, for columns
+    line_start: usize,
 }
 
 impl<'a> Cursor<'a> {
@@ -141,6 +243,7 @@ impl<'a> Cursor<'a> {
             chars: chars.peekable(),
             current_consumed: 0,
             line: 1,
+            line_start: 0,
         }
     }
 
@@ -148,13 +251,37 @@ impl<'a> Cursor<'a> {
         *self.chars.peek().unwrap_or(&'\0')
     }
 
+    // the char after the one peek would return
+    fn peek_second(&self) -> char {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next().unwrap_or('\0')
+    }
+
     fn current(&self) -> usize {
         self.current_consumed
     }
 
+    // spans are byte offsets, strings and comments can hold multibyte chars
     fn next(&mut self) -> Option<char> {
-        self.current_consumed += 1;
-        self.chars.next()
+        let next = self.chars.next();
+        self.current_consumed += next.map_or(0, char::len_utf8);
+        if next == Some('\n') {
+            self.line += 1;
+            self.line_start = self.current_consumed;
+        }
+
+        next
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        Span {
+            file_id: self.file_id,
+            start,
+            end: self.current(),
+            line: self.line,
+            column: start.saturating_sub(self.line_start) + 1,
+        }
     }
 
     fn consume_until(&mut self, mut predicate: impl FnMut(char) -> bool) {
@@ -163,16 +290,17 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    // unicode letters are fine, `let π = 3.14;`
     fn is_start_of_identifier(&self, c: char) -> bool {
-        c.is_ascii_alphabetic() || c == '_'
+        c.is_alphabetic() || c == '_'
     }
 
     fn is_in_identifier(&self, c: char) -> bool {
-        c.is_ascii_alphanumeric() || c == '_'
+        c.is_alphanumeric() || c == '_'
     }
 
     fn consume_identifier(&mut self, initial: char) -> Token {
-        let start = self.current() - 1;
+        let start = self.current() - initial.len_utf8();
         let mut identifier = String::new();
         identifier.push(initial);
 
@@ -186,15 +314,12 @@ impl<'a> Cursor<'a> {
             }
         }
 
-        Token::new(
-            TokenKind::Identifier,
-            Span {
-                file_id: self.file_id,
-                start,
-                end: self.current(),
-                line: self.line,
-            },
-        )
+        let kind = match Keyword::from_identifier(&identifier) {
+            Some(keyword) => TokenKind::Keyword(keyword),
+            None => TokenKind::Identifier,
+        };
+
+        Token::new(kind, self.span_from(start))
     }
 
     fn consume_number(&mut self, initial: char) -> Token {
@@ -202,13 +327,22 @@ impl<'a> Cursor<'a> {
         let mut number = String::new();
         number.push(initial);
 
+        // 0x and 0b prefixes, the parser works out the radix from the text
+        if initial == '0' && matches!(self.peek(), 'x' | 'b') {
+            number.push(self.peek());
+            self.next();
+            self.consume_until(|c| !c.is_ascii_alphanumeric());
+
+            return Token::new(TokenKind::Literal, self.span_from(start));
+        }
+
         let mut is_floating = false;
         loop {
             let c = self.peek();
             if c.is_ascii_digit() {
                 number.push(c);
                 self.next();
-            } else if c == '.' && !is_floating {
+            } else if c == '.' && !is_floating && self.peek_second() != '.' {
                 is_floating = true;
                 number.push(c);
                 self.next();
@@ -217,38 +351,29 @@ impl<'a> Cursor<'a> {
             }
         }
 
-        Token::new(
-            TokenKind::Literal,
-            Span {
-                file_id: self.file_id,
-                start,
-                end: self.current(),
-                line: self.line,
-            },
-        )
+        Token::new(TokenKind::Literal, self.span_from(start))
     }
 
     fn consume_quoted_string(&mut self) -> Token {
-        let start = self.current() - 1;
+        // strings can run over several lines, the token belongs to the line it starts on
+        let opening = self.span_from(self.current() - 1);
         let mut s = String::new();
-        loop {
-            let c = self.peek();
-            if c == '"' {
-                self.next();
-                return Token::new(
-                    TokenKind::Literal,
-                    Span {
-                        file_id: self.file_id,
-                        start,
-                        end: self.current(),
-                        line: self.line,
-                    },
-                );
+        // running out of input leaves the string open, the parser reports it
+        let kind = loop {
+            match self.next() {
+                Some('"') => break TokenKind::Literal,
+                Some(c) => s.push(c),
+                None => break TokenKind::UnterminatedString,
             }
+        };
 
-            s.push(c);
-            self.next();
-        }
+        Token::new(
+            kind,
+            Span {
+                end: self.current(),
+                ..opening
+            },
+        )
     }
 
     fn consume_comment_or_divide(&mut self) -> Token {
@@ -261,51 +386,26 @@ impl<'a> Cursor<'a> {
             TokenKind::Divide
         };
 
-        Token::new(
-            token_kind,
-            Span {
-                file_id: self.file_id,
-                start,
-                end: self.current(),
-                line: self.line,
-            },
-        )
+        Token::new(token_kind, self.span_from(start))
     }
 
     pub fn next_token(&mut self) -> Token {
         let next = self.next();
         if next.is_none() {
-            return Token::new(
-                TokenKind::EndOfFile,
-                Span {
-                    file_id: self.file_id,
-                    start: self.current(),
-                    end: self.current(),
-                    line: self.line,
-                },
-            );
+            return Token::new(TokenKind::EndOfFile, self.span_from(self.current()));
         }
 
-        let single_char_span = Span {
-            file_id: self.file_id,
-            start: self.current() - 1,
-            end: self.current(),
-            line: self.line,
-        };
-
         let next = next.unwrap();
+        let single_char_span = self.span_from(self.current() - next.len_utf8());
+
         match next {
             '=' if self.peek() == '=' => {
                 self.next();
-                Token::new(
-                    TokenKind::Equal,
-                    Span {
-                        file_id: self.file_id,
-                        start: self.current() - 2,
-                        end: self.current(),
-                        line: self.line,
-                    },
-                )
+                Token::new(TokenKind::Equal, self.span_from(self.current() - 2))
+            }
+            '=' if self.peek() == '>' => {
+                self.next();
+                Token::new(TokenKind::FatArrow, self.span_from(self.current() - 2))
             }
             '=' => Token::new(TokenKind::Assignment, single_char_span),
 
@@ -313,110 +413,93 @@ impl<'a> Cursor<'a> {
                 self.next();
                 Token::new(
                     TokenKind::GreaterThanOrEquals,
-                    Span {
-                        file_id: self.file_id,
-                        start: self.current() - 2,
-                        end: self.current(),
-                        line: self.line,
-                    },
+                    self.span_from(self.current() - 2),
                 )
             }
 
+            '>' if self.peek() == '>' => {
+                self.next();
+                Token::new(TokenKind::ShiftRight, self.span_from(self.current() - 2))
+            }
             '>' => Token::new(TokenKind::GreaterThan, single_char_span),
             '<' if self.peek() == '=' => {
                 self.next();
                 Token::new(
                     TokenKind::LessThanOrEquals,
-                    Span {
-                        file_id: self.file_id,
-                        start: self.current() - 2,
-                        end: self.current(),
-                        line: self.line,
-                    },
+                    self.span_from(self.current() - 2),
                 )
             }
+            '<' if self.peek() == '<' => {
+                self.next();
+                Token::new(TokenKind::ShiftLeft, self.span_from(self.current() - 2))
+            }
             '<' => Token::new(TokenKind::LessThan, single_char_span),
 
             '(' => Token::new(TokenKind::OpenParen, single_char_span),
             ')' => Token::new(TokenKind::CloseParen, single_char_span),
             '{' => Token::new(TokenKind::OpenBrace, single_char_span),
             '}' => Token::new(TokenKind::CloseBrace, single_char_span),
+            '+' if self.peek() == '=' => {
+                self.next();
+                Token::new(TokenKind::AddAssign, self.span_from(self.current() - 2))
+            }
+            '-' if self.peek() == '=' => {
+                self.next();
+                Token::new(
+                    TokenKind::SubtractAssign,
+                    self.span_from(self.current() - 2),
+                )
+            }
             '+' => Token::new(TokenKind::Add, single_char_span),
             '-' => Token::new(TokenKind::Subtract, single_char_span),
             '*' => Token::new(TokenKind::Multiply, single_char_span),
+            '%' => Token::new(TokenKind::Modulo, single_char_span),
             ',' => Token::new(TokenKind::Comma, single_char_span),
             '[' => Token::new(TokenKind::OpenSquareBrace, single_char_span),
             ']' => Token::new(TokenKind::CloseSquareBrace, single_char_span),
 
             '!' if self.peek() == '=' => {
                 self.next();
-                Token::new(
-                    TokenKind::NotEqual,
-                    Span {
-                        file_id: self.file_id,
-                        start: self.current() - 2,
-                        end: self.current(),
-                        line: self.line,
-                    },
-                )
+                Token::new(TokenKind::NotEqual, self.span_from(self.current() - 2))
             }
             '!' => Token::new(TokenKind::Not, single_char_span),
 
+            '&' if self.peek() == '&' => {
+                self.next();
+                Token::new(TokenKind::And, self.span_from(self.current() - 2))
+            }
+            '|' if self.peek() == '|' => {
+                self.next();
+                Token::new(TokenKind::Or, self.span_from(self.current() - 2))
+            }
+            '&' => Token::new(TokenKind::BitAnd, single_char_span),
+            '|' => Token::new(TokenKind::BitOr, single_char_span),
+            '^' => Token::new(TokenKind::BitXor, single_char_span),
+            '~' => Token::new(TokenKind::BitNot, single_char_span),
+
             '"' => self.consume_quoted_string(),
             '/' => self.consume_comment_or_divide(),
             c @ '0'..='9' => self.consume_number(c),
-            ';' => Token::new(
-                TokenKind::EndOfLine,
-                Span {
-                    file_id: self.file_id,
-                    start: self.current() - 1,
-                    end: self.current(),
-                    line: self.line,
-                },
-            ),
-            '\n' => {
-                self.line += 1;
-
-                Token::new(
-                    TokenKind::Whitespace,
-                    Span {
-                        file_id: self.file_id,
-                        start: 0,
-                        end: 0,
-                        line: self.line,
-                    },
-                )
+            ';' => Token::new(TokenKind::EndOfLine, self.span_from(self.current() - 1)),
+            '\n' => Token::new(TokenKind::Whitespace, self.span_from(self.current())),
+            '?' => Token::new(TokenKind::Question, self.span_from(self.current() - 1)),
+            ':' => Token::new(TokenKind::Colon, self.span_from(self.current() - 1)),
+            '.' if self.peek() == '.' && self.peek_second() == '.' => {
+                self.next();
+                self.next();
+                Token::new(TokenKind::Ellipsis, self.span_from(self.current() - 3))
+            }
+            '.' if self.peek() == '.' => {
+                self.next();
+                Token::new(TokenKind::DotDot, self.span_from(self.current() - 2))
+            }
+            '.' => Token::new(TokenKind::Dot, self.span_from(self.current() - 1)),
+            c if c.is_ascii_whitespace() => {
+                Token::new(TokenKind::Whitespace, self.span_from(self.current()))
             }
-            ':' => Token::new(
-                TokenKind::Colon,
-                Span {
-                    file_id: self.file_id,
-                    start: self.current() - 1,
-                    end: self.current(),
-                    line: self.line,
-                },
-            ),
-            '.' => Token::new(
-                TokenKind::Dot,
-                Span {
-                    file_id: self.file_id,
-                    start: self.current() - 1,
-                    end: self.current(),
-                    line: self.line,
-                },
-            ),
-            c if c.is_ascii_whitespace() => Token::new(
-                TokenKind::Whitespace,
-                Span {
-                    file_id: self.file_id,
-                    start: 0,
-                    end: 0,
-                    line: self.line,
-                },
-            ),
             c if self.is_start_of_identifier(c) => self.consume_identifier(c),
 
-            c => todo!("{}", c),
+            _ => Token::new(TokenKind::Unknown, single_char_span),
         }
     }
 }
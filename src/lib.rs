@@ -1,8 +1,12 @@
 pub mod ast;
+pub mod bytecode;
 pub mod compiler;
+pub mod disassembler;
 pub mod error;
+pub mod fmt;
 pub mod instructions;
 pub mod lexer;
+pub mod lsp;
 pub mod macros;
 pub mod parser;
 pub mod scope;
@@ -10,3 +14,54 @@ pub mod stdlib;
 pub mod typechecker;
 pub mod types;
 pub mod vm;
+
+use compiler::{CompiledProgram, Compiler};
+use error::SolError;
+use lexer::Lexer;
+use parser::Parser;
+use typechecker::Typechecker;
+use vm::VM;
+
+/// Lex, parse, typecheck and compile `src` in one go.
+///
+/// ```
+/// let program = sol::compile_source(0, "let x = 1 + 2;").unwrap();
+/// assert_eq!(program.global_symbols.len(), 1);
+///
+/// sol::run_source("let y = 2 * 3;").unwrap();
+///
+/// assert!(matches!(
+///     sol::compile_source(0, "let z: int = \"three\";"),
+///     Err(sol::error::SolError::Typechecker(_))
+/// ));
+/// ```
+pub fn compile_source(file_id: usize, src: &str) -> Result<CompiledProgram, SolError> {
+    let lexer = Lexer::new(file_id, src);
+    let parser = Parser::new(lexer, src);
+
+    let mut statements = Vec::new();
+    let mut errors = Vec::new();
+    for statement in parser {
+        match statement {
+            Ok(statement) => statements.push(statement),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(SolError::Parser(errors));
+    }
+
+    Typechecker::default()
+        .analyze(&statements)
+        .typed_statements?;
+
+    Ok(Compiler::new().compile(&statements)?)
+}
+
+/// [`compile_source`] and then run the program to completion.
+pub fn run_source(src: &str) -> Result<(), SolError> {
+    let program = compile_source(0, src)?;
+
+    Ok(VM::new(program).run()?)
+}
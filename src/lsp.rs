@@ -0,0 +1,440 @@
+use crate::{
+    ast::Statement,
+    lexer::{Keyword, Lexer, Span, TokenKind},
+    parser::{Parser, ParserError},
+    typechecker::{Symbol, SymbolKind, Typechecker, TypecheckerError},
+};
+use codespan_reporting::diagnostic::Severity;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, LogMessage,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{DocumentSymbolRequest, HoverRequest, Request as _},
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    Hover, HoverContents, HoverParams, HoverProviderCapability, LogMessageParams, MarkupContent,
+    MarkupKind, MessageType, OneOf, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+};
+use std::{collections::HashMap, error::Error};
+
+pub type LspError = Box<dyn Error>;
+
+// serves until the client asks for a shutdown
+pub fn run(connection: Connection) -> Result<(), LspError> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..Default::default()
+    };
+
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    // keyed by the uri's text, `Uri` caches its parts in cells
+    let mut documents = HashMap::<String, String>::new();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+
+                // a request the server can't read is the client's problem, it gets told so
+                let id = request.id.clone();
+                let response = handle_request(&documents, request).unwrap_or_else(|e| {
+                    Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string())
+                });
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                let method = notification.method.clone();
+                let uri = match handle_notification(&mut documents, notification) {
+                    Ok(Some(uri)) => uri,
+                    Ok(None) => continue,
+                    // notifications have no response, the client's log is the only place to say
+                    Err(e) => {
+                        let params = LogMessageParams {
+                            typ: MessageType::ERROR,
+                            message: format!("invalid {method}: {e}"),
+                        };
+                        connection
+                            .sender
+                            .send(Message::Notification(Notification::new(
+                                LogMessage::METHOD.to_owned(),
+                                params,
+                            )))?;
+
+                        continue;
+                    }
+                };
+
+                let diagnostics = documents
+                    .get(uri.as_str())
+                    .map(|text| diagnostics(text))
+                    .unwrap_or_default();
+
+                let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+                connection
+                    .sender
+                    .send(Message::Notification(Notification::new(
+                        PublishDiagnostics::METHOD.to_owned(),
+                        params,
+                    )))?;
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+// returns the document that changed, if any
+fn handle_notification(
+    documents: &mut HashMap<String, String>,
+    notification: Notification,
+) -> Result<Option<Uri>, LspError> {
+    match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: <DidOpenTextDocument as lsp_types::notification::Notification>::Params =
+                serde_json::from_value(notification.params)?;
+            let uri = params.text_document.uri;
+            documents.insert(uri.as_str().to_owned(), params.text_document.text);
+
+            Ok(Some(uri))
+        }
+        DidChangeTextDocument::METHOD => {
+            let params: <DidChangeTextDocument as lsp_types::notification::Notification>::Params =
+                serde_json::from_value(notification.params)?;
+            let uri = params.text_document.uri;
+            // full sync, the last change is the whole document
+            if let Some(change) = params.content_changes.into_iter().last() {
+                documents.insert(uri.as_str().to_owned(), change.text);
+            }
+
+            Ok(Some(uri))
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: <DidCloseTextDocument as lsp_types::notification::Notification>::Params =
+                serde_json::from_value(notification.params)?;
+            let uri = params.text_document.uri;
+            documents.remove(uri.as_str());
+
+            Ok(Some(uri))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn handle_request(
+    documents: &HashMap<String, String>,
+    request: Request,
+) -> Result<Response, LspError> {
+    let Request { id, method, params } = request;
+
+    match method.as_str() {
+        DocumentSymbolRequest::METHOD => {
+            let params: DocumentSymbolParams = serde_json::from_value(params)?;
+            let symbols = documents
+                .get(params.text_document.uri.as_str())
+                .map(|text| document_symbols(text))
+                .unwrap_or_default();
+
+            Ok(Response::new_ok(
+                id,
+                DocumentSymbolResponse::Nested(symbols),
+            ))
+        }
+        HoverRequest::METHOD => {
+            let params: HoverParams = serde_json::from_value(params)?;
+            let position = params.text_document_position_params;
+            let hover = documents
+                .get(position.text_document.uri.as_str())
+                .and_then(|text| hover(text, position.position));
+
+            Ok(Response::new_ok(id, hover))
+        }
+        _ => Ok(unknown_method(id, &method)),
+    }
+}
+
+fn unknown_method(id: RequestId, method: &str) -> Response {
+    Response::new_err(
+        id,
+        ErrorCode::MethodNotFound as i32,
+        format!("unsupported method {method}"),
+    )
+}
+
+// lsp positions count utf-16 code units, spans are byte offsets
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character = before[line_start..].encode_utf16().count();
+
+    Position::new(line as u32, character as u32)
+}
+
+fn offset(text: &str, position: Position) -> usize {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= position.character as usize || c == '\n' {
+            return line_start + i;
+        }
+
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+fn range(text: &str, start: usize, end: usize) -> Range {
+    Range::new(position(text, start), position(text, end))
+}
+
+// the statements that parsed, with where each was written
+fn parse(text: &str) -> (Vec<Statement>, Vec<Span>, Vec<Diagnostic>) {
+    let mut parser = Parser::new(Lexer::new(0, text), text);
+
+    let mut statements = Vec::new();
+    let mut spans = Vec::new();
+    let mut diagnostics = Vec::new();
+    while let Some(result) = parser.next() {
+        match result {
+            Ok(statement) => {
+                statements.push(statement);
+                spans.extend(parser.statement_span());
+            }
+            Err(ParserError::Diagnostic(diagnostic)) => {
+                diagnostics.push(convert_diagnostic(text, diagnostic))
+            }
+        }
+    }
+
+    (statements, spans, diagnostics)
+}
+
+fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let (statements, spans, mut diagnostics) = parse(text);
+
+    // the typechecker can't make sense of a partial program
+    if diagnostics.is_empty() {
+        let analysis = Typechecker::default()
+            .with_statement_spans(spans)
+            .analyze(&statements);
+        match analysis.typed_statements {
+            Ok(_) => {}
+            Err(TypecheckerError::Diagnostic(diagnostic)) => {
+                diagnostics.push(convert_diagnostic(text, diagnostic))
+            }
+            Err(e) => {
+                // most typechecker errors don't carry spans, they cover the statement they came from
+                let range = analysis
+                    .error_span
+                    .map(|span| range(text, span.start, span.end))
+                    .unwrap_or_default();
+                diagnostics.push(lsp_diagnostic(
+                    range,
+                    DiagnosticSeverity::ERROR,
+                    e.to_string(),
+                ));
+            }
+        }
+
+        diagnostics.extend(
+            analysis
+                .warnings
+                .into_iter()
+                .map(|warning| convert_diagnostic(text, warning)),
+        );
+    }
+
+    diagnostics
+}
+
+fn convert_diagnostic(
+    text: &str,
+    diagnostic: codespan_reporting::diagnostic::Diagnostic<usize>,
+) -> Diagnostic {
+    let range = diagnostic
+        .labels
+        .first()
+        .map(|label| range(text, label.range.start, label.range.end))
+        .unwrap_or_default();
+
+    let message = match diagnostic.labels.first() {
+        Some(label) if !label.message.is_empty() => {
+            format!("{}: {}", diagnostic.message, label.message)
+        }
+        _ => diagnostic.message,
+    };
+
+    let severity = match diagnostic.severity {
+        Severity::Bug | Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+        Severity::Note => DiagnosticSeverity::INFORMATION,
+        Severity::Help => DiagnosticSeverity::HINT,
+    };
+
+    lsp_diagnostic(range, severity, message)
+}
+
+fn lsp_diagnostic(range: Range, severity: DiagnosticSeverity, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some("sol".to_owned()),
+        message,
+        ..Default::default()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum DefinitionKind {
+    Variable,
+    Constant,
+    Function,
+}
+
+struct Definition<'a> {
+    name: &'a str,
+    span: Span,
+    kind: DefinitionKind,
+    top_level: bool,
+}
+
+// works from the tokens so the outline survives code that doesn't parse
+fn definitions(text: &str) -> Vec<Definition<'_>> {
+    let tokens = Lexer::new(0, text).collect::<Vec<_>>();
+    let is_identifier = |i: usize| {
+        tokens
+            .get(i)
+            .is_some_and(|t| *t.kind() == TokenKind::Identifier)
+    };
+
+    let mut definitions = Vec::new();
+    let mut depth = 0;
+    let mut in_parameters = false;
+    for (i, token) in tokens.iter().enumerate() {
+        let next_kind = tokens.get(i + 1).map(|t| *t.kind());
+        let defined = match token.kind() {
+            TokenKind::OpenBrace => {
+                depth += 1;
+                None
+            }
+            TokenKind::CloseBrace => {
+                depth -= 1;
+                None
+            }
+            TokenKind::CloseParen => {
+                in_parameters = false;
+                None
+            }
+            TokenKind::Keyword(Keyword::Let)
+                if next_kind == Some(TokenKind::Keyword(Keyword::Mut)) =>
+            {
+                Some((i + 2, DefinitionKind::Variable))
+            }
+            TokenKind::Keyword(Keyword::Let | Keyword::For) => {
+                Some((i + 1, DefinitionKind::Variable))
+            }
+            TokenKind::Keyword(Keyword::Const) => Some((i + 1, DefinitionKind::Constant)),
+            TokenKind::Keyword(Keyword::Fn) => {
+                in_parameters = true;
+                Some((i + 1, DefinitionKind::Function))
+            }
+            TokenKind::Identifier if in_parameters && next_kind == Some(TokenKind::Colon) => {
+                Some((i, DefinitionKind::Variable))
+            }
+            _ => None,
+        };
+
+        if let Some((index, kind)) = defined {
+            if is_identifier(index) {
+                definitions.push(Definition {
+                    name: tokens[index].text(text),
+                    span: *tokens[index].span(),
+                    kind,
+                    top_level: depth == 0,
+                });
+            }
+        }
+    }
+
+    definitions
+}
+
+#[allow(deprecated)]
+fn document_symbols(text: &str) -> Vec<DocumentSymbol> {
+    definitions(text)
+        .into_iter()
+        .filter(|d| d.top_level && d.kind != DefinitionKind::Variable)
+        .map(|d| {
+            let range = range(text, d.span.start, d.span.end);
+            let kind = match d.kind {
+                DefinitionKind::Function => lsp_types::SymbolKind::FUNCTION,
+                _ => lsp_types::SymbolKind::CONSTANT,
+            };
+
+            DocumentSymbol {
+                name: d.name.to_owned(),
+                detail: None,
+                kind,
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: range,
+                children: None,
+            }
+        })
+        .collect()
+}
+
+fn hover(text: &str, position: Position) -> Option<Hover> {
+    let offset = offset(text, position);
+    // a cursor between `(` and a name is on the name
+    let token = Lexer::new(0, text).find(|t| {
+        *t.kind() == TokenKind::Identifier && t.span().start <= offset && offset <= t.span().end
+    })?;
+
+    let name = token.text(text);
+    let (statements, spans, _) = parse(text);
+    let symbols = Typechecker::default()
+        .with_statement_spans(spans)
+        .analyze(&statements)
+        .symbols;
+
+    // the closest definition written before the cursor, functions can also be called before theirs
+    let named = || symbols.iter().filter(|s| s.name == name);
+    let symbol = named()
+        .filter(|s| s.span.start <= offset)
+        .max_by_key(|s| s.span.start)
+        .or_else(|| {
+            named()
+                .filter(|s| s.kind == SymbolKind::Function)
+                .min_by_key(|s| s.span.start)
+        })?;
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("```sol\n{}\n```", describe(symbol)),
+        }),
+        range: Some(range(text, token.span().start, token.span().end)),
+    })
+}
+
+fn describe(symbol: &Symbol) -> String {
+    match symbol.kind {
+        SymbolKind::Variable => format!("{}: {}", symbol.name, symbol.defined_type),
+        SymbolKind::Function => format!("fn {} -> {}", symbol.name, symbol.defined_type),
+    }
+}
@@ -48,3 +48,28 @@ macro_rules! impl_binary_comparator {
         }
     }
 }
+
+#[macro_export]
+macro_rules! impl_integer_op {
+    ($registers:expr, $dest: expr, $lhs:expr, $symbol:literal, $rhs:expr, $op:expr) => {
+        match (&$registers[$lhs], &$registers[$rhs]) {
+            (VMValue::Literal(lhs), VMValue::Literal(rhs)) => match (lhs.as_ref(), rhs.as_ref()) {
+                (types::Literal::Integer(lhs), types::Literal::Integer(rhs)) => {
+                    let result: Result<i64, ExecutionError> = $op(*lhs, *rhs);
+                    $registers[$dest] =
+                        VMValue::Literal(Cow::Owned(types::Literal::Integer(result?)))
+                }
+                _ => {
+                    return Err(ExecutionError::InvalidOperation {
+                        cause: format!("'{}' must be used on integers", $symbol),
+                    })
+                }
+            },
+            _ => {
+                return Err(ExecutionError::InvalidOperation {
+                    cause: format!("'{}' must be used on literals only", $symbol),
+                })
+            }
+        }
+    };
+}
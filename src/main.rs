@@ -3,7 +3,8 @@ use codespan_reporting::{
     files::SimpleFiles,
     term::termcolor::{ColorChoice, StandardStream},
 };
-use compiler::Compiler;
+use compiler::{CompiledProgram, Compiler};
+use disassembler::disassemble;
 use error::DiagnosticEmitted;
 use lexer::Lexer;
 use parser::Parser;
@@ -13,17 +14,22 @@ use std::{
     path::Path,
     process::ExitCode,
     str::FromStr,
+    time::Instant,
 };
 use tracing::Level;
 use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
 use typechecker::Typechecker;
-use vm::VM;
+use vm::{ExecutionError, StepControl, VMValue, VM};
 
 mod ast;
+mod bytecode;
 mod compiler;
+mod disassembler;
 mod error;
+mod fmt;
 mod instructions;
 mod lexer;
+mod lsp;
 mod macros;
 mod parser;
 mod scope;
@@ -37,9 +43,7 @@ mod vm;
 // TODO: Better dump printing
 // TODO: Add ability to include other files? C-style #include? files that are included can't have
 //       global code, only the "main" file can... for now
-// TODO: Add generic statemap type thing passed to each stdlib function
-//       This will let me trivially add networking
-//       Also move to another crate due to dependencies
+// TODO: Move stdlib to another crate due to dependencies
 // TODO: Increase register count / reuse registers in some way....
 //       Find a way to reclaim registers once they are proved unused
 //       Collect a list of registers as we parse that won't be reused in a scope
@@ -47,7 +51,6 @@ mod vm;
 // TODO: Allow anonymous functions
 // TODO: Allows functions in objects in some way
 // TODO: Multi-crate setup
-// TODO: LSP :)
 
 #[derive(clap::Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -63,6 +66,24 @@ enum Commands {
         file: String,
         #[arg(short, long, default_value_t = false)]
         no_typecheck: bool,
+        /// print the value of the last expression in the file
+        #[arg(long, default_value_t = false)]
+        print_result: bool,
+        /// print execution statistics to stderr
+        #[arg(long, default_value_t = false)]
+        stats: bool,
+        /// print the instruction count and elapsed time to stderr
+        #[arg(long, default_value_t = false)]
+        time: bool,
+        /// print every executed instruction to stderr
+        #[arg(long, default_value_t = false)]
+        trace: bool,
+        /// fail instead of running if the typechecker warns about anything
+        #[arg(long, default_value_t = false)]
+        deny_warnings: bool,
+        /// warn when a function's return value is thrown away
+        #[arg(long, default_value_t = false)]
+        warn_unused_results: bool,
     },
     /// dump internal state
     Dump {
@@ -71,7 +92,19 @@ enum Commands {
         target: DumpTarget,
         #[arg(long, default_value_t = false)]
         typecheck: bool,
+        /// write compiled bytecode to this file instead of printing it, run it with `sol run`
+        #[arg(long)]
+        output: Option<String>,
     },
+    /// rewrite a program file in the canonical format
+    Fmt {
+        file: String,
+        /// exit with an error instead of writing if the file isn't formatted
+        #[arg(long, default_value_t = false)]
+        check: bool,
+    },
+    /// start a language server over stdio
+    Lsp,
 }
 
 #[derive(ValueEnum, Clone, Default, Debug)]
@@ -104,7 +137,7 @@ fn read_file_to_string(path_unchecked: &str) -> Result<String, std::io::Error> {
     Ok(buffer)
 }
 
-fn main_internal(no_color: bool) -> Result<(), Box<dyn std::error::Error>> {
+fn main_internal(no_color: bool) -> Result<ExitCode, Box<dyn std::error::Error>> {
     let args = Args::parse();
 
     let mut code_reporting_file_db = SimpleFiles::new();
@@ -118,50 +151,137 @@ fn main_internal(no_color: bool) -> Result<(), Box<dyn std::error::Error>> {
     let config = codespan_reporting::term::Config::default();
 
     match args.command {
-        Commands::Run { file, no_typecheck } => {
-            let buffer = read_file_to_string(&file)?;
-            let file_id = code_reporting_file_db.add(&file, &buffer);
-
-            let lexer = Lexer::new(file_id, &buffer);
-            let parser = Parser::new(lexer, &buffer);
+        Commands::Run {
+            file,
+            no_typecheck,
+            print_result,
+            stats,
+            time,
+            trace,
+            deny_warnings,
+            warn_unused_results,
+        } => {
+            // already compiled with `sol dump --target bytecode --output`
+            let program = if Path::new(&file)
+                .extension()
+                .is_some_and(|ext| ext == "solc")
+            {
+                CompiledProgram::from_bytes(&std::fs::read(&file)?)?
+            } else {
+                let buffer = read_file_to_string(&file)?;
+                let file_id = code_reporting_file_db.add(&file, &buffer);
+
+                let lexer = Lexer::new(file_id, &buffer);
+                let parser = Parser::new(lexer, &buffer);
+
+                let (statements, spans) = parser.collect_and_emit_diagnostics(
+                    &writer,
+                    &config,
+                    &code_reporting_file_db,
+                )?;
+
+                if !no_typecheck {
+                    let mut typechecker = Typechecker::default().with_statement_spans(spans);
+                    if warn_unused_results {
+                        typechecker = typechecker.with_unused_result_warnings();
+                    }
+                    let analysis = typechecker.analyze(&statements);
+                    analysis.typed_statements.map_err(|e| {
+                        e.emit_diagnostic(&writer, &config, &code_reporting_file_db)
+                    })?;
+
+                    for warning in &analysis.warnings {
+                        codespan_reporting::term::emit(
+                            &mut writer.lock(),
+                            &config,
+                            &code_reporting_file_db,
+                            warning,
+                        )?;
+                    }
 
-            let statements =
-                parser.collect_and_emit_diagnostics(&writer, &config, &code_reporting_file_db)?;
+                    if deny_warnings && !analysis.warnings.is_empty() {
+                        return Err(DiagnosticEmitted.into());
+                    }
+                }
 
-            if !no_typecheck {
-                let typechecker = Typechecker::default();
-                typechecker.check(&statements)?;
+                let compiler = Compiler::new();
+                compiler.compile_and_emit_diagnostics(
+                    &statements,
+                    &writer,
+                    &config,
+                    &code_reporting_file_db,
+                )?
+            };
+
+            let mut vm = VM::new(program).with_stats(stats);
+            if trace {
+                // TODO: source line for each instruction once the ast has spans
+                vm = vm.with_step_hook(|step| {
+                    eprintln!(
+                        "{:<12} {:04}  {}",
+                        step.function_name, step.ip, step.instruction
+                    );
+                    StepControl::Continue
+                });
             }
 
-            let compiler = Compiler::new();
-            let program = compiler.compile_and_emit_diagnostics(
-                &statements,
-                &writer,
-                &config,
-                &code_reporting_file_db,
-            )?;
+            let started = Instant::now();
+            let result = if print_result {
+                vm.run_with_result().map(|result| match result {
+                    None | Some(VMValue::Empty | VMValue::Nil) => {}
+                    Some(result) => println!("{result}"),
+                })
+            } else {
+                vm.run()
+            };
 
-            let vm = VM::new(program);
+            let elapsed = started.elapsed();
+
+            if stats {
+                eprint!("{}", vm.stats());
+            }
 
-            vm.run()?;
+            if time {
+                eprintln!(
+                    "instructions: {}, elapsed: {}ms",
+                    vm.stats().instructions_executed,
+                    elapsed.as_millis()
+                );
+            }
+
+            match result {
+                Ok(_) => {}
+                // only a byte makes it to the OS, anything that doesn't fit is a plain failure
+                Err(ExecutionError::Exit { code }) => {
+                    return Ok(u8::try_from(code).map_or(ExitCode::FAILURE, ExitCode::from))
+                }
+                Err(e) => return Err(e.into()),
+            }
         }
         Commands::Dump {
             file,
             target,
             typecheck,
+            output,
         } => {
+            if output.is_some() && !matches!(target, DumpTarget::Bytecode) {
+                return Err("--output only applies to `--target bytecode`".into());
+            }
+
             let buffer = read_file_to_string(&file)?;
+            let file_id = code_reporting_file_db.add(&file, &buffer);
 
             match target {
                 DumpTarget::Tokens => {
-                    let tokens = Lexer::new(0, &buffer).collect::<Vec<_>>();
-                    tracing::info!("{:#?}", tokens);
+                    for token in Lexer::new(file_id, &buffer) {
+                        println!("{:?} {:?}", token, token.text(&buffer));
+                    }
                 }
                 DumpTarget::Ast => {
-                    let lexer = Lexer::new(0, &buffer);
+                    let lexer = Lexer::new(file_id, &buffer);
                     let parser = Parser::new(lexer, &buffer);
 
-                    let statements = parser.collect_and_emit_diagnostics(
+                    let (statements, _) = parser.collect_and_emit_diagnostics(
                         &writer,
                         &config,
                         &code_reporting_file_db,
@@ -169,16 +289,18 @@ fn main_internal(no_color: bool) -> Result<(), Box<dyn std::error::Error>> {
 
                     if typecheck {
                         let typechecker = Typechecker::default();
-                        typechecker.check(&statements)?;
+                        typechecker.check(&statements).map_err(|e| {
+                            e.emit_diagnostic(&writer, &config, &code_reporting_file_db)
+                        })?;
                     }
 
-                    tracing::info!("{statements:#?}")
+                    println!("{statements:#?}")
                 }
                 DumpTarget::Bytecode => {
-                    let lexer = Lexer::new(0, &buffer);
+                    let lexer = Lexer::new(file_id, &buffer);
                     let parser = Parser::new(lexer, &buffer);
 
-                    let statements = parser.collect_and_emit_diagnostics(
+                    let (statements, _) = parser.collect_and_emit_diagnostics(
                         &writer,
                         &config,
                         &code_reporting_file_db,
@@ -186,32 +308,65 @@ fn main_internal(no_color: bool) -> Result<(), Box<dyn std::error::Error>> {
 
                     if typecheck {
                         let typechecker = Typechecker::default();
-                        typechecker.check(&statements)?;
+                        typechecker.check(&statements).map_err(|e| {
+                            e.emit_diagnostic(&writer, &config, &code_reporting_file_db)
+                        })?;
                     }
 
                     let compiler = Compiler::new();
 
                     let program = compiler.compile(&statements)?;
-                    tracing::info!("{:#?}", program);
+                    match output {
+                        Some(output) => std::fs::write(output, program.to_bytes())?,
+                        None => println!("{}", disassemble(&program)),
+                    }
                 }
                 DumpTarget::Typecheck => {
-                    let lexer = Lexer::new(0, &buffer);
+                    let lexer = Lexer::new(file_id, &buffer);
                     let parser = Parser::new(lexer, &buffer);
                     let typechecker = Typechecker::default();
 
-                    let statements = parser.collect_and_emit_diagnostics(
+                    let (statements, _) = parser.collect_and_emit_diagnostics(
                         &writer,
                         &config,
                         &code_reporting_file_db,
                     )?;
 
-                    typechecker.check(&statements)?;
+                    let typed_statements = typechecker.check(&statements).map_err(|e| {
+                        e.emit_diagnostic(&writer, &config, &code_reporting_file_db)
+                    })?;
+                    println!("{typed_statements:#?}")
+                }
+            }
+        }
+        Commands::Fmt { file, check } => {
+            let buffer = read_file_to_string(&file)?;
+            let file_id = code_reporting_file_db.add(&file, &buffer);
+
+            let lexer = Lexer::new(file_id, &buffer);
+            let parser = Parser::new(lexer, &buffer);
+
+            let (statements, _) =
+                parser.collect_and_emit_diagnostics(&writer, &config, &code_reporting_file_db)?;
+
+            let formatted = fmt::format(&statements);
+            if formatted != buffer {
+                if check {
+                    eprintln!("{file} is not formatted");
+                    return Ok(ExitCode::FAILURE);
                 }
+
+                std::fs::write(&file, formatted)?;
             }
         }
+        Commands::Lsp => {
+            let (connection, io_threads) = lsp_server::Connection::stdio();
+            lsp::run(connection)?;
+            io_threads.join()?;
+        }
     };
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
 fn main() -> ExitCode {
@@ -232,7 +387,7 @@ fn main() -> ExitCode {
         .init();
 
     match main_internal(no_color) {
-        Ok(_) => ExitCode::SUCCESS,
+        Ok(code) => code,
         Err(e) => {
             let err = e.downcast_ref::<DiagnosticEmitted>();
 
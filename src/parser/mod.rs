@@ -1,7 +1,7 @@
 use crate::{
     ast::{self, FunctionParameter, Statement},
     error::DiagnosticEmitted,
-    lexer::{Span, Token, TokenKind},
+    lexer::{Keyword, Span, Token, TokenKind},
     types,
 };
 use codespan_reporting::{
@@ -9,18 +9,58 @@ use codespan_reporting::{
     files::Files,
     term::termcolor::StandardStream,
 };
-use ordermap::OrderMap;
-use std::iter::Peekable;
+use std::{collections::HashMap, iter::Peekable};
 
 mod error;
 pub use error::ParserError;
 
+fn duplicate(what: &str, name: &str, span: Span, previous: Span) -> ParserError {
+    let diagnostic = Diagnostic::error()
+        .with_message(format!("duplicate {what} `{name}`"))
+        .with_labels(vec![
+            Label::primary(span.file_id, span).with_message(format!("`{name}` written again here")),
+            Label::secondary(previous.file_id, previous).with_message("first written here"),
+        ]);
+
+    ParserError::Diagnostic(diagnostic)
+}
+
+fn invalid_token(token: &Token) -> ParserError {
+    let (message, label) = match token.kind() {
+        TokenKind::UnterminatedString => ("unterminated string", "add a closing `\"`"),
+        _ => ("unknown character", "this can't start a token"),
+    };
+
+    let diagnostic = Diagnostic::error()
+        .with_message(message)
+        .with_labels(vec![
+            Label::primary(token.span().file_id, token.span()).with_message(label)
+        ]);
+
+    ParserError::Diagnostic(diagnostic)
+}
+
 pub struct Parser<'a, I>
 where
     I: Iterator<Item = Token>,
 {
     tokens: Peekable<I>,
     input: &'a str,
+    // used to find the end of a broken statement when recovering
+    consumed: usize,
+    last_consumed: Option<TokenKind>,
+    // where the last consumed token ended, closes off statement spans
+    last_consumed_end: usize,
+    // the lexer doesn't hand us an end of file token, this lets us make one for the right file
+    file_id: usize,
+    // set while directly inside a function body, where a final expression without `;` is returned
+    tail_returns: bool,
+    // how many blocks we're inside, the last statement of a block can leave off its `;`
+    block_depth: usize,
+    // the top level statement most recently handed out, blocks keep spans for their own
+    statement_span: Option<Span>,
+    // the first token the lexer couldn't make sense of in this statement, it outranks the parser's error
+    invalid_token: Option<Token>,
 }
 
 impl<'a, I> Parser<'a, I>
@@ -31,60 +71,81 @@ where
         Self {
             tokens: tokens.peekable(),
             input,
+            consumed: 0,
+            last_consumed: None,
+            last_consumed_end: 0,
+            file_id: 0,
+            tail_returns: false,
+            block_depth: 0,
+            statement_span: None,
+            invalid_token: None,
         }
     }
 
+    // where the statement the iterator last returned was written
+    pub fn statement_span(&self) -> Option<Span> {
+        self.statement_span
+    }
+
     fn text(&self, token: &Token) -> &'a str {
         token.text(self.input)
     }
 
     fn peek(&mut self) -> TokenKind {
-        *self
-            .tokens
-            .peek()
-            .map(|t| t.kind())
-            .unwrap_or(&TokenKind::EndOfFile)
+        *self.peek_token().kind()
+    }
+
+    fn note_invalid(&mut self, token: Token) {
+        if matches!(
+            token.kind(),
+            TokenKind::Unknown | TokenKind::UnterminatedString
+        ) {
+            self.invalid_token.get_or_insert(token);
+        }
     }
 
     fn peek_token(&mut self) -> Token {
-        *self.tokens.peek().unwrap_or(&Token::new(
-            TokenKind::EndOfFile,
-            Span {
-                file_id: 0,
-                start: 0,
-                end: 0,
-                line: 0,
-            },
-        ))
+        match self.tokens.peek() {
+            Some(&token) => {
+                self.note_invalid(token);
+                token
+            }
+            // just after the last real token, trailing whitespace and comments don't matter
+            None => Token::new(
+                TokenKind::EndOfFile,
+                Span::end_of(self.file_id, &self.input[..self.last_consumed_end]),
+            ),
+        }
     }
 
     fn parse_const(&mut self) -> Result<ast::Statement, ParserError> {
-        let name = self.consume(TokenKind::Identifier)?.text(self.input);
+        let name_token = self.consume_name()?;
+        let name = self.text(&name_token);
 
         let type_name = if self.peek() == TokenKind::Colon {
             self.consume(TokenKind::Colon)?;
-            let type_name_token = self.consume(TokenKind::Identifier)?;
-
-            Some(self.text(&type_name_token).to_owned())
+            Some(self.parse_type_name()?)
         } else {
             None
         };
 
         self.consume(TokenKind::Assignment)?;
-        // could be expr in future
-        let literal = self.parse_literal()?;
+        // the typechecker makes sure this is only literals and other consts
+        let value = self.parse_expression(0)?;
 
-        self.consume(TokenKind::EndOfLine)?;
+        self.consume_end_of_statement()?;
 
         Ok(ast::Statement::Const {
             name: name.to_owned(),
-            value: literal,
+            value,
             type_name,
+            span: *name_token.span(),
         })
     }
 
     fn parse_function(&mut self) -> Result<ast::Function, ParserError> {
-        let name = self.consume(TokenKind::Identifier)?.text(self.input);
+        let name_token = self.consume_name()?;
+        let name = self.text(&name_token);
 
         let _open_paren = self.consume(TokenKind::OpenParen)?;
         let args = self.parse_parameters()?;
@@ -94,38 +155,33 @@ where
             self.consume(TokenKind::Subtract)?;
             self.consume(TokenKind::GreaterThan)?;
 
-            let return_type_name_token = self.consume(TokenKind::Identifier)?;
-            Some(self.text(&return_type_name_token).to_owned())
+            Some(self.parse_type_name()?)
         } else {
             None
         };
 
-        let block = self.parse_block()?;
+        let block = self.parse_block_with(true)?;
 
         Ok(ast::Function::new(
             name.to_owned(),
             args,
             block.into(),
             return_type_name,
+            *name_token.span(),
         ))
     }
 
     fn parse_let(&mut self) -> Result<ast::Statement, ParserError> {
-        let maybe_mutable_token = self.peek_token();
-        let has_mutable_token = *maybe_mutable_token.kind() == TokenKind::Identifier
-            && self.text(&maybe_mutable_token) == "mut";
-
+        let has_mutable_token = self.peek() == TokenKind::Keyword(Keyword::Mut);
         if has_mutable_token {
-            self.consume(TokenKind::Identifier)?;
+            self.consume(TokenKind::Keyword(Keyword::Mut))?;
         }
 
-        let variable_name = self.consume(TokenKind::Identifier)?;
+        let variable_name = self.consume_name()?;
 
         let type_name = if self.peek() == TokenKind::Colon {
             self.consume(TokenKind::Colon)?;
-            let type_name_token = self.consume(TokenKind::Identifier)?;
-
-            Some(self.text(&type_name_token).to_owned())
+            Some(self.parse_type_name()?)
         } else {
             None
         };
@@ -133,25 +189,49 @@ where
         self.consume(TokenKind::Assignment)?;
 
         let expression = self.parse_expression(0)?;
-        self.consume(TokenKind::EndOfLine)?;
+        self.consume_end_of_statement()?;
 
         Ok(ast::Statement::Let {
             name: self.text(&variable_name).to_owned(),
             value: expression.into(),
             is_mutable: has_mutable_token,
             type_name,
+            span: *variable_name.span(),
         })
     }
 
-    fn parse_let_mutation(&mut self, name: &str) -> Result<ast::Statement, ParserError> {
-        self.consume(TokenKind::Assignment)?;
+    fn parse_let_mutation(
+        &mut self,
+        name: &str,
+        span: Span,
+    ) -> Result<ast::Statement, ParserError> {
+        // `x += 1` is written out as `x = x + 1`
+        let compound = match self.peek() {
+            TokenKind::AddAssign => Some(ast::Operator::Plus),
+            TokenKind::SubtractAssign => Some(ast::Operator::Minus),
+            _ => None,
+        };
+        if compound.is_some() {
+            self.next();
+        } else {
+            self.consume(TokenKind::Assignment)?;
+        }
 
-        let expression = self.parse_expression(0)?;
-        self.consume(TokenKind::EndOfLine)?;
+        let mut expression = self.parse_expression(0)?;
+        self.consume_end_of_statement()?;
+
+        if let Some(op) = compound {
+            expression = ast::Expression::Infix {
+                op,
+                lhs: ast::Expression::Variable(name.to_owned()).into(),
+                rhs: expression.into(),
+            };
+        }
 
         Ok(ast::Statement::Reassignment {
             name: name.to_owned(),
             value: expression.into(),
+            span,
         })
     }
 
@@ -159,11 +239,7 @@ where
         let token = self.consume(TokenKind::Literal)?;
         let text = self.text(&token);
 
-        let expr = if text == "true" {
-            ast::Expression::Literal(types::Literal::Boolean(true))
-        } else if text == "false" {
-            ast::Expression::Literal(types::Literal::Boolean(false))
-        } else if text.starts_with('"') && text.ends_with('"') {
+        let expr = if text.starts_with('"') && text.ends_with('"') {
             ast::Expression::Literal(types::Literal::String(text[1..text.len() - 1].to_owned()))
         } else if text.contains('.') {
             let float = text.parse::<f64>();
@@ -178,7 +254,13 @@ where
 
             ast::Expression::Literal(types::Literal::Float(float.unwrap()))
         } else {
-            let integer = text.parse::<i64>();
+            let integer = if let Some(hex) = text.strip_prefix("0x") {
+                i64::from_str_radix(hex, 16)
+            } else if let Some(binary) = text.strip_prefix("0b") {
+                i64::from_str_radix(binary, 2)
+            } else {
+                text.parse::<i64>()
+            };
             if integer.is_err() {
                 let diagnostic = Diagnostic::error()
                     .with_message("could not convert to integer")
@@ -196,22 +278,32 @@ where
     fn parse_object(&mut self) -> Result<ast::Expression, ParserError> {
         self.consume(TokenKind::OpenBrace)?;
 
-        let mut fields = OrderMap::new();
+        let mut fields = Vec::new();
+        // spreads are allowed to overwrite, only names written out twice are rejected
+        let mut seen = HashMap::new();
         // left side is identifier only.
         loop {
             if self.peek() == TokenKind::CloseBrace {
                 break;
             }
 
-            let key = self.consume(TokenKind::Identifier)?;
-            self.consume(TokenKind::Colon)?;
-            let value = self.parse_expression(0)?;
+            if self.peek() == TokenKind::Ellipsis {
+                self.consume(TokenKind::Ellipsis)?;
+                fields.push(ast::ObjectField::Spread(self.parse_expression(0)?));
+            } else {
+                let key = self.consume_name()?;
+                let name = self.text(&key).to_string();
+                if let Some(previous) = seen.insert(name.clone(), *key.span()) {
+                    return Err(duplicate("field", &name, *key.span(), previous));
+                }
 
-            fields.insert(self.text(&key).to_string(), value);
+                self.consume(TokenKind::Colon)?;
+                let value = self.parse_expression(0)?;
 
-            if self.peek() == TokenKind::Comma {
-                self.consume(TokenKind::Comma)?;
-            } else {
+                fields.push(ast::ObjectField::Named(name, value));
+            }
+
+            if !self.parse_list_separator(TokenKind::CloseBrace, '}')? {
                 break;
             }
         }
@@ -221,6 +313,38 @@ where
         Ok(ast::Expression::Object { fields })
     }
 
+    // items are separated by commas and the last one may have a comma after it too,
+    // gives back whether there are more items before `close`
+    fn parse_list_separator(
+        &mut self,
+        close: TokenKind,
+        symbol: char,
+    ) -> Result<bool, ParserError> {
+        match self.peek() {
+            TokenKind::Comma => {
+                self.consume(TokenKind::Comma)?;
+                Ok(self.peek() != close)
+            }
+            kind if kind == close => Ok(false),
+            _ => {
+                let next = self.peek_token();
+                // points at the gap between the two items
+                let gap = Span {
+                    start: self.last_consumed_end,
+                    end: next.span().start,
+                    ..*next.span()
+                };
+
+                let diagnostic = Diagnostic::error()
+                    .with_message(format!("expected `,` or `{symbol}`"))
+                    .with_labels(vec![Label::primary(gap.file_id, gap)
+                        .with_message(format!("missing `,` or `{symbol}` here"))]);
+
+                Err(ParserError::Diagnostic(diagnostic))
+            }
+        }
+    }
+
     fn parse_array(&mut self) -> Result<ast::Expression, ParserError> {
         self.consume(TokenKind::OpenSquareBrace)?;
 
@@ -233,9 +357,7 @@ where
             let value = self.parse_expression(0)?;
             this.push(value);
 
-            if self.peek() == TokenKind::Comma {
-                self.consume(TokenKind::Comma)?;
-            } else {
+            if !self.parse_list_separator(TokenKind::CloseSquareBrace, ']')? {
                 break;
             }
         }
@@ -251,19 +373,28 @@ where
                 TokenKind::OpenSquareBrace => self.parse_array(),
                 TokenKind::OpenBrace => self.parse_object(),
                 TokenKind::Identifier => self.parse_expression_identifier(),
+                TokenKind::Keyword(keyword @ (Keyword::True | Keyword::False | Keyword::Nil)) => {
+                    self.parse_keyword_literal(keyword)
+                        .map(ast::Expression::Literal)
+                }
+                TokenKind::Keyword(Keyword::If) => {
+                    self.consume(TokenKind::Keyword(Keyword::If))?;
+                    self.parse_if_expression()
+                }
                 TokenKind::OpenParen => {
                     self.consume(TokenKind::OpenParen)?;
                     let expr = self.parse_expression(0)?;
                     self.consume(TokenKind::CloseParen)?;
                     Ok(expr)
                 }
-                TokenKind::Add | TokenKind::Subtract | TokenKind::Not => {
+                TokenKind::Add | TokenKind::Subtract | TokenKind::Not | TokenKind::BitNot => {
                     let token = self.peek();
                     self.consume(token)?;
                     let op = match token {
                         TokenKind::Add => ast::Operator::Plus,
                         TokenKind::Subtract => ast::Operator::Minus,
                         TokenKind::Not => ast::Operator::Not,
+                        TokenKind::BitNot => ast::Operator::BitNot,
                         _ => unreachable!(),
                     };
 
@@ -293,29 +424,72 @@ where
                     Err(ParserError::Diagnostic(diagnostic))
                 }
             }
-        };
+        }?;
 
+        self.parse_infix(lhs, binding_power)
+    }
+
+    // continues an expression where lhs has already been parsed
+    fn parse_infix(
+        &mut self,
+        lhs: ast::Expression,
+        binding_power: u8,
+    ) -> Result<ast::Expression, ParserError> {
+        let mut lhs = Ok(lhs);
+        // binding power of the last comparison at this level, `a < b < c` is a mistake
+        let mut comparison_binding_power = None;
         loop {
             let token = self.peek();
+            let peeked_token = self.peek_token();
+            if token == TokenKind::Keyword(Keyword::As) {
+                if ast::CAST_BINDING_POWER < binding_power {
+                    break lhs;
+                }
+
+                self.consume(TokenKind::Keyword(Keyword::As))?;
+                let type_token = self.consume(TokenKind::Identifier)?;
+                lhs = Ok(ast::Expression::Cast {
+                    expr: Box::new(lhs?),
+                    type_name: self.text(&type_token).to_owned(),
+                });
+
+                continue;
+            }
+
             let op = match token {
                 TokenKind::Add => ast::Operator::Plus,
                 TokenKind::Subtract => ast::Operator::Minus,
                 TokenKind::Multiply => ast::Operator::Multiply,
                 TokenKind::Divide => ast::Operator::Divide,
+                TokenKind::Modulo => ast::Operator::Modulo,
                 TokenKind::GreaterThan => ast::Operator::GreaterThan,
                 TokenKind::GreaterThanOrEquals => ast::Operator::GreaterThanOrEqual,
                 TokenKind::LessThan => ast::Operator::LessThan,
                 TokenKind::LessThanOrEquals => ast::Operator::LessThanOrEqual,
                 TokenKind::Equal => ast::Operator::Equal,
                 TokenKind::NotEqual => ast::Operator::NotEqual,
+                TokenKind::And => ast::Operator::And,
+                TokenKind::Or => ast::Operator::Or,
+                TokenKind::BitAnd => ast::Operator::BitAnd,
+                TokenKind::BitOr => ast::Operator::BitOr,
+                TokenKind::BitXor => ast::Operator::BitXor,
+                TokenKind::ShiftLeft => ast::Operator::ShiftLeft,
+                TokenKind::ShiftRight => ast::Operator::ShiftRight,
                 // these don't belong to us, leave it for someone else to consume
                 TokenKind::Comma => break lhs,
                 TokenKind::CloseSquareBrace => break lhs,
+                TokenKind::DotDot => break lhs,
                 TokenKind::Literal => break lhs,
+                TokenKind::Identifier => break lhs,
                 TokenKind::OpenBrace => break lhs,
                 TokenKind::CloseParen => break lhs,
                 TokenKind::CloseBrace => break lhs,
                 TokenKind::EndOfLine => break lhs,
+                TokenKind::EndOfFile => break lhs,
+                // an expression never continues past `=`
+                TokenKind::Assignment => break lhs,
+                // `as` is handled above, any other keyword starts something new
+                TokenKind::Keyword(_) => break lhs,
 
                 // FIXME: invalid operators seem to infinite loop somehow here
                 _ => {
@@ -343,9 +517,26 @@ where
                     break lhs;
                 }
 
+                if op.is_comparison() {
+                    if comparison_binding_power == Some(left_binding_power) {
+                        let diagnostic = Diagnostic::error()
+                            .with_message("comparison operators cannot be chained")
+                            .with_labels(vec![Label::primary(
+                                peeked_token.span().file_id,
+                                peeked_token.span(),
+                            )
+                            .with_message("use `&&` to combine comparisons")]);
+
+                        break Err(ParserError::Diagnostic(diagnostic));
+                    }
+
+                    comparison_binding_power = Some(left_binding_power);
+                }
+
                 self.consume(token)?;
                 let rhs = self.parse_expression(right_binding_power)?;
-                break Ok(ast::Expression::Infix {
+                // keep going, there might be more operators at this level
+                lhs = Ok(ast::Expression::Infix {
                     lhs: Box::new(lhs?),
                     rhs: Box::new(rhs),
                     op,
@@ -364,7 +555,7 @@ where
                 break;
             }
 
-            let token = self.consume(TokenKind::Identifier)?;
+            let token = self.consume_name()?;
             path.push(self.text(&token).to_string());
         }
 
@@ -374,6 +565,19 @@ where
     fn parse_array_access(&mut self, first: &str) -> Result<ast::Expression, ParserError> {
         self.consume(TokenKind::OpenSquareBrace)?;
         let index = self.parse_expression(0)?;
+
+        if self.peek() == TokenKind::DotDot {
+            self.consume(TokenKind::DotDot)?;
+            let end = self.parse_expression(0)?;
+            self.consume(TokenKind::CloseSquareBrace)?;
+
+            return Ok(ast::Expression::ArraySlice {
+                name: first.to_owned(),
+                start: Box::new(index),
+                end: Box::new(end),
+            });
+        }
+
         self.consume(TokenKind::CloseSquareBrace)?;
 
         Ok(ast::Expression::ArrayAccess {
@@ -386,12 +590,9 @@ where
         let token = self.consume(TokenKind::Identifier)?;
 
         let expr = match self.text(&token) {
-            // hmmmm
-            "true" => Ok(ast::Expression::Literal(types::Literal::Boolean(true))),
-            "false" => Ok(ast::Expression::Literal(types::Literal::Boolean(false))),
             name if self.peek() == TokenKind::Dot => self.parse_object_access(name),
             name if self.peek() == TokenKind::OpenSquareBrace => self.parse_array_access(name),
-            name if self.peek() == TokenKind::OpenParen => self.parse_function_call(name, false),
+            name if self.peek() == TokenKind::OpenParen => self.parse_function_call(name),
             name => self.parse_variable(name),
         }?;
 
@@ -400,7 +601,7 @@ where
 
     fn parse_return(&mut self) -> Result<ast::Statement, ParserError> {
         let expr = self.parse_expression(0)?;
-        self.consume(TokenKind::EndOfLine)?;
+        self.consume_end_of_statement()?;
 
         Ok(ast::Statement::Return(expr))
     }
@@ -410,85 +611,321 @@ where
         Ok(ast::Statement::Loop { body: block.into() })
     }
 
+    fn parse_for(&mut self) -> Result<ast::Statement, ParserError> {
+        let binding_token = self.consume_name()?;
+        let binding = self.text(&binding_token).to_owned();
+
+        let in_token = self.peek_token();
+        if *in_token.kind() != TokenKind::Keyword(Keyword::In) {
+            let diagnostic = Diagnostic::error()
+                .with_message("unexpected token")
+                .with_labels(vec![Label::primary(
+                    in_token.span().file_id,
+                    in_token.span(),
+                )
+                .with_message("expected `in` after the loop variable")]);
+
+            return Err(ParserError::Diagnostic(diagnostic));
+        }
+        self.consume(TokenKind::Keyword(Keyword::In))?;
+
+        let start = self.parse_expression(0)?;
+        let iterable = if self.peek() == TokenKind::DotDot {
+            self.consume(TokenKind::DotDot)?;
+            let end = self.parse_expression(0)?;
+
+            ast::Iterable::Range { start, end }
+        } else {
+            ast::Iterable::Array(start)
+        };
+
+        let block = self.parse_block()?;
+
+        Ok(ast::Statement::For {
+            binding,
+            iterable,
+            body: block.into(),
+            span: *binding_token.span(),
+        })
+    }
+
+    fn parse_match(&mut self) -> Result<ast::Statement, ParserError> {
+        let value = self.parse_expression(0)?;
+        self.consume(TokenKind::OpenBrace)?;
+
+        let mut arms = Vec::new();
+        while self.peek() != TokenKind::CloseBrace {
+            let pattern = self.parse_pattern()?;
+            self.consume(TokenKind::FatArrow)?;
+            let body = self.parse_block()?;
+            arms.push(ast::MatchArm { pattern, body });
+
+            if self.peek() == TokenKind::Comma {
+                self.consume(TokenKind::Comma)?;
+            }
+        }
+
+        self.consume(TokenKind::CloseBrace)?;
+
+        Ok(ast::Statement::Match { value, arms })
+    }
+
+    // a literal, optionally negated, or `_`
+    fn parse_pattern(&mut self) -> Result<ast::Pattern, ParserError> {
+        let token = self.peek_token();
+        if self.peek() == TokenKind::Identifier && self.text(&token) == "_" {
+            self.consume(TokenKind::Identifier)?;
+            return Ok(ast::Pattern::Wildcard);
+        }
+
+        match self.parse_constant()? {
+            Some(literal) => Ok(ast::Pattern::Literal(literal)),
+            None => Err(Self::invalid_pattern(&token)),
+        }
+    }
+
+    // a literal, a negative number, true, false or nil
+    fn parse_constant(&mut self) -> Result<Option<types::Literal>, ParserError> {
+        let token = self.peek_token();
+        let literal = match token.kind() {
+            TokenKind::Literal => self.parse_literal()?,
+            TokenKind::Subtract => {
+                self.consume(TokenKind::Subtract)?;
+                match self.parse_literal()? {
+                    ast::Expression::Literal(types::Literal::Integer(i)) => {
+                        ast::Expression::Literal(types::Literal::Integer(-i))
+                    }
+                    ast::Expression::Literal(types::Literal::Float(f)) => {
+                        ast::Expression::Literal(types::Literal::Float(-f))
+                    }
+                    _ => return Ok(None),
+                }
+            }
+            TokenKind::Keyword(keyword @ (Keyword::True | Keyword::False | Keyword::Nil)) => {
+                ast::Expression::Literal(self.parse_keyword_literal(*keyword)?)
+            }
+            _ => return Ok(None),
+        };
+
+        match literal {
+            ast::Expression::Literal(literal) => Ok(Some(literal)),
+            _ => unreachable!(),
+        }
+    }
+
+    // true, false and nil
+    fn parse_keyword_literal(&mut self, keyword: Keyword) -> Result<types::Literal, ParserError> {
+        self.consume(TokenKind::Keyword(keyword))?;
+
+        Ok(match keyword {
+            Keyword::True => types::Literal::Boolean(true),
+            Keyword::False => types::Literal::Boolean(false),
+            Keyword::Nil => types::Literal::Nil,
+            _ => unreachable!(),
+        })
+    }
+
+    // an identifier that names something, so `let let = 1;` fails clearly
+    fn consume_name(&mut self) -> Result<Token, ParserError> {
+        let token = self.peek_token();
+        if let TokenKind::Keyword(keyword) = token.kind() {
+            let diagnostic = Diagnostic::error()
+                .with_message(format!("`{keyword}` is a reserved keyword"))
+                .with_labels(vec![Label::primary(token.span().file_id, token.span())
+                    .with_message("keywords can't be used as names")]);
+
+            return Err(ParserError::Diagnostic(diagnostic));
+        }
+
+        self.consume(TokenKind::Identifier)
+    }
+
+    fn invalid_pattern(token: &Token) -> ParserError {
+        let diagnostic = Diagnostic::error()
+            .with_message("invalid pattern")
+            .with_labels(vec![Label::primary(token.span().file_id, token.span())
+                .with_message("expected a literal or `_`")]);
+
+        ParserError::Diagnostic(diagnostic)
+    }
+
     fn parse_break(&mut self) -> Result<ast::Statement, ParserError> {
-        self.consume(TokenKind::EndOfLine)?;
+        let value = if matches!(self.peek(), TokenKind::EndOfLine | TokenKind::CloseBrace) {
+            None
+        } else {
+            Some(self.parse_expression(0)?)
+        };
+
+        self.consume_end_of_statement()?;
 
-        Ok(ast::Statement::Break)
+        Ok(ast::Statement::Break(value))
     }
 
-    fn parse_object_mutation(&mut self, first: &str) -> Result<ast::Statement, ParserError> {
+    fn parse_object_mutation(
+        &mut self,
+        first: &str,
+        span: Span,
+    ) -> Result<ast::Statement, ParserError> {
         let object_access = self.parse_object_access(first)?;
 
+        // `config.name;` is an expression statement, or `config.name }` a tail expression
+        if self.peek() != TokenKind::Assignment {
+            let expr = self.parse_infix(object_access, 0)?;
+            return self.finish_expression_statement(expr);
+        }
+
         self.consume(TokenKind::Assignment)?;
 
         let expr = self.parse_expression(0)?;
 
-        self.consume(TokenKind::EndOfLine)?;
+        self.consume_end_of_statement()?;
 
         Ok(ast::Statement::ObjectMutation {
             path: object_access,
             value: expr.into(),
+            span,
         })
     }
 
+    fn parse_statement_keyword(&mut self, keyword: Keyword) -> Result<ast::Statement, ParserError> {
+        let token = self.consume(TokenKind::Keyword(keyword))?;
+        match keyword {
+            Keyword::Let => self.parse_let(),
+            Keyword::Const => self.parse_const(),
+            Keyword::Fn => Ok(ast::Statement::Function(self.parse_function()?)),
+            Keyword::If => self.parse_if_statement(),
+            Keyword::Return => self.parse_return(),
+            Keyword::Loop => self.parse_loop(),
+            Keyword::For => self.parse_for(),
+            Keyword::Match => self.parse_match(),
+            Keyword::Break => self.parse_break(),
+            Keyword::Continue => {
+                self.consume_end_of_statement()?;
+                Ok(ast::Statement::Continue)
+            }
+            _ => {
+                let diagnostic = Diagnostic::error()
+                    .with_message("unexpected token")
+                    .with_labels(vec![Label::primary(token.span().file_id, token.span())
+                        .with_message(format!("`{keyword}` can't start a statement"))]);
+
+                Err(ParserError::Diagnostic(diagnostic))
+            }
+        }
+    }
+
     fn parse_statement_identifier(&mut self) -> Result<ast::Statement, ParserError> {
         let identifier = self.consume(TokenKind::Identifier)?;
         match self.text(&identifier) {
-            "let" => self.parse_let(),
-            "const" => self.parse_const(),
-            "fn" => Ok(ast::Statement::Function(self.parse_function()?)),
-            "if" => self.parse_if_statement(),
-            "return" => self.parse_return(),
-            "loop" => self.parse_loop(),
-            "break" => self.parse_break(),
-            name if self.peek() == TokenKind::OpenParen => Ok(ast::Statement::Expression(
-                self.parse_function_call(name, true)?,
-            )),
-            name if self.peek() == TokenKind::Dot => self.parse_object_mutation(name),
-            name if self.peek() == TokenKind::Assignment => self.parse_let_mutation(name),
-            name => Ok(ast::Statement::Expression(self.parse_variable(name)?)),
+            name if self.peek() == TokenKind::Dot => {
+                self.parse_object_mutation(name, *identifier.span())
+            }
+            name if matches!(
+                self.peek(),
+                TokenKind::Assignment | TokenKind::AddAssign | TokenKind::SubtractAssign
+            ) =>
+            {
+                self.parse_let_mutation(name, *identifier.span())
+            }
+            name => {
+                let lhs = match name {
+                    name if self.peek() == TokenKind::OpenParen => {
+                        self.parse_function_call(name)?
+                    }
+                    name if self.peek() == TokenKind::OpenSquareBrace => {
+                        self.parse_array_access(name)?
+                    }
+                    name => self.parse_variable(name)?,
+                };
+
+                let expr = self.parse_infix(lhs, 0)?;
+                self.finish_expression_statement(expr)
+            }
         }
     }
 
+    // `x }` at the end of a function body is `return x; }`
+    fn finish_expression_statement(
+        &mut self,
+        expr: ast::Expression,
+    ) -> Result<ast::Statement, ParserError> {
+        if self.tail_returns && self.peek() == TokenKind::CloseBrace {
+            return Ok(ast::Statement::Return(expr));
+        }
+
+        self.consume_end_of_statement()?;
+
+        Ok(ast::Statement::Expression(expr))
+    }
+
     fn parse_block(&mut self) -> Result<ast::Statement, ParserError> {
+        self.parse_block_with(false)
+    }
+
+    fn parse_block_with(&mut self, tail_returns: bool) -> Result<ast::Statement, ParserError> {
         self.consume(TokenKind::OpenBrace)?;
+        let outer_tail_returns = std::mem::replace(&mut self.tail_returns, tail_returns);
+        self.block_depth += 1;
 
         let mut statements = Vec::new();
+        let mut spans = Vec::new();
         loop {
-            let token = self.peek();
-            if token == TokenKind::CloseBrace {
+            let token = self.peek_token();
+            if *token.kind() == TokenKind::CloseBrace {
                 break;
             }
 
             let statement = self.parse_statement()?;
             statements.push(statement);
+            spans.push(Span {
+                end: self.last_consumed_end,
+                ..*token.span()
+            });
         }
 
+        self.tail_returns = outer_tail_returns;
+        self.block_depth -= 1;
         self.consume(TokenKind::CloseBrace)?;
 
-        Ok(ast::Statement::Block { body: statements })
+        Ok(ast::Statement::Block {
+            body: statements,
+            spans,
+        })
     }
 
-    fn parse_if_statement(&mut self) -> Result<ast::Statement, ParserError> {
+    // `if x = 1` is almost always a typo for `==`
+    fn parse_condition(&mut self) -> Result<ast::Expression, ParserError> {
         let condition = self.parse_expression(0)?;
+        if self.peek() == TokenKind::Assignment {
+            let token = self.peek_token();
+            let diagnostic = Diagnostic::error()
+                .with_message("assignment in condition")
+                .with_labels(vec![Label::primary(token.span().file_id, token.span())
+                    .with_message("use `==` to compare")]);
+
+            return Err(ParserError::Diagnostic(diagnostic));
+        }
+
+        Ok(condition)
+    }
+
+    fn parse_if_statement(&mut self) -> Result<ast::Statement, ParserError> {
+        let condition = self.parse_condition()?;
 
         let block = self.parse_block()?;
 
-        let maybe_else = self.peek_token();
-        let else_statement =
-            if *maybe_else.kind() == TokenKind::Identifier && self.text(&maybe_else) == "else" {
-                self.consume(TokenKind::Identifier)?;
-
-                let maybe_if = self.peek_token();
-                if *maybe_if.kind() == TokenKind::Identifier && self.text(&maybe_if) == "if" {
-                    self.consume(TokenKind::Identifier)?;
-                    Some(self.parse_if_statement()?)
-                } else {
-                    Some(self.parse_block()?)
-                }
+        let else_statement = if self.peek() == TokenKind::Keyword(Keyword::Else) {
+            self.consume(TokenKind::Keyword(Keyword::Else))?;
+
+            if self.peek() == TokenKind::Keyword(Keyword::If) {
+                self.consume(TokenKind::Keyword(Keyword::If))?;
+                Some(self.parse_if_statement()?)
             } else {
-                None
-            };
+                Some(self.parse_block()?)
+            }
+        } else {
+            None
+        };
 
         Ok(ast::Statement::If {
             condition: condition.into(),
@@ -497,56 +934,89 @@ where
         })
     }
 
+    fn parse_if_expression(&mut self) -> Result<ast::Expression, ParserError> {
+        let condition = self.parse_condition()?;
+        let body = self.parse_expression_block()?;
+
+        let else_body = if self.peek() == TokenKind::Keyword(Keyword::Else) {
+            self.consume(TokenKind::Keyword(Keyword::Else))?;
+
+            if self.peek() == TokenKind::Keyword(Keyword::If) {
+                self.consume(TokenKind::Keyword(Keyword::If))?;
+                Some(self.parse_if_expression()?)
+            } else {
+                Some(self.parse_expression_block()?)
+            }
+        } else {
+            None
+        };
+
+        Ok(ast::Expression::If {
+            condition: condition.into(),
+            body: body.into(),
+            else_body: else_body.map(|e| e.into()),
+        })
+    }
+
+    // `{ expr }`, an arm of an if expression
+    fn parse_expression_block(&mut self) -> Result<ast::Expression, ParserError> {
+        self.consume(TokenKind::OpenBrace)?;
+        let expr = self.parse_expression(0)?;
+        self.consume(TokenKind::CloseBrace)?;
+
+        Ok(expr)
+    }
+
     fn parse_variable(&mut self, name: &str) -> Result<ast::Expression, ParserError> {
         Ok(ast::Expression::Variable(name.to_owned()))
     }
 
-    fn parse_function_call(
-        &mut self,
-        name: &str,
-        is_statement: bool,
-    ) -> Result<ast::Expression, ParserError> {
+    fn parse_function_call(&mut self, name: &str) -> Result<ast::Expression, ParserError> {
         self.consume(TokenKind::OpenParen)?;
 
         let mut args = Vec::new();
+        let mut spans = Vec::new();
         loop {
-            let token = self.peek();
-            if token == TokenKind::CloseParen {
+            let token = self.peek_token();
+            if *token.kind() == TokenKind::CloseParen {
                 break;
             }
 
             let expr = self.parse_expression(0)?;
             args.push(expr);
+            spans.push(Span {
+                end: self.last_consumed_end,
+                ..*token.span()
+            });
 
-            // if no comma, should break and expect close paren
-            if self.peek() == TokenKind::Comma {
-                self.consume(TokenKind::Comma)?;
+            if !self.parse_list_separator(TokenKind::CloseParen, ')')? {
+                break;
             }
         }
 
         self.consume(TokenKind::CloseParen)?;
 
-        // if we parsed as part of a full statement, then it should have end of line
-        // but if it was something like an expression, there is probably more
-        if is_statement {
-            self.consume(TokenKind::EndOfLine)?;
-        }
-
         Ok(ast::Expression::FunctionCall {
             name: name.to_owned(),
             args,
+            spans,
         })
     }
 
     fn parse_statement(&mut self) -> Result<ast::Statement, ParserError> {
         match self.peek() {
-            TokenKind::Literal => {
-                let expr = ast::Statement::Expression(self.parse_expression(0)?);
-                self.consume(TokenKind::EndOfLine)?;
-
-                Ok(expr)
+            TokenKind::Literal
+            | TokenKind::OpenParen
+            | TokenKind::Add
+            | TokenKind::Subtract
+            | TokenKind::Not
+            | TokenKind::BitNot
+            | TokenKind::Keyword(Keyword::True | Keyword::False | Keyword::Nil) => {
+                let expr = self.parse_expression(0)?;
+                self.finish_expression_statement(expr)
             }
             TokenKind::Identifier => self.parse_statement_identifier(),
+            TokenKind::Keyword(keyword) => self.parse_statement_keyword(keyword),
             TokenKind::OpenBrace => self.parse_block(),
             _ => {
                 let peeked_token = self.peek_token();
@@ -567,35 +1037,137 @@ where
         }
     }
 
+    // `int`, or `int?` for int or nil
+    fn parse_type_name(&mut self) -> Result<ast::TypeName, ParserError> {
+        // `[int]` is an array of ints
+        let (type_name_token, mut name) = if self.peek() == TokenKind::OpenSquareBrace {
+            let open = self.consume(TokenKind::OpenSquareBrace)?;
+            let inner = self.parse_type_name()?;
+            self.consume(TokenKind::CloseSquareBrace)?;
+
+            (open, format!("[{}]", inner.name))
+        } else {
+            let type_name_token = self.consume(TokenKind::Identifier)?;
+            let name = self.text(&type_name_token).to_owned();
+
+            (type_name_token, name)
+        };
+
+        if self.peek() == TokenKind::Question {
+            self.consume(TokenKind::Question)?;
+            name.push('?');
+        }
+
+        Ok(ast::TypeName {
+            name,
+            span: Span {
+                end: self.last_consumed_end,
+                ..*type_name_token.span()
+            },
+        })
+    }
+
     fn parse_parameters(&mut self) -> Result<Vec<FunctionParameter>, ParserError> {
-        let mut args = Vec::new();
+        let mut args = Vec::<FunctionParameter>::new();
+        let mut seen_default = false;
 
         loop {
             if self.peek() == TokenKind::CloseParen {
                 break;
             }
 
-            let identifier = self.consume(TokenKind::Identifier)?;
+            let identifier = self.consume_name()?;
             let name = self.text(&identifier).to_owned();
+            if let Some(previous) = args.iter().find(|arg| arg.name == name) {
+                return Err(duplicate(
+                    "parameter",
+                    &name,
+                    *identifier.span(),
+                    previous.span,
+                ));
+            }
 
             self.consume(TokenKind::Colon)?;
 
-            let type_name_token = self.consume(TokenKind::Identifier)?;
-            let type_name = self.text(&type_name_token);
+            // both `values: ...int` and `values: int...` are accepted
+            let is_prefix_variadic = self.peek() == TokenKind::Ellipsis;
+            if is_prefix_variadic {
+                self.consume(TokenKind::Ellipsis)?;
+            }
+
+            let type_name = self.parse_type_name()?;
+
+            let is_variadic = is_prefix_variadic || self.peek() == TokenKind::Ellipsis;
+            if is_variadic && !is_prefix_variadic {
+                self.consume(TokenKind::Ellipsis)?;
+            }
+
+            let default_value = if !is_variadic && self.peek() == TokenKind::Assignment {
+                self.consume(TokenKind::Assignment)?;
+                let token = self.peek_token();
+                let default_value = self.parse_constant()?.ok_or_else(|| {
+                    let diagnostic = Diagnostic::error()
+                        .with_message("invalid default value")
+                        .with_labels(vec![Label::primary(token.span().file_id, token.span())
+                            .with_message("expected a literal")]);
+
+                    ParserError::Diagnostic(diagnostic)
+                })?;
+
+                Some(default_value)
+            } else {
+                None
+            };
+
+            // callers can only leave out arguments from the end, a variadic parameter can be empty
+            if seen_default && default_value.is_none() && !is_variadic {
+                let diagnostic = Diagnostic::error()
+                    .with_message("parameters with a default value must come last")
+                    .with_labels(vec![Label::primary(
+                        identifier.span().file_id,
+                        identifier.span(),
+                    )
+                    .with_message(format!("`{name}` needs a default value"))]);
+
+                return Err(ParserError::Diagnostic(diagnostic));
+            }
+            seen_default |= default_value.is_some();
 
             args.push(FunctionParameter {
                 name,
-                type_name: type_name.to_string(),
+                type_name,
+                is_variadic,
+                default_value,
+                span: *identifier.span(),
             });
 
-            if self.peek() == TokenKind::Comma {
-                self.consume(TokenKind::Comma)?;
+            if !self.parse_list_separator(TokenKind::CloseParen, ')')? || is_variadic {
+                break;
             }
         }
 
         Ok(args)
     }
 
+    // `;` ends a statement, unless it's the last one in a block
+    fn consume_end_of_statement(&mut self) -> Result<(), ParserError> {
+        match self.peek() {
+            TokenKind::EndOfLine => self.consume(TokenKind::EndOfLine).map(|_| ()),
+            TokenKind::CloseBrace if self.block_depth > 0 => Ok(()),
+            _ => {
+                // just after whatever came last, not on the token that follows
+                let end = Span::end_of(self.file_id, &self.input[..self.last_consumed_end]);
+                let diagnostic = Diagnostic::error()
+                    .with_message("missing semicolon")
+                    .with_labels(vec![
+                        Label::primary(end.file_id, end).with_message("add `;` here")
+                    ]);
+
+                Err(ParserError::Diagnostic(diagnostic))
+            }
+        }
+    }
+
     pub fn consume(&mut self, expected: TokenKind) -> Result<Token, ParserError> {
         let token = self.next();
 
@@ -632,6 +1204,11 @@ where
 
     fn next(&mut self) -> Option<Token> {
         let token = self.tokens.next()?;
+        self.note_invalid(token);
+        self.consumed += 1;
+        self.last_consumed = Some(*token.kind());
+        self.last_consumed_end = token.span().end;
+        self.file_id = token.span().file_id;
 
         // tracing::info!("{:?}", token);
 
@@ -642,29 +1219,39 @@ where
         }
     }
 
+    // the statements, and where each was written for the typechecker's diagnostics
     pub fn collect_and_emit_diagnostics<T>(
-        self,
+        mut self,
         writer: &StandardStream,
         config: &codespan_reporting::term::Config,
         files: &'a T,
-    ) -> Result<Vec<Statement>, Box<dyn std::error::Error>>
+    ) -> Result<(Vec<Statement>, Vec<Span>), Box<dyn std::error::Error>>
     where
         T: Files<'a, FileId = usize> + 'a,
     {
         let mut statements = Vec::new();
-        for statement in self.into_iter() {
-            if let Err(e) = statement {
-                let ParserError::Diagnostic(diagnostic) = e;
-                codespan_reporting::term::emit(&mut writer.lock(), config, files, &diagnostic)?;
-
-                // cause statuscode to be set
-                return Err(DiagnosticEmitted.into());
+        let mut spans = Vec::new();
+        let mut had_error = false;
+        // the iterator recovers after an error, so report everything in one go
+        while let Some(statement) = Iterator::next(&mut self) {
+            match statement {
+                Ok(statement) => {
+                    statements.push(statement);
+                    spans.extend(self.statement_span());
+                }
+                Err(ParserError::Diagnostic(diagnostic)) => {
+                    had_error = true;
+                    codespan_reporting::term::emit(&mut writer.lock(), config, files, &diagnostic)?;
+                }
             }
+        }
 
-            statements.push(statement.unwrap());
+        if had_error {
+            // cause statuscode to be set
+            return Err(DiagnosticEmitted.into());
         }
 
-        Ok(statements)
+        Ok((statements, spans))
     }
 }
 
@@ -681,9 +1268,69 @@ where
             return None;
         }
 
+        let consumed_at_start = self.consumed;
+        let start = self.peek_token();
+        let mut statement = self.next_statement();
+        if let Some(token) = self.invalid_token.take() {
+            statement = Some(Err(invalid_token(&token)));
+        }
+        self.statement_span = Some(Span {
+            end: self.last_consumed_end,
+            ..*start.span()
+        });
+        if let Some(Err(_)) = statement {
+            // the failed statement may have left us inside a function body
+            self.tail_returns = false;
+            self.block_depth = 0;
+            self.synchronize(consumed_at_start);
+            // whatever was skipped is part of the error already reported
+            self.invalid_token = None;
+        }
+
+        statement
+    }
+}
+
+impl<I> Parser<'_, I>
+where
+    I: Iterator<Item = Token>,
+{
+    // skips the rest of a broken statement, up to and including the next `;` or `}`
+    fn synchronize(&mut self, consumed_at_start: usize) {
+        // always make progress, otherwise the same token errors forever
+        if self.consumed == consumed_at_start && self.next().is_none() {
+            return;
+        }
+
+        // a block opened while skipping is skipped whole, otherwise a broken
+        // `if` header leaves its body to be parsed as top level statements
+        let mut depth = 0usize;
+        loop {
+            match self.last_consumed {
+                Some(TokenKind::OpenBrace) => depth += 1,
+                Some(TokenKind::CloseBrace) if depth > 1 => depth -= 1,
+                Some(TokenKind::CloseBrace) => return,
+                Some(TokenKind::EndOfLine) if depth == 0 => return,
+                _ => {}
+            }
+
+            if self.next().is_none() {
+                return;
+            }
+        }
+    }
+
+    fn next_statement(&mut self) -> Option<Result<Statement, ParserError>> {
         match self.peek() {
             TokenKind::Identifier => Some(self.parse_statement_identifier()),
             TokenKind::OpenBrace => Some(self.parse_block()),
+            TokenKind::Literal
+            | TokenKind::OpenParen
+            | TokenKind::Add
+            | TokenKind::Subtract
+            | TokenKind::Not
+            | TokenKind::BitNot
+            | TokenKind::Keyword(_) => Some(self.parse_statement()),
             TokenKind::EndOfLine => {
                 let token = self.consume(TokenKind::EndOfLine);
                 match token {
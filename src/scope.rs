@@ -1,7 +1,7 @@
-use crate::instructions::Register;
+use crate::{instructions::Register, types::Literal};
 use std::{
     cell::RefCell,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
 };
 
 #[derive(Debug)]
@@ -23,6 +23,8 @@ pub struct Scope {
     r#type: ScopeType,
     functions: RefCell<HashSet<String>>,
     symbols: RefCell<HashMap<String, Value>>,
+    // folded values of consts, for folding other consts that use them
+    constants: RefCell<HashMap<String, Literal>>,
 }
 
 impl Scope {
@@ -47,6 +49,7 @@ impl Scope {
     }
 
     pub fn define_immutable(&self, name: &str, register: Register) {
+        self.constants.borrow_mut().remove(name);
         self.symbols.borrow_mut().insert(
             name.to_owned(),
             Value {
@@ -57,6 +60,7 @@ impl Scope {
     }
 
     pub fn define_mutable(&self, name: &str, register: Register) {
+        self.constants.borrow_mut().remove(name);
         self.symbols.borrow_mut().insert(
             name.to_owned(),
             Value {
@@ -66,6 +70,14 @@ impl Scope {
         );
     }
 
+    pub fn define_constant(&self, name: &str, value: Literal) {
+        self.constants.borrow_mut().insert(name.to_owned(), value);
+    }
+
+    pub fn get_constant(&self, name: &str) -> Option<Literal> {
+        self.constants.borrow().get(name).cloned()
+    }
+
     pub fn get_register_for(&self, name: &str) -> Option<Register> {
         self.symbols.borrow().get(name).map(|v| v.register)
     }
@@ -73,4 +85,19 @@ impl Scope {
     pub fn is_mutable(&self, name: &str) -> Option<bool> {
         self.symbols.borrow().get(name).map(|v| v.is_mutable)
     }
+
+    // forgets the variable, handing back the register it was in
+    #[allow(unused)]
+    pub fn remove(&self, name: &str) -> Option<Register> {
+        self.constants.borrow_mut().remove(name);
+        self.symbols.borrow_mut().remove(name).map(|v| v.register)
+    }
+
+    pub fn registers(&self) -> BTreeMap<String, Register> {
+        self.symbols
+            .borrow()
+            .iter()
+            .map(|(name, v)| (name.to_owned(), v.register))
+            .collect()
+    }
 }
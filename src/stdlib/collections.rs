@@ -0,0 +1,166 @@
+use crate::{
+    stdlib::NativeContext,
+    types::{Array, Literal, ObjectValue},
+    vm::{ExecutionError, VMValue},
+};
+use std::{borrow::Cow, rc::Rc};
+
+// the compiler turns most calls into an ArrayLength instruction, this covers the rest
+pub fn len<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    match args.as_slice() {
+        [value] => match value.length() {
+            Some(length) => Ok(Some(VMValue::Literal(Cow::Owned(Literal::Integer(
+                length as i64,
+            ))))),
+            None => Err(ExecutionError::InvalidOperation {
+                cause: format!("cannot take length of {}", value.type_name()),
+            }),
+        },
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: "len expects exactly one argument".to_owned(),
+        }),
+    }
+}
+
+// like len, only reached when the compiler couldn't use its own instruction
+pub fn push<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    match args.as_slice() {
+        [VMValue::Array(array), value] => {
+            array
+                .borrow_mut()
+                .push(Rc::new(value.to_object_value().into()));
+
+            Ok(None)
+        }
+        [other, _] => Err(ExecutionError::InvalidOperation {
+            cause: format!("cannot push to {}", other.type_name()),
+        }),
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: "push expects an array and a value".to_owned(),
+        }),
+    }
+}
+
+// popping an empty array gives nil
+pub fn pop<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    match args.as_slice() {
+        [VMValue::Array(array)] => {
+            let popped = array.borrow_mut().pop();
+
+            Ok(Some(match popped {
+                Some(value) => VMValue::from(&*value.borrow()),
+                None => VMValue::Nil,
+            }))
+        }
+        [other] => Err(ExecutionError::InvalidOperation {
+            cause: format!("cannot pop from {}", other.type_name()),
+        }),
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: "pop expects exactly one argument".to_owned(),
+        }),
+    }
+}
+
+// an empty delimiter splits between every character
+pub fn split<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let [haystack, delimiter] = args.as_slice() else {
+        return Err(ExecutionError::InvalidOperation {
+            cause: "split expects a string and a delimiter".to_owned(),
+        });
+    };
+
+    let as_str = |value: &VMValue| {
+        match value {
+            VMValue::Literal(literal) => match literal.as_ref() {
+                Literal::String(s) => Some(s.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+        .ok_or_else(|| ExecutionError::InvalidOperation {
+            cause: format!("split expects a string but got {}", value.type_name()),
+        })
+    };
+
+    let haystack = as_str(haystack)?;
+    let delimiter = as_str(delimiter)?;
+
+    let parts: Vec<String> = if delimiter.is_empty() {
+        haystack.chars().map(|c| c.to_string()).collect()
+    } else {
+        haystack
+            .split(delimiter.as_str())
+            .map(str::to_owned)
+            .collect()
+    };
+
+    let array = Array::create_for_vm();
+    for part in parts {
+        array
+            .borrow_mut()
+            .push(Rc::new(ObjectValue::Literal(Literal::String(part)).into()));
+    }
+
+    Ok(Some(VMValue::Array(array)))
+}
+
+// range(end), range(start, end) or range(start, end, step), end is exclusive
+// a step of 0 or one that heads away from end gives nil
+pub fn range<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let bounds = args
+        .iter()
+        .map(|arg| {
+            match arg {
+                VMValue::Literal(literal) => match literal.as_ref() {
+                    Literal::Integer(n) => Some(*n),
+                    _ => None,
+                },
+                _ => None,
+            }
+            .ok_or_else(|| ExecutionError::InvalidOperation {
+                cause: format!("range expects an int but got {}", arg.type_name()),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (start, end, step) = match bounds.as_slice() {
+        [end] => (0, *end, 1),
+        [start, end] => (*start, *end, 1),
+        [start, end, step] => (*start, *end, *step),
+        _ => {
+            return Err(ExecutionError::InvalidOperation {
+                cause: "range expects between 1 and 3 arguments".to_owned(),
+            })
+        }
+    };
+
+    if step == 0 || (step > 0 && start > end) || (step < 0 && start < end) {
+        return Ok(Some(VMValue::Nil));
+    }
+
+    let array = Array::create_for_vm();
+    let mut n = Some(start);
+    while let Some(i) = n.filter(|&i| if step > 0 { i < end } else { i > end }) {
+        array
+            .borrow_mut()
+            .push(Rc::new(ObjectValue::Literal(Literal::Integer(i)).into()));
+        n = i.checked_add(step);
+    }
+
+    Ok(Some(VMValue::Array(array)))
+}
@@ -0,0 +1,37 @@
+use crate::{
+    stdlib::NativeContext,
+    types::Literal,
+    vm::{ExecutionError, VMValue},
+};
+use std::{borrow::Cow, io::Write};
+
+// reads a line without its line ending, nil once there's nothing left to read
+pub fn input<'a>(
+    args: Vec<VMValue<'a>>,
+    context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let failed = |e: std::io::Error| ExecutionError::InvalidOperation {
+        cause: format!("input failed: {e}"),
+    };
+
+    if let Some(VMValue::Literal(literal)) = args.first() {
+        if let Literal::String(prompt) = literal.as_ref() {
+            write!(context.output, "{prompt}").map_err(failed)?;
+            context.output.flush().map_err(failed)?;
+        }
+    }
+
+    let mut line = String::new();
+    if context.input.read_line(&mut line).map_err(failed)? == 0 {
+        return Ok(Some(VMValue::Nil));
+    }
+
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+
+    Ok(Some(VMValue::Literal(Cow::Owned(Literal::String(line)))))
+}
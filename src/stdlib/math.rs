@@ -0,0 +1,91 @@
+use crate::{
+    stdlib::NativeContext,
+    types::Literal,
+    vm::{ExecutionError, VMValue},
+};
+use std::borrow::Cow;
+
+fn float(value: f64) -> Option<VMValue<'static>> {
+    Some(VMValue::Literal(Cow::Owned(Literal::Float(value))))
+}
+
+// ints are widened, everything else is an error naming the function
+fn as_float(name: &str, value: &VMValue) -> Result<f64, ExecutionError> {
+    match value {
+        VMValue::Literal(literal) => match literal.as_ref() {
+            Literal::Integer(n) => Ok(*n as f64),
+            Literal::Float(n) => Ok(*n),
+            _ => Err(ExecutionError::InvalidOperation {
+                cause: format!("{name} expects a number but got {}", value.type_name()),
+            }),
+        },
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: format!("{name} expects a number but got {}", value.type_name()),
+        }),
+    }
+}
+
+fn single_float(name: &str, args: &[VMValue]) -> Result<f64, ExecutionError> {
+    match args {
+        [value] => as_float(name, value),
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: format!("{name} expects exactly one argument"),
+        }),
+    }
+}
+
+// sqrt(-1.0) is NaN, same as everywhere else
+pub fn sqrt<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    Ok(float(single_float("sqrt", &args)?.sqrt()))
+}
+
+pub fn floor<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    Ok(float(single_float("floor", &args)?.floor()))
+}
+
+pub fn ceil<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    Ok(float(single_float("ceil", &args)?.ceil()))
+}
+
+// keeps the type it was given, abs(-3) is the int 3
+pub fn abs<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    if let [VMValue::Literal(literal)] = args.as_slice() {
+        if let Literal::Integer(n) = literal.as_ref() {
+            let n = n
+                .checked_abs()
+                .ok_or_else(|| ExecutionError::InvalidOperation {
+                    cause: format!("abs of {n} does not fit in an int"),
+                })?;
+
+            return Ok(Some(VMValue::Literal(Cow::Owned(Literal::Integer(n)))));
+        }
+    }
+
+    Ok(float(single_float("abs", &args)?.abs()))
+}
+
+pub fn pow<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    match args.as_slice() {
+        [base, exponent] => Ok(float(
+            as_float("pow", base)?.powf(as_float("pow", exponent)?),
+        )),
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: "pow expects a base and an exponent".to_owned(),
+        }),
+    }
+}
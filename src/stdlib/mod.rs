@@ -1,12 +1,190 @@
-use crate::vm::VMValue;
+use crate::{
+    typechecker::{DefinedType, NativeSignature, ParameterType},
+    vm::{ExecutionError, VMValue},
+};
 use phf::phf_map;
+use std::{
+    any::Any,
+    io::{BufRead, BufReader, Write},
+};
 
+mod collections;
+mod io;
+mod math;
 mod print;
+mod process;
+mod rand;
+mod strings;
+mod testing;
+mod value;
 
 // FIXME: allow strict typing by native functions
 // can do this once typechecking exists.
-pub type NativeFunctionType = fn(Vec<VMValue>) -> Option<VMValue>;
+pub type NativeFunctionType =
+    for<'a> fn(Vec<VMValue<'a>>, &mut NativeContext) -> Result<Option<VMValue<'a>>, ExecutionError>;
+
+// handed to every native function, anything that has to outlive a single call lives here
+pub struct NativeContext {
+    pub user_data: Option<Box<dyn Any>>,
+    // where print and friends write to
+    pub output: Box<dyn Write>,
+    // where input reads lines from
+    pub input: Box<dyn BufRead>,
+}
+
+impl Default for NativeContext {
+    fn default() -> Self {
+        Self {
+            user_data: None,
+            output: Box::new(std::io::stdout()),
+            input: Box::new(BufReader::new(std::io::stdin())),
+        }
+    }
+}
+
 pub static STANDARD_LIBRARY: phf::Map<&'static str, NativeFunctionType> = phf_map! {
     // FIXME: add serialise to string method and call it from print
     "print" => print::print,
+    "input" => io::input,
+    "exit" => process::exit,
+    "seed" => rand::seed,
+    "random" => rand::random,
+    "random_int" => rand::random_int,
+    "is_nil" => value::is_nil,
+    "len" => collections::len,
+    "push" => collections::push,
+    "pop" => collections::pop,
+    "split" => collections::split,
+    "range" => collections::range,
+    "sqrt" => math::sqrt,
+    "abs" => math::abs,
+    "floor" => math::floor,
+    "ceil" => math::ceil,
+    "pow" => math::pow,
+    "to_string" => value::to_string,
+    "parse_int" => value::parse_int,
+    "parse_float" => value::parse_float,
+    "trim" => strings::trim,
+    "to_uppercase" => strings::to_uppercase,
+    "to_lowercase" => strings::to_lowercase,
+    "contains" => strings::contains,
+    "starts_with" => strings::starts_with,
+    "ends_with" => strings::ends_with,
+    "replace" => strings::replace,
+    "replace_n" => strings::replace_n,
+    "assert" => testing::assert,
+    "assert_eq" => testing::assert_eq,
 };
+
+// how the typechecker sees everything above, along with the functions the compiler turns into instructions
+pub fn standard_library_signatures() -> Vec<(&'static str, NativeSignature)> {
+    use DefinedType::{Bool, Nil, Optional, String, F64, I64};
+    use ParameterType::{Any, Array, OneOf, Type};
+
+    let number = ParameterType::number;
+
+    vec![
+        (
+            "print",
+            NativeSignature::new(vec![Any], Nil)
+                .with_required(0)
+                .with_variadic(),
+        ),
+        // nil at the end of input
+        (
+            "input",
+            NativeSignature::new(vec![Type(String)], Optional(Box::new(String))).with_required(0),
+        ),
+        (
+            "exit",
+            NativeSignature::new(vec![Type(I64)], Nil).with_required(0),
+        ),
+        ("seed", NativeSignature::new(vec![Type(I64)], Nil)),
+        ("random", NativeSignature::new(vec![], F64)),
+        (
+            "random_int",
+            NativeSignature::new(vec![Type(I64), Type(I64)], I64),
+        ),
+        ("is_nil", NativeSignature::new(vec![Any], Bool)),
+        ("to_string", NativeSignature::new(vec![Any], String)),
+        ("type_of", NativeSignature::new(vec![Any], String)),
+        // truncates toward zero, strings and bools need `as`
+        ("int", NativeSignature::new(vec![number()], I64)),
+        ("float", NativeSignature::new(vec![number()], F64)),
+        (
+            "len",
+            NativeSignature::new(vec![OneOf(vec![Type(String), Array])], I64),
+        ),
+        ("push", NativeSignature::new(vec![Array, Any], Nil)),
+        // the typechecker gives back the element type
+        ("pop", NativeSignature::new(vec![Array], Nil)),
+        // nil when the step is 0 or heads away from the end
+        (
+            "range",
+            NativeSignature::new(
+                vec![Type(I64), Type(I64), Type(I64)],
+                Optional(Box::new(DefinedType::Array(Box::new(I64)))),
+            )
+            .with_required(1),
+        ),
+        (
+            "split",
+            NativeSignature::new(
+                vec![Type(String), Type(String)],
+                DefinedType::Array(Box::new(String)),
+            ),
+        ),
+        ("sqrt", NativeSignature::new(vec![number()], F64)),
+        // ints stay ints, the typechecker handles that
+        ("abs", NativeSignature::new(vec![number()], F64)),
+        ("floor", NativeSignature::new(vec![number()], F64)),
+        ("ceil", NativeSignature::new(vec![number()], F64)),
+        ("pow", NativeSignature::new(vec![number(), number()], F64)),
+        // nil when the string isn't a number, callers compare against nil before using it
+        (
+            "parse_int",
+            NativeSignature::new(vec![Type(String)], Optional(Box::new(I64))),
+        ),
+        (
+            "parse_float",
+            NativeSignature::new(vec![Type(String)], Optional(Box::new(F64))),
+        ),
+        ("trim", NativeSignature::new(vec![Type(String)], String)),
+        (
+            "to_uppercase",
+            NativeSignature::new(vec![Type(String)], String),
+        ),
+        (
+            "to_lowercase",
+            NativeSignature::new(vec![Type(String)], String),
+        ),
+        (
+            "contains",
+            NativeSignature::new(vec![Type(String), Type(String)], Bool),
+        ),
+        (
+            "starts_with",
+            NativeSignature::new(vec![Type(String), Type(String)], Bool),
+        ),
+        (
+            "ends_with",
+            NativeSignature::new(vec![Type(String), Type(String)], Bool),
+        ),
+        (
+            "replace",
+            NativeSignature::new(vec![Type(String), Type(String), Type(String)], String),
+        ),
+        (
+            "replace_n",
+            NativeSignature::new(
+                vec![Type(String), Type(String), Type(String), Type(I64)],
+                String,
+            ),
+        ),
+        (
+            "assert",
+            NativeSignature::new(vec![Type(Bool), Type(String)], Nil).with_required(1),
+        ),
+        ("assert_eq", NativeSignature::new(vec![Any, Any], Nil)),
+    ]
+}
@@ -1,17 +1,21 @@
-use crate::vm::VMValue;
+use crate::{
+    stdlib::NativeContext,
+    vm::{ExecutionError, VMValue},
+};
+use std::io::Write;
 
-pub fn print(args: Vec<VMValue>) -> Option<VMValue> {
+pub fn print<'a>(
+    args: Vec<VMValue<'a>>,
+    context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let mut line = String::new();
     for arg in args {
-        match arg {
-            VMValue::Empty => print!("<empty>"),
-            VMValue::Literal(literal) => print!("{}", literal.as_ref()),
-            VMValue::Function(f) => print!("{}", f),
-            VMValue::Object(object) => print!("{}", object.borrow()),
-            VMValue::Array(array) => print!("{}", array.borrow()),
-        }
+        line.push_str(&arg.to_string());
     }
 
-    println!();
+    writeln!(context.output, "{line}").map_err(|e| ExecutionError::InvalidOperation {
+        cause: format!("print failed: {e}"),
+    })?;
 
-    None
+    Ok(None)
 }
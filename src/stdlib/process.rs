@@ -0,0 +1,30 @@
+use crate::{
+    stdlib::NativeContext,
+    types::Literal,
+    vm::{ExecutionError, VMValue},
+};
+
+// stops the vm, the host decides what to do with the code
+pub fn exit<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let code = match args.as_slice() {
+        [] => 0,
+        [VMValue::Literal(literal)] => match literal.as_ref() {
+            Literal::Integer(code) => *code,
+            _ => {
+                return Err(ExecutionError::InvalidOperation {
+                    cause: "exit code must be an integer".to_owned(),
+                })
+            }
+        },
+        _ => {
+            return Err(ExecutionError::InvalidOperation {
+                cause: "exit expects at most one argument".to_owned(),
+            })
+        }
+    };
+
+    Err(ExecutionError::Exit { code })
+}
@@ -0,0 +1,124 @@
+use crate::{
+    stdlib::NativeContext,
+    types::Literal,
+    vm::{ExecutionError, VMValue},
+};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// xorshift64*, tiny and good enough for scripts, no need for a dependency
+thread_local! {
+    static STATE: Cell<u64> = Cell::new(initial_state());
+}
+
+fn initial_state() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+
+    scramble(nanos)
+}
+
+// splitmix64, spreads small seeds out so they still give a usable state
+// xorshift can never leave 0, so that is avoided too
+fn scramble(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+
+    if z == 0 {
+        0x9E3779B97F4A7C15
+    } else {
+        z
+    }
+}
+
+fn next_u64() -> u64 {
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+
+        x.wrapping_mul(0x2545F4914F6CDD1D)
+    })
+}
+
+pub fn seed<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let seed = match args.as_slice() {
+        [VMValue::Literal(literal)] => match literal.as_ref() {
+            Literal::Integer(n) => *n,
+            _ => {
+                return Err(ExecutionError::InvalidOperation {
+                    cause: "seed expects an integer".to_owned(),
+                })
+            }
+        },
+        _ => {
+            return Err(ExecutionError::InvalidOperation {
+                cause: "seed expects a single integer argument".to_owned(),
+            })
+        }
+    };
+
+    STATE.with(|state| state.set(scramble(seed as u64)));
+
+    Ok(None)
+}
+
+// float in [0, 1)
+pub fn random<'a>(
+    _args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    // top 53 bits fill the mantissa exactly
+    let value = (next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+
+    Ok(Some(VMValue::Literal(Cow::Owned(Literal::Float(value)))))
+}
+
+// integer in [min, max], both ends inclusive
+pub fn random_int<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let (min, max) = match args.as_slice() {
+        [VMValue::Literal(min), VMValue::Literal(max)] => match (min.as_ref(), max.as_ref()) {
+            (Literal::Integer(min), Literal::Integer(max)) if min <= max => (*min, *max),
+            (Literal::Integer(min), Literal::Integer(max)) => {
+                return Err(ExecutionError::InvalidOperation {
+                    cause: format!("random_int range is empty, {min} is greater than {max}"),
+                })
+            }
+            _ => {
+                return Err(ExecutionError::InvalidOperation {
+                    cause: "random_int expects integer bounds".to_owned(),
+                })
+            }
+        },
+        _ => {
+            return Err(ExecutionError::InvalidOperation {
+                cause: "random_int expects two arguments".to_owned(),
+            })
+        }
+    };
+
+    let span = (max.wrapping_sub(min) as u64).wrapping_add(1);
+    let value = if span == 0 {
+        // min..=max covers every i64
+        next_u64() as i64
+    } else {
+        min.wrapping_add((next_u64() % span) as i64)
+    };
+
+    Ok(Some(VMValue::Literal(Cow::Owned(Literal::Integer(value)))))
+}
@@ -0,0 +1,155 @@
+use crate::{
+    stdlib::NativeContext,
+    types::Literal,
+    vm::{ExecutionError, VMValue},
+};
+use std::borrow::Cow;
+
+fn as_str<'v>(name: &str, value: &'v VMValue) -> Result<&'v str, ExecutionError> {
+    match value {
+        VMValue::Literal(literal) => match literal.as_ref() {
+            Literal::String(s) => Some(s.as_str()),
+            _ => None,
+        },
+        _ => None,
+    }
+    .ok_or_else(|| ExecutionError::InvalidOperation {
+        cause: format!("{name} expects a string but got {}", value.type_name()),
+    })
+}
+
+fn map_string<'a>(
+    name: &str,
+    args: Vec<VMValue<'a>>,
+    f: fn(&str) -> String,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let [value] = args.as_slice() else {
+        return Err(ExecutionError::InvalidOperation {
+            cause: format!("{name} expects exactly one argument"),
+        });
+    };
+
+    Ok(Some(VMValue::Literal(Cow::Owned(Literal::String(f(
+        as_str(name, value)?,
+    ))))))
+}
+
+fn test_string<'a>(
+    name: &str,
+    args: Vec<VMValue<'a>>,
+    f: fn(&str, &str) -> bool,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let [haystack, needle] = args.as_slice() else {
+        return Err(ExecutionError::InvalidOperation {
+            cause: format!("{name} expects a string and what to look for"),
+        });
+    };
+
+    Ok(Some(VMValue::Literal(Cow::Owned(Literal::Boolean(f(
+        as_str(name, haystack)?,
+        as_str(name, needle)?,
+    ))))))
+}
+
+// unicode whitespace counts, not just spaces
+pub fn trim<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    map_string("trim", args, |s| s.trim().to_owned())
+}
+
+pub fn to_uppercase<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    map_string("to_uppercase", args, str::to_uppercase)
+}
+
+pub fn to_lowercase<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    map_string("to_lowercase", args, str::to_lowercase)
+}
+
+pub fn contains<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    test_string("contains", args, |haystack, needle| {
+        haystack.contains(needle)
+    })
+}
+
+pub fn starts_with<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    test_string("starts_with", args, |haystack, needle| {
+        haystack.starts_with(needle)
+    })
+}
+
+pub fn ends_with<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    test_string("ends_with", args, |haystack, needle| {
+        haystack.ends_with(needle)
+    })
+}
+
+pub fn replace<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let [haystack, pattern, replacement] = args.as_slice() else {
+        return Err(ExecutionError::InvalidOperation {
+            cause: "replace expects a string, a pattern and a replacement".to_owned(),
+        });
+    };
+
+    let replaced = as_str("replace", haystack)?
+        .replace(as_str("replace", pattern)?, as_str("replace", replacement)?);
+
+    Ok(Some(VMValue::Literal(Cow::Owned(Literal::String(
+        replaced,
+    )))))
+}
+
+// only the first n matches are replaced
+pub fn replace_n<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let [haystack, pattern, replacement, n] = args.as_slice() else {
+        return Err(ExecutionError::InvalidOperation {
+            cause: "replace_n expects a string, a pattern, a replacement and a count".to_owned(),
+        });
+    };
+
+    let n = match n {
+        VMValue::Literal(literal) => match literal.as_ref() {
+            Literal::Integer(n) => Some(*n),
+            _ => None,
+        },
+        _ => None,
+    }
+    .ok_or_else(|| ExecutionError::InvalidOperation {
+        cause: format!("replace_n expects an int but got {}", n.type_name()),
+    })?;
+    let n = usize::try_from(n).map_err(|_| ExecutionError::InvalidOperation {
+        cause: format!("replace_n expects a count of at least 0 but got {n}"),
+    })?;
+
+    let replaced = as_str("replace_n", haystack)?.replacen(
+        as_str("replace_n", pattern)?,
+        as_str("replace_n", replacement)?,
+        n,
+    );
+
+    Ok(Some(VMValue::Literal(Cow::Owned(Literal::String(
+        replaced,
+    )))))
+}
@@ -0,0 +1,57 @@
+use crate::{
+    stdlib::NativeContext,
+    types::Literal,
+    vm::{ExecutionError, VMValue},
+};
+
+pub fn assert<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    let (condition, message) = match args.as_slice() {
+        [condition] => (condition, None),
+        [condition, message] => (condition, Some(message)),
+        _ => {
+            return Err(ExecutionError::InvalidOperation {
+                cause: "assert expects a condition and an optional message".to_owned(),
+            })
+        }
+    };
+
+    let holds = match condition {
+        VMValue::Literal(literal) => match literal.as_ref() {
+            Literal::Boolean(b) => Some(*b),
+            _ => None,
+        },
+        _ => None,
+    }
+    .ok_or_else(|| ExecutionError::InvalidOperation {
+        cause: format!("assert expects a bool but got {}", condition.type_name()),
+    })?;
+
+    if holds {
+        return Ok(None);
+    }
+
+    Err(ExecutionError::AssertionFailed {
+        message: match message {
+            Some(message) => format!("assertion failed: {message}"),
+            None => "assertion failed".to_owned(),
+        },
+    })
+}
+
+pub fn assert_eq<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    match args.as_slice() {
+        [left, right] if left == right => Ok(None),
+        [left, right] => Err(ExecutionError::AssertionFailed {
+            message: format!("assertion failed: {left} != {right}"),
+        }),
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: "assert_eq expects exactly two arguments".to_owned(),
+        }),
+    }
+}
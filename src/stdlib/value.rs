@@ -0,0 +1,85 @@
+use crate::{
+    stdlib::NativeContext,
+    types::Literal,
+    vm::{ExecutionError, VMValue},
+};
+use std::borrow::Cow;
+
+pub fn is_nil<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    match args.as_slice() {
+        [value] => {
+            let is_nil = match value {
+                VMValue::Empty | VMValue::Nil => true,
+                VMValue::Literal(literal) => matches!(literal.as_ref(), Literal::Nil),
+                _ => false,
+            };
+
+            Ok(Some(VMValue::Literal(Cow::Owned(Literal::Boolean(is_nil)))))
+        }
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: "is_nil expects exactly one argument".to_owned(),
+        }),
+    }
+}
+
+pub fn to_string<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    match args.as_slice() {
+        [value] => Ok(Some(VMValue::Literal(Cow::Owned(Literal::String(
+            value.to_string(),
+        ))))),
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: "to_string expects exactly one argument".to_owned(),
+        }),
+    }
+}
+
+// a string that isn't a number gives back nil rather than an error, so callers can is_nil check it
+fn parse<'a, T>(
+    name: &str,
+    args: Vec<VMValue<'a>>,
+    to_literal: fn(T) -> Literal,
+) -> Result<Option<VMValue<'a>>, ExecutionError>
+where
+    T: std::str::FromStr,
+{
+    let [value] = args.as_slice() else {
+        return Err(ExecutionError::InvalidOperation {
+            cause: format!("{name} expects exactly one argument"),
+        });
+    };
+
+    let s = match value {
+        VMValue::Literal(literal) => match literal.as_ref() {
+            Literal::String(s) => Some(s),
+            _ => None,
+        },
+        _ => None,
+    }
+    .ok_or_else(|| ExecutionError::InvalidOperation {
+        cause: format!("{name} expects a string but got {}", value.type_name()),
+    })?;
+
+    Ok(Some(s.parse::<T>().map_or(VMValue::Nil, |n| {
+        VMValue::Literal(Cow::Owned(to_literal(n)))
+    })))
+}
+
+pub fn parse_int<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    parse("parse_int", args, Literal::Integer)
+}
+
+pub fn parse_float<'a>(
+    args: Vec<VMValue<'a>>,
+    _context: &mut NativeContext,
+) -> Result<Option<VMValue<'a>>, ExecutionError> {
+    parse("parse_float", args, Literal::Float)
+}
@@ -1,16 +1,58 @@
 use crate::{
-    ast::{self, Expression, Statement},
+    ast::{self, Expression, ObjectField, Statement},
+    error::DiagnosticEmitted,
+    lexer::Span,
     parser::{self},
+    stdlib,
+    types::Literal,
+};
+use codespan_reporting::{
+    diagnostic::{Diagnostic, Label},
+    files::Files,
+    term::termcolor::StandardStream,
 };
 use itertools::Itertools;
 use ordermap::OrderMap;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
-use types::{DefinedType, TypecheckerScope};
+use types::TypecheckerScope;
+use warnings::Unread;
+
+pub use typed_ast::{TypedExpression, TypedIterable, TypedStatement};
+pub use types::{DefinedType, NativeSignature, ParameterType, Symbol, SymbolKind};
 
+mod typed_ast;
 mod types;
+mod warnings;
+
+// everything a full check produces, for tools that want more than pass or fail
+pub struct Analysis<'a> {
+    pub typed_statements: Result<Vec<TypedStatement<'a>>, TypecheckerError>,
+    pub symbols: Vec<Symbol>,
+    // sorted by where they point
+    pub warnings: Vec<Diagnostic<usize>>,
+    // the innermost statement that failed to check, for errors that carry no span of their own
+    pub error_span: Option<Span>,
+}
 
 pub struct Typechecker {
     scope_stack: Vec<TypecheckerScope>,
+    // type of the first `break value;` in each enclosing loop
+    loop_break_types: Vec<Option<DefinedType>>,
+    // type and span of every return in each enclosing function, recorded in the scope it's written in
+    return_types: Vec<Vec<(DefinedType, Span)>>,
+    // top level functions whose return type couldn't be worked out before their body is checked
+    undeclared_functions: HashSet<String>,
+    // top level functions whose body doesn't check, a call before the definition reports why
+    failed_functions: HashMap<String, (TypecheckerError, Option<Span>)>,
+    // scopes are thrown away as we go, this keeps what was in them
+    symbols: Vec<Symbol>,
+    warnings: Vec<Diagnostic<usize>>,
+    // warn when a call that gives back a value is used as a statement
+    warn_unused_results: bool,
+    // the parser only keeps spans for statements inside blocks, callers can pass the top level ones
+    statement_spans: Vec<Span>,
+    error_span: Option<Span>,
     #[cfg(debug_assertions)]
     validated_types: Vec<String>,
 }
@@ -32,57 +74,172 @@ pub enum TypecheckerError {
     UnexpectedType { got: String },
     #[error("type error: {what} not found with name '{val}'")]
     NotFound { val: String, what: &'static str },
+    #[error("type error: '{name}' expects at least {expected} arguments but got {got}")]
+    TooFewArguments {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("type error: '{name}' expects at most {expected} arguments but got {got}")]
+    TooManyArguments {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("type error: if expression is missing an else")]
+    MissingElse,
+    #[error("type error: match is missing a `_` arm")]
+    NonExhaustiveMatch,
+    #[error("type error: {got} may be nil, compare it against nil first")]
+    PossiblyNil { got: String },
+    #[error("type error: const `{name}` can only be made of literals and other consts")]
+    NonConstant { name: String },
+    #[error("type error: `{name}` starts as an empty array, annotate what it holds like `let {name}: [int] = [];`")]
+    EmptyArray { name: String },
+    #[error("type error: the return type of `{name}` isn't known where it's called, annotate it with `-> type`")]
+    UnknownReturnType { name: String },
+    #[error("type error: {}", .0.message)]
+    Diagnostic(Diagnostic<usize>),
 }
 
-fn recursively_find_all_return<'a>(
-    statements: &'a Vec<Statement>,
-    collection: &mut Vec<&'a Expression>,
-) {
-    for statement in statements {
-        match statement {
-            Statement::Return(e) => collection.push(e),
-            Statement::If {
-                body,
-                else_statement,
-                ..
-            } => {
-                match body.as_ref() {
-                    Statement::Block { body } => recursively_find_all_return(body, collection),
-                    _ => unreachable!(),
-                };
-
-                if let Some(s) = else_statement {
-                    match s.as_ref() {
-                        Statement::Block { body } => recursively_find_all_return(body, collection),
-                        _ => unreachable!(),
-                    };
+impl TypecheckerError {
+    // diagnostics are printed like the parser's, anything else goes back to the caller as is
+    pub fn emit_diagnostic<'a, T>(
+        self,
+        writer: &StandardStream,
+        config: &codespan_reporting::term::Config,
+        files: &'a T,
+    ) -> Box<dyn std::error::Error>
+    where
+        T: Files<'a, FileId = usize> + 'a,
+    {
+        match self {
+            TypecheckerError::Diagnostic(diagnostic) => {
+                if let Err(e) =
+                    codespan_reporting::term::emit(&mut writer.lock(), config, files, &diagnostic)
+                {
+                    return e.into();
                 }
+
+                // cause statuscode to be set
+                DiagnosticEmitted.into()
             }
-            Statement::Block { body } => {
-                recursively_find_all_return(body, collection);
-            }
-            Statement::Loop { body } => {
-                match body.as_ref() {
-                    Statement::Block { body } => recursively_find_all_return(body, collection),
-                    _ => unreachable!(),
-                };
-            }
-            _ => {}
+            e => e.into(),
         }
     }
 }
 
+// a return that doesn't match the declared type, pointing at both
+fn return_type_mismatch(
+    function_name: &str,
+    expected: &DefinedType,
+    got: &DefinedType,
+    return_span: Span,
+    return_type_name: Option<&ast::TypeName>,
+) -> TypecheckerError {
+    let mut labels = vec![Label::primary(return_span.file_id, return_span)
+        .with_message(format!("expected {expected} but got {got}"))];
+    if let Some(type_name) = return_type_name {
+        labels.push(
+            Label::secondary(type_name.span.file_id, type_name.span).with_message(format!(
+                "`{function_name}` is declared to return {expected}"
+            )),
+        );
+    }
+
+    TypecheckerError::Diagnostic(
+        Diagnostic::error()
+            .with_message(format!("mismatched return type in `{function_name}`"))
+            .with_labels(labels),
+    )
+}
+
+// `[]` has no elements to take a type from, it takes the type of whatever it's assigned to
+fn is_empty_array(value: &TypedExpression) -> bool {
+    matches!(value, TypedExpression::Array { this, .. } if this.is_empty())
+}
+
+// `x == nil`, `nil != x` and so on, the variable and the operator
+fn nil_check(condition: &Expression) -> Option<(&str, &ast::Operator)> {
+    let Expression::Infix { op, lhs, rhs } = condition else {
+        return None;
+    };
+
+    if !matches!(op, ast::Operator::Equal | ast::Operator::NotEqual) {
+        return None;
+    }
+
+    match (lhs.as_ref(), rhs.as_ref()) {
+        (Expression::Variable(name), Expression::Literal(Literal::Nil))
+        | (Expression::Literal(Literal::Nil), Expression::Variable(name)) => Some((name, op)),
+        _ => None,
+    }
+}
+
+fn always_exits(block: &Statement) -> bool {
+    match block {
+        Statement::Block { body, .. } => matches!(
+            body.last(),
+            Some(Statement::Return(_) | Statement::Break(_) | Statement::Continue)
+        ),
+        _ => false,
+    }
+}
+
 impl Typechecker {
     pub fn new() -> Self {
-        let mut initial_scope = TypecheckerScope::new();
-        // FIXME: read from map with macro or something to generate this
-        initial_scope.define_function_return("print".to_owned(), DefinedType::Nil);
-
-        Self {
-            scope_stack: vec![initial_scope],
+        let mut typechecker = Self {
+            scope_stack: vec![TypecheckerScope::new()],
+            loop_break_types: vec![],
+            return_types: vec![],
+            undeclared_functions: HashSet::new(),
+            failed_functions: HashMap::new(),
+            symbols: vec![],
+            warnings: vec![],
+            warn_unused_results: false,
+            statement_spans: vec![],
+            error_span: None,
             #[cfg(debug_assertions)]
             validated_types: vec![],
+        };
+
+        for (name, signature) in stdlib::standard_library_signatures() {
+            typechecker.declare_native(name, signature);
         }
+
+        typechecker
+    }
+
+    pub fn with_unused_result_warnings(mut self) -> Self {
+        self.warn_unused_results = true;
+        self
+    }
+
+    // one per statement passed to check or analyze
+    pub fn with_statement_spans(mut self, spans: Vec<Span>) -> Self {
+        self.statement_spans = spans;
+        self
+    }
+
+    // for functions added to the vm with define_native_function
+    #[allow(unused)]
+    pub fn declare_native(&mut self, name: &str, signature: NativeSignature) {
+        let NativeSignature {
+            parameters,
+            required,
+            variadic,
+            return_type,
+        } = signature;
+        let total = if variadic {
+            usize::MAX
+        } else {
+            parameters.len()
+        };
+
+        let scope = self.scope_stack.first_mut().unwrap();
+        scope.define_arity(name.to_owned(), Some((required, total)));
+        scope.define_parameters(name.to_owned(), Some((parameters, variadic)));
+        scope.define_function_return(name.to_owned(), return_type);
     }
 
     #[inline(always)]
@@ -109,10 +266,74 @@ impl Typechecker {
     }
 
     fn remove_scope(&mut self) {
-        self.scope_stack.pop();
+        if let Some(mut scope) = self.scope_stack.pop() {
+            self.warn_unread(&mut scope);
+        }
+    }
+
+    fn warn_unread(&mut self, scope: &mut TypecheckerScope) {
+        let unread = scope.take_unread();
+        self.warnings
+            .extend(unread.into_iter().map(warnings::unused));
+    }
+
+    fn track_unread(&mut self, name: &str, span: Span, what: &'static str) {
+        if name.starts_with('_') {
+            return;
+        }
+
+        let unread = Unread {
+            name: name.to_owned(),
+            span,
+            what,
+        };
+
+        let shadowed = self.scope_stack.last_mut().unwrap().track_unread(unread);
+        if let Some(shadowed) = shadowed {
+            self.warnings.push(warnings::unused(shadowed));
+        }
+    }
+
+    // counts as a read of the innermost definition only
+    fn mark_read(&mut self, name: &str) {
+        for scope in self.scope_stack.iter_mut().rev() {
+            if scope.get_type_for(name).is_some() {
+                scope.mark_read(name);
+                return;
+            }
+        }
     }
 
-    fn define_function_return_current_scope(&mut self, name: String, type_name: DefinedType) {
+    // only the first statement after a return or break is reported
+    fn warn_unreachable(&mut self, body: &[Statement], spans: &[Span]) {
+        let exit = body.iter().position(|s| {
+            matches!(
+                s,
+                Statement::Return(_) | Statement::Break(_) | Statement::Continue
+            )
+        });
+
+        if let Some(exit) = exit {
+            if let (Some(exit_span), Some(next_span)) = (spans.get(exit), spans.get(exit + 1)) {
+                self.warnings
+                    .push(warnings::unreachable(*exit_span, *next_span));
+            }
+        }
+    }
+
+    fn define_function_return_current_scope(
+        &mut self,
+        name: String,
+        type_name: DefinedType,
+        span: Span,
+    ) {
+        self.symbols.push(Symbol {
+            name: name.clone(),
+            kind: SymbolKind::Function,
+            defined_type: type_name.clone(),
+            span,
+        });
+
         self.scope_stack
             .last_mut()
             .unwrap()
@@ -130,8 +351,145 @@ impl Typechecker {
         None
     }
 
-    fn define_type_current_scope(&mut self, name: String, type_name: DefinedType) {
-        self.scope_stack.last_mut().unwrap().define(name, type_name);
+    // the variadic signature of the closest function with this name
+    fn resolve_variadic(&self, name: &str) -> Option<&(usize, DefinedType)> {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find(|scope| scope.get_function_return_for(name).is_some())
+            .and_then(|scope| scope.get_variadic_for(name))
+    }
+
+    // what each argument to the closest function with this name has to be, natives only
+    fn resolve_parameters(&self, name: &str) -> Option<&(Vec<ParameterType>, bool)> {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find(|scope| scope.get_function_return_for(name).is_some())
+            .and_then(|scope| scope.get_parameters_for(name))
+    }
+
+    // what each argument to the closest function with this name has to be, sol functions only
+    fn resolve_parameter_types(&self, name: &str) -> Option<&Vec<DefinedType>> {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find(|scope| scope.get_function_return_for(name).is_some())
+            .and_then(|scope| scope.get_parameter_types_for(name))
+    }
+
+    // the argument count range of the closest function with this name, if it has defaults
+    fn resolve_arity(&self, name: &str) -> Option<&(usize, usize)> {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find(|scope| scope.get_function_return_for(name).is_some())
+            .and_then(|scope| scope.get_arity_for(name))
+    }
+
+    fn define_type_current_scope(
+        &mut self,
+        name: String,
+        type_name: DefinedType,
+        is_mutable: bool,
+        span: Span,
+    ) {
+        self.symbols.push(Symbol {
+            name: name.clone(),
+            kind: SymbolKind::Variable,
+            defined_type: type_name.clone(),
+            span,
+        });
+
+        self.scope_stack
+            .last_mut()
+            .unwrap()
+            .define(name, type_name, is_mutable);
+    }
+
+    // the innermost definition decides, a `let mut` can shadow a `let`
+    fn is_mutable(&self, name: &str) -> bool {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find(|scope| scope.get_type_for(name).is_some())
+            .is_some_and(|scope| scope.is_mutable(name))
+    }
+
+    // points at the assignment, and at the `let` that needs a `mut` when there is one
+    fn immutable_assignment(&self, name: &str, span: Span) -> TypecheckerError {
+        let declared_at = self
+            .scope_stack
+            .iter()
+            .rev()
+            .find(|scope| scope.get_type_for(name).is_some())
+            .and_then(|scope| scope.declared_at(name));
+
+        let mut labels =
+            vec![Label::primary(span.file_id, span).with_message("cannot assign twice")];
+        if let Some(declared_at) = declared_at {
+            labels.push(
+                Label::secondary(declared_at.file_id, declared_at)
+                    .with_message(format!("declared here, use `let mut {name}` to change it")),
+            );
+        }
+
+        TypecheckerError::Diagnostic(
+            Diagnostic::error()
+                .with_message(format!(
+                    "cannot assign twice to immutable variable `{name}`"
+                ))
+                .with_labels(labels),
+        )
+    }
+
+    // `int("12")` and friends, the conversion they want is `as`
+    fn cast_argument(name: &str, got: &DefinedType, span: Span) -> TypecheckerError {
+        let mut diagnostic = Diagnostic::error()
+            .with_message(format!("`{name}` only takes numbers"))
+            .with_labels(vec![
+                Label::primary(span.file_id, span).with_message(format!("this is {got}"))
+            ]);
+        if matches!(got, DefinedType::String | DefinedType::Bool) {
+            diagnostic =
+                diagnostic.with_notes(vec![format!("use `as` to convert it: `x as {name}`")]);
+        }
+
+        TypecheckerError::Diagnostic(diagnostic)
+    }
+
+    fn warn_duplicate(&mut self, name: &str, span: Span) {
+        let previous = self.scope_stack.last_mut().unwrap().declare(name, span);
+        if name.starts_with('_') {
+            return;
+        }
+
+        if let Some(previous) = previous {
+            self.warnings
+                .push(warnings::duplicate(name, span, previous));
+        }
+    }
+
+    // only the closest variable with this name counts, a `let` can shadow a const
+    fn resolve_is_constant(&self, name: &str) -> bool {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find(|scope| scope.get_type_for(name).is_some())
+            .is_some_and(|scope| scope.is_constant(name))
+    }
+
+    // what a const can be made of, the compiler folds these
+    fn is_constant_expression(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Literal(_) => true,
+            Expression::Variable(name) => self.resolve_is_constant(name),
+            Expression::Prefix { expr, .. } => self.is_constant_expression(expr),
+            Expression::Infix { lhs, rhs, .. } => {
+                self.is_constant_expression(lhs) && self.is_constant_expression(rhs)
+            }
+            _ => false,
+        }
     }
 
     fn resolve_type(&mut self, name: &str) -> Option<&DefinedType> {
@@ -145,66 +503,299 @@ impl Typechecker {
         None
     }
 
-    fn typecheck_statement(&mut self, statement: &Statement) -> Result<(), TypecheckerError> {
+    fn typecheck_statement<'a>(
+        &mut self,
+        statement: &'a Statement,
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
         match statement {
             Statement::Const {
                 name,
                 value,
                 type_name,
-            } => self.typecheck_let(
-                name,
-                value,
-                type_name,
-                #[cfg(debug_assertions)]
-                "const",
-            ),
+                span,
+            } => {
+                if !self.is_constant_expression(value) {
+                    return Err(TypecheckerError::NonConstant {
+                        name: name.to_owned(),
+                    });
+                }
+
+                let value = self.typecheck_let(
+                    name,
+                    value,
+                    type_name,
+                    false,
+                    *span,
+                    #[cfg(debug_assertions)]
+                    "const",
+                )?;
+                self.scope_stack
+                    .last_mut()
+                    .unwrap()
+                    .define_constant(name.to_owned());
+
+                Ok(TypedStatement::Const { name, value })
+            }
             Statement::Let {
                 name,
                 value,
                 type_name,
-                ..
-            } => self.typecheck_let(
-                name,
-                value,
-                type_name,
-                #[cfg(debug_assertions)]
-                "let",
-            ),
-            Statement::Block { body } => self.typecheck_block(body),
-            Statement::Reassignment { name, value } => self.typecheck_reassignment(name, value),
-            Statement::ObjectMutation { path, value } => {
-                self.typecheck_object_mutation(path, value)
+                is_mutable,
+                span,
+            } => {
+                let value = self.typecheck_let(
+                    name,
+                    value,
+                    type_name,
+                    *is_mutable,
+                    *span,
+                    #[cfg(debug_assertions)]
+                    "let",
+                )?;
+                self.warn_duplicate(name, *span);
+                self.track_unread(name, *span, "variable");
+
+                Ok(TypedStatement::Let {
+                    name,
+                    value,
+                    is_mutable: *is_mutable,
+                })
+            }
+            Statement::Block { body, spans } => self.typecheck_block(body, spans),
+            Statement::Reassignment { name, value, span } => {
+                self.typecheck_reassignment(name, value, *span)
+            }
+            Statement::ObjectMutation { path, value, span } => {
+                self.typecheck_object_mutation(path, value, *span)
             }
             Statement::If {
                 condition,
                 body,
                 else_statement,
             } => self.typecheck_if(condition, body, else_statement),
-            Statement::Loop { body } => self.typecheck_statement(body),
+            Statement::Loop { body } => {
+                self.loop_break_types.push(None);
+                let typed_body = self.typecheck_statement(body);
+                self.loop_break_types.pop();
+
+                Ok(TypedStatement::Loop {
+                    body: Box::new(typed_body?),
+                })
+            }
+            Statement::For {
+                binding,
+                iterable,
+                body,
+                span,
+            } => self.typecheck_for(binding, iterable, body, *span),
+            Statement::Match { value, arms } => self.typecheck_match(value, arms),
             Statement::Function(function) => self.typecheck_function(function),
-            Statement::Expression(expression) => self.typecheck_expression(expression).map(|_| ()),
-            Statement::Return(expression) => self.typecheck_expression(expression).map(|_| ()),
-            Statement::Break => Ok(()),
+            Statement::Expression(expression) => self
+                .typecheck_expression(expression)
+                .map(TypedStatement::Expression),
+            Statement::Return(expression) => self
+                .typecheck_expression(expression)
+                .map(TypedStatement::Return),
+            Statement::Break(None) => Ok(TypedStatement::Break(None)),
+            Statement::Break(Some(value)) => self.typecheck_break(value),
+            Statement::Continue => Ok(TypedStatement::Continue),
         }
     }
 
-    fn typecheck_block(&mut self, body: &Vec<Statement>) -> Result<(), TypecheckerError> {
+    // the loop variable is the element type, or an int for ranges
+    fn typecheck_for<'a>(
+        &mut self,
+        binding: &'a str,
+        iterable: &'a ast::Iterable,
+        body: &'a Statement,
+        span: Span,
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
+        let expect_int = |typed: &TypedExpression| {
+            let t = typed.defined_type();
+            if *t != DefinedType::I64 {
+                return Err(TypecheckerError::TypeMismatch {
+                    expected: "int".to_string(),
+                    got: t.to_string(),
+                });
+            }
+
+            Ok(())
+        };
+
+        let (iterable, element_type) = match iterable {
+            ast::Iterable::Array(array) => {
+                let array = self.typecheck_expression(array)?;
+                let element_type = match array.defined_type() {
+                    DefinedType::Array(element_type) => *element_type.clone(),
+                    t => return Err(TypecheckerError::UnexpectedType { got: t.to_string() }),
+                };
+
+                (TypedIterable::Array(array), element_type)
+            }
+            ast::Iterable::Range { start, end } => {
+                let start = self.typecheck_expression(start)?;
+                expect_int(&start)?;
+                let end = self.typecheck_expression(end)?;
+                expect_int(&end)?;
+
+                (TypedIterable::Range { start, end }, DefinedType::I64)
+            }
+        };
+
         self.add_scope();
+        self.define_type_current_scope(binding.to_owned(), element_type, false, span);
+
+        self.loop_break_types.push(None);
+        let typed_body = self.typecheck_statement(body);
+        self.loop_break_types.pop();
+        self.remove_scope();
+
+        Ok(TypedStatement::For {
+            binding,
+            iterable,
+            body: Box::new(typed_body?),
+        })
+    }
+
+    // patterns have to be the same type as the value, and something has to match
+    fn typecheck_match<'a>(
+        &mut self,
+        value: &'a Expression,
+        arms: &'a [ast::MatchArm],
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
+        let value = self.typecheck_expression(value)?;
+        let value_type = value.defined_type().clone();
+
+        let mut has_wildcard = false;
+        let mut typed_arms = Vec::with_capacity(arms.len());
+        for arm in arms {
+            match &arm.pattern {
+                ast::Pattern::Literal(literal) => {
+                    let pattern_type = DefinedType::from(literal);
+                    if pattern_type != value_type {
+                        return Err(TypecheckerError::TypeMismatch {
+                            expected: value_type.to_string(),
+                            got: pattern_type.to_string(),
+                        });
+                    }
+                }
+                ast::Pattern::Wildcard => has_wildcard = true,
+            }
+
+            typed_arms.push((&arm.pattern, self.typecheck_statement(&arm.body)?));
+        }
+
+        let covers_bool = |b| {
+            arms.iter()
+                .any(|arm| arm.pattern == ast::Pattern::Literal(Literal::Boolean(b)))
+        };
+        let exhaustive = has_wildcard
+            || (value_type == DefinedType::Bool && covers_bool(true) && covers_bool(false));
+        if !exhaustive {
+            return Err(TypecheckerError::NonExhaustiveMatch);
+        }
+
+        Ok(TypedStatement::Match {
+            value,
+            arms: typed_arms,
+        })
+    }
+
+    // every break in a loop has to agree on what the loop produces
+    fn typecheck_break<'a>(
+        &mut self,
+        value: &'a Expression,
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
+        let typed_value = self.typecheck_expression(value)?;
+        let value_type = typed_value.defined_type().clone();
 
-        for s in body {
-            self.typecheck_statement(s)?;
+        if let Some(break_type) = self.loop_break_types.last_mut() {
+            match break_type {
+                Some(expected) if *expected != value_type => {
+                    return Err(TypecheckerError::TypeMismatch {
+                        expected: expected.to_string(),
+                        got: value_type.to_string(),
+                    });
+                }
+                Some(_) => {}
+                None => *break_type = Some(value_type),
+            }
         }
 
+        Ok(TypedStatement::Break(Some(typed_value)))
+    }
+
+    fn typecheck_block<'a>(
+        &mut self,
+        body: &'a [Statement],
+        spans: &[Span],
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
+        self.add_scope();
+        self.warn_unreachable(body, spans);
+
+        let typed_body = self.typecheck_body(body, spans)?;
+
         self.remove_scope();
 
-        Ok(())
+        Ok(TypedStatement::Block { body: typed_body })
     }
 
-    fn typecheck_reassignment(
+    // an error passes out through every statement around it, only the innermost is kept
+    fn failed_at(&mut self, span: Option<Span>) {
+        if self.error_span.is_none() {
+            self.error_span = span;
+        }
+    }
+
+    // top level statements only have a span if the caller passed them in, otherwise the
+    // warning goes out without a label
+    fn warn_unused_result(&mut self, statement: &TypedStatement, span: Option<Span>) {
+        if !self.warn_unused_results {
+            return;
+        }
+
+        if let TypedStatement::Expression(TypedExpression::FunctionCall {
+            name,
+            defined_type,
+            ..
+        }) = statement
+        {
+            if *defined_type != DefinedType::Nil {
+                self.warnings.push(warnings::unused_result(name, span));
+            }
+        }
+    }
+
+    // the statements of a block, keeping hold of what each return gives back for the enclosing function
+    fn typecheck_body<'a>(
         &mut self,
-        name: &str,
-        value: &Expression,
-    ) -> Result<(), TypecheckerError> {
+        body: &'a [Statement],
+        spans: &[Span],
+    ) -> Result<Vec<TypedStatement<'a>>, TypecheckerError> {
+        let mut typed_body = Vec::with_capacity(body.len());
+        for (statement, span) in body.iter().zip(spans) {
+            let typed_statement = self
+                .typecheck_statement(statement)
+                .inspect_err(|_| self.failed_at(Some(*span)))?;
+            self.warn_unused_result(&typed_statement, Some(*span));
+            if let TypedStatement::Return(expression) = &typed_statement {
+                if let Some(return_types) = self.return_types.last_mut() {
+                    return_types.push((expression.defined_type().clone(), *span));
+                }
+            }
+
+            typed_body.push(typed_statement);
+        }
+
+        Ok(typed_body)
+    }
+
+    fn typecheck_reassignment<'a>(
+        &mut self,
+        name: &'a str,
+        value: &'a Expression,
+        span: Span,
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
         let existing_var_type =
             self.resolve_type(name)
                 .cloned()
@@ -213,10 +804,17 @@ impl Typechecker {
                     what: "variable",
                 })?;
 
-        let new_var_type = self.typecheck_expression(value)?;
+        if !self.is_mutable(name) {
+            return Err(self.immutable_assignment(name, span));
+        }
+
+        let value = self.typecheck_expression(value)?;
+        let new_var_type = value.defined_type();
 
-        if existing_var_type == new_var_type {
-            Ok(())
+        let empty_array_fits =
+            is_empty_array(&value) && matches!(existing_var_type, DefinedType::Array(_));
+        if empty_array_fits || existing_var_type.accepts(new_var_type) {
+            Ok(TypedStatement::Reassignment { name, value })
         } else {
             Err(TypecheckerError::TypeMismatch {
                 expected: existing_var_type.to_string(),
@@ -225,99 +823,340 @@ impl Typechecker {
         }
     }
 
-    fn typecheck_object_mutation(
+    fn typecheck_object_mutation<'a>(
         &mut self,
-        path: &Expression,
-        value: &Expression,
-    ) -> Result<(), TypecheckerError> {
-        let path_type = self.typecheck_expression(path)?;
-        let value_type = self.typecheck_expression(value)?;
+        path: &'a Expression,
+        value: &'a Expression,
+        span: Span,
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
+        // fields belong to the binding, a `let` object can't have them changed
+        if let Expression::ObjectAccess { path } = path {
+            let base = path.first().unwrap();
+            if self.resolve_type(base).is_some() && !self.is_mutable(base) {
+                return Err(self.immutable_assignment(base, span));
+            }
+        }
 
-        if path_type == value_type {
-            Ok(())
+        let path = self.typecheck_expression(path)?;
+        let value = self.typecheck_expression(value)?;
+
+        if path.defined_type() == value.defined_type() {
+            Ok(TypedStatement::ObjectMutation { path, value })
         } else {
             Err(TypecheckerError::TypeMismatch {
-                expected: path_type.to_string(),
-                got: value_type.to_string(),
+                expected: path.defined_type().to_string(),
+                got: value.defined_type().to_string(),
             })
         }
     }
 
-    fn typecheck_if(
+    fn typecheck_if<'a>(
         &mut self,
-        condition: &Expression,
-        body: &Statement,
-        else_statement: &Option<Box<Statement>>,
-    ) -> Result<(), TypecheckerError> {
-        let t = self.typecheck_expression(condition)?;
-        if t != DefinedType::Bool {
+        condition: &'a Expression,
+        body: &'a Statement,
+        else_statement: &'a Option<Box<Statement>>,
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
+        // comparing an optional against nil tells us which branch has the inner type
+        let narrowed = nil_check(condition).and_then(|(name, op)| match self.resolve_type(name) {
+            Some(DefinedType::Optional(inner)) => Some((name, op, *inner.clone())),
+            _ => None,
+        });
+        let body_exits = always_exits(body);
+
+        let condition = self.typecheck_expression(condition)?;
+        let t = condition.defined_type();
+        if *t != DefinedType::Bool {
             return Err(TypecheckerError::TypeMismatch {
                 expected: "bool".to_string(),
                 got: t.to_string(),
             });
         }
 
-        self.typecheck_statement(body)?;
-        if let Some(else_statement) = else_statement {
-            self.typecheck_statement(else_statement)?;
+        let body = match &narrowed {
+            Some((name, ast::Operator::NotEqual, inner)) => {
+                self.typecheck_narrowed(body, name, inner.clone())?
+            }
+            _ => self.typecheck_statement(body)?,
+        };
+        let else_statement = match (else_statement, &narrowed) {
+            (Some(else_statement), Some((name, ast::Operator::Equal, inner))) => Some(Box::new(
+                self.typecheck_narrowed(else_statement, name, inner.clone())?,
+            )),
+            (Some(else_statement), _) => Some(Box::new(self.typecheck_statement(else_statement)?)),
+            (None, _) => None,
+        };
+
+        // `if x == nil { return; }` leaves x not nil for the rest of the block
+        if let (None, Some((name, ast::Operator::Equal, inner))) =
+            (else_statement.as_ref(), narrowed)
+        {
+            if body_exits {
+                self.narrow(name, inner);
+            }
+        }
+
+        Ok(TypedStatement::If {
+            condition,
+            body: Box::new(body),
+            else_statement,
+        })
+    }
+
+    fn typecheck_narrowed<'a>(
+        &mut self,
+        statement: &'a Statement,
+        name: &str,
+        inner: DefinedType,
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
+        self.add_scope();
+        self.narrow(name, inner);
+        let typed_statement = self.typecheck_statement(statement);
+        self.remove_scope();
+
+        typed_statement
+    }
+
+    // shadows the variable's type in the current scope, keeping whether it can be assigned
+    // and whether it's a const
+    fn narrow(&mut self, name: &str, inner: DefinedType) {
+        let is_mutable = self.is_mutable(name);
+        let is_constant = self.resolve_is_constant(name);
+
+        let scope = self.scope_stack.last_mut().unwrap();
+        scope.define(name.to_owned(), inner, is_mutable);
+        if is_constant {
+            scope.define_constant(name.to_owned());
+        }
+    }
+
+    // what a call needs to know about a function, without looking at its body
+    fn declare_function(&mut self, function: &ast::Function) -> Result<(), TypecheckerError> {
+        let ast::Function {
+            name,
+            return_type_name,
+            parameters,
+            ..
+        } = function;
+
+        let mut variadic = None;
+        let mut parameter_types = Vec::with_capacity(parameters.len());
+        for (i, parameter) in parameters.iter().enumerate() {
+            let defined_type = DefinedType::try_from(&parameter.type_name)?;
+            if parameter.is_variadic {
+                variadic = Some((i, defined_type));
+            } else {
+                parameter_types.push(defined_type);
+            }
+        }
+        let required = parameters
+            .iter()
+            .take_while(|p| p.default_value.is_none())
+            .count();
+        // a variadic parameter after the defaults takes any number of what's left
+        let total = if variadic.is_some() {
+            usize::MAX
+        } else {
+            parameters.len()
+        };
+        let arity = (required < parameters.len()).then_some((required, total));
+
+        let scope = self.scope_stack.last_mut().unwrap();
+        scope.define_variadic(name.to_owned(), variadic);
+        scope.define_arity(name.to_owned(), arity);
+        // a native of the same name doesn't get to check this one's arguments
+        scope.define_parameters(name.to_owned(), None);
+        scope.define_parameter_types(name.to_owned(), parameter_types);
+
+        // known up front so the body can call it recursively
+        if let Some(return_type_name) = return_type_name {
+            let return_type = DefinedType::try_from(return_type_name)?;
+            scope.define_function_return(name.to_owned(), return_type);
         }
 
         Ok(())
     }
 
-    fn typecheck_function(&mut self, function: &ast::Function) -> Result<(), TypecheckerError> {
+    // top level functions can be called before they're defined, annotated ones are declared
+    // straight away and the rest are inferred in whatever order their bodies allow
+    fn declare_functions(&mut self, statements: &[Statement]) {
+        let mut pending = Vec::new();
+        for statement in statements {
+            if let Statement::Function(function) = statement {
+                // anything wrong is reported when the function itself is checked
+                let _ = self.declare_function(function);
+                if function.return_type_name.is_none() {
+                    pending.push(function);
+                }
+            }
+        }
+
+        // calls to functions still waiting on a type fail with UnknownReturnType, any other
+        // failure is a mistake in the body itself
+        let mut failures = HashMap::new();
+        loop {
+            self.undeclared_functions = pending.iter().map(|f| f.name.to_owned()).collect();
+
+            let before = pending.len();
+            pending.retain(|function| match self.infer_return_type(function) {
+                Ok(()) => false,
+                Err(error) => {
+                    let span = self.error_span.take();
+                    failures.insert(function.name.to_owned(), (error, span));
+                    true
+                }
+            });
+            if pending.len() == before {
+                break;
+            }
+        }
+
+        self.undeclared_functions.clear();
+        for function in pending {
+            match failures.remove(&function.name) {
+                Some((TypecheckerError::UnknownReturnType { .. }, _)) | None => {
+                    self.undeclared_functions.insert(function.name.to_owned());
+                }
+                Some(failure) => {
+                    self.failed_functions
+                        .insert(function.name.to_owned(), failure);
+                }
+            }
+        }
+    }
+
+    // a dry run of the function, only its return type or error is kept, with the statement
+    // that failed left in error_span
+    fn infer_return_type(&mut self, function: &ast::Function) -> Result<(), TypecheckerError> {
+        let scopes = self.scope_stack.len();
+        let loops = self.loop_break_types.len();
+        let functions = self.return_types.len();
+        let symbols = self.symbols.len();
+        let warnings = self.warnings.len();
+        #[cfg(debug_assertions)]
+        let validated_types = self.validated_types.len();
+
+        let inferred = self.typecheck_function(function).map(|_| ());
+
+        self.scope_stack.truncate(scopes);
+        self.loop_break_types.truncate(loops);
+        self.return_types.truncate(functions);
+        self.symbols.truncate(symbols);
+        self.warnings.truncate(warnings);
+        #[cfg(debug_assertions)]
+        self.validated_types.truncate(validated_types);
+
+        inferred
+    }
+
+    fn typecheck_function<'a>(
+        &mut self,
+        function: &'a ast::Function,
+    ) -> Result<TypedStatement<'a>, TypecheckerError> {
         let ast::Function {
             name,
             return_type_name,
             body,
             parameters,
+            span,
         } = function;
 
-        let statements = match body.as_ref() {
-            Statement::Block { body } => body,
+        let (statements, spans) = match body.as_ref() {
+            Statement::Block { body, spans } => (body, spans),
             _ => unreachable!(),
         };
+        self.warn_unreachable(statements, spans);
+        self.declare_function(function)?;
 
+        // parameters and locals shouldn't leak into, or be mistaken for redeclarations in, the caller's scope
+        self.add_scope();
+
+        let mut typed_parameters = Vec::with_capacity(parameters.len());
         for parameter in parameters {
+            let mut defined_type = DefinedType::try_from(&parameter.type_name)?;
+            if parameter.is_variadic {
+                defined_type = DefinedType::Array(Box::new(defined_type));
+            }
+
+            if let Some(default_value) = &parameter.default_value {
+                let default_type = DefinedType::from(default_value);
+                if !defined_type.accepts_widening(&default_type) {
+                    return Err(TypecheckerError::TypeMismatch {
+                        expected: defined_type.to_string(),
+                        got: default_type.to_string(),
+                    });
+                }
+            }
             self.define_type_current_scope(
                 parameter.name.to_string(),
-                DefinedType::try_from(&parameter.type_name)?,
+                defined_type.clone(),
+                false,
+                parameter.span,
             );
+            self.track_unread(&parameter.name, parameter.span, "parameter");
+            typed_parameters.push((parameter.name.as_str(), defined_type));
         }
 
-        for statement in statements {
-            self.typecheck_statement(statement)?
-        }
+        self.return_types.push(Vec::new());
+        let typed_body = self.typecheck_body(statements, spans);
+        let (return_types, return_spans): (Vec<_>, Vec<_>) =
+            self.return_types.pop().unwrap().into_iter().unzip();
+        let typed_body = typed_body?;
 
         let defined_return_type = return_type_name.as_ref().map(DefinedType::try_from);
 
-        let mut return_statements = Vec::new();
-        recursively_find_all_return(statements, &mut return_statements);
+        self.remove_scope();
+
+        // an optional return type takes any mix of the inner type and nil
+        if let Some(Ok(optional @ DefinedType::Optional(_))) = &defined_return_type {
+            if let Some(i) = return_types
+                .iter()
+                .position(|t| !optional.accepts_widening(t))
+            {
+                return Err(return_type_mismatch(
+                    name,
+                    optional,
+                    &return_types[i],
+                    return_spans[i],
+                    return_type_name.as_ref(),
+                ));
+            }
+
+            self.define_function_return_current_scope(name.to_owned(), optional.clone(), *span);
 
-        let mut return_types = Vec::with_capacity(return_statements.len());
-        for return_statement in return_statements {
-            // FIXME: we can't evaluate these like this?
-            let defined_ret_type = self.typecheck_expression(return_statement)?;
-            return_types.push(defined_ret_type);
+            return Ok(TypedStatement::Function {
+                name,
+                parameters: typed_parameters,
+                return_type: optional.clone(),
+                body: typed_body,
+            });
         }
 
         let all_equal = return_types.iter().all_equal_value();
-        match all_equal {
+        let return_type = match all_equal {
             Ok(inferred_type) => {
                 if let Some(dt) = defined_return_type {
                     let func_ret_type = dt?;
-                    if func_ret_type == *inferred_type {
+                    if func_ret_type.accepts_widening(inferred_type) {
                         self.add_validated_types_for_debug(format!(
                             "{:8} -> inferred: {inferred_type}, defined: {func_ret_type}",
                             "fn"
                         ));
 
-                        self.define_function_return_current_scope(name.to_owned(), func_ret_type);
+                        self.define_function_return_current_scope(
+                            name.to_owned(),
+                            func_ret_type.clone(),
+                            *span,
+                        );
+
+                        func_ret_type
                     } else {
-                        return Err(TypecheckerError::TypeMismatch {
-                            expected: func_ret_type.to_string(),
-                            got: inferred_type.to_string(),
-                        });
+                        return Err(return_type_mismatch(
+                            name,
+                            &func_ret_type,
+                            inferred_type,
+                            return_spans[0],
+                            return_type_name.as_ref(),
+                        ));
                     }
                 } else {
                     self.add_validated_types_for_debug(format!(
@@ -328,35 +1167,86 @@ impl Typechecker {
                     self.define_function_return_current_scope(
                         name.to_owned(),
                         inferred_type.clone(),
+                        *span,
                     );
+
+                    inferred_type.clone()
                 }
             }
             Err(types) => {
-                if let Some((mismatch1, mismatch2)) = types {
-                    return Err(TypecheckerError::TypeMismatchBothWrong {
-                        mismatch1: mismatch1.to_string(),
-                        mismatch2: mismatch2.to_string(),
-                    });
+                if let (Some(Ok(declared)), Some(_)) = (&defined_return_type, &types) {
+                    // a float return type takes a mix of ints and floats, otherwise
+                    // the first return that doesn't fit is the one that's wrong
+                    if let Some(i) = return_types
+                        .iter()
+                        .position(|t| !declared.accepts_widening(t))
+                    {
+                        return Err(return_type_mismatch(
+                            name,
+                            declared,
+                            &return_types[i],
+                            return_spans[i],
+                            return_type_name.as_ref(),
+                        ));
+                    }
+
+                    self.define_function_return_current_scope(
+                        name.to_owned(),
+                        declared.clone(),
+                        *span,
+                    );
+
+                    declared.clone()
+                } else if let Some((first, other)) = types {
+                    // all_equal_value hands back the first return and the first one that differs
+                    let other_span =
+                        return_spans[return_types.iter().position(|t| t == other).unwrap()];
+
+                    return Err(TypecheckerError::Diagnostic(
+                        Diagnostic::error()
+                            .with_message(format!("`{name}` returns different types"))
+                            .with_labels(vec![
+                                Label::primary(other_span.file_id, other_span)
+                                    .with_message(format!("this returns {other}")),
+                                Label::secondary(return_spans[0].file_id, return_spans[0])
+                                    .with_message(format!("but this returns {first}")),
+                            ]),
+                    ));
                 } else {
                     self.add_validated_types_for_debug(format!("{:8} -> nil", "fn"));
 
-                    self.define_function_return_current_scope(name.to_owned(), DefinedType::Nil);
+                    self.define_function_return_current_scope(
+                        name.to_owned(),
+                        DefinedType::Nil,
+                        *span,
+                    );
+
+                    DefinedType::Nil
                 }
             }
         };
 
-        Ok(())
+        Ok(TypedStatement::Function {
+            name,
+            parameters: typed_parameters,
+            return_type,
+            body: typed_body,
+        })
     }
 
     // we return the typename of the expression return value
-    fn typecheck_expression(&mut self, expr: &Expression) -> Result<DefinedType, TypecheckerError> {
+    fn typecheck_expression<'a>(
+        &mut self,
+        expr: &'a Expression,
+    ) -> Result<TypedExpression<'a>, TypecheckerError> {
         let is_numeric = |t: &DefinedType| *t == DefinedType::I64 || *t == DefinedType::F64;
 
         match expr {
             Expression::Prefix { op, expr } => {
-                let expr = self.typecheck_expression(expr)?;
+                let typed_expr = self.typecheck_expression(expr)?;
+                let expr = typed_expr.defined_type().clone();
 
-                match op {
+                let defined_type = match op {
                     ast::Operator::Plus | ast::Operator::Minus => {
                         if is_numeric(&expr) {
                             Ok(expr)
@@ -377,18 +1267,58 @@ impl Typechecker {
                             })
                         }
                     }
+                    ast::Operator::BitNot => {
+                        if expr == DefinedType::I64 {
+                            Ok(expr)
+                        } else {
+                            Err(TypecheckerError::TypeMismatch {
+                                expected: "int".to_string(),
+                                got: expr.to_string(),
+                            })
+                        }
+                    }
                     _ => unreachable!(),
-                }
+                }?;
+
+                Ok(TypedExpression::Prefix {
+                    op,
+                    expr: Box::new(typed_expr),
+                    defined_type,
+                })
             }
             Expression::Infix { op, lhs, rhs } => {
-                let lhs = self.typecheck_expression(lhs)?;
-                let rhs = self.typecheck_expression(rhs)?;
+                let typed_lhs = self.typecheck_expression(lhs)?;
+                let typed_rhs = self.typecheck_expression(rhs)?;
+                let lhs = typed_lhs.defined_type().clone();
+                let rhs = typed_rhs.defined_type().clone();
+
+                let possibly_nil = [(&typed_lhs, &lhs), (&typed_rhs, &rhs)]
+                    .into_iter()
+                    .find(|(_, t)| matches!(t, DefinedType::Optional(_)));
 
-                match op {
+                let defined_type = match op {
+                    // nothing but a comparison can use it until it's been checked against nil
+                    op if !matches!(op, ast::Operator::Equal | ast::Operator::NotEqual)
+                        && possibly_nil.is_some() =>
+                    {
+                        let (typed, t) = possibly_nil.unwrap();
+                        Err(TypecheckerError::PossiblyNil {
+                            got: match typed {
+                                TypedExpression::Variable { name, .. } => format!("`{name}`"),
+                                _ => t.to_string(),
+                            },
+                        })
+                    }
+                    ast::Operator::Plus
+                        if lhs == DefinedType::String && rhs == DefinedType::String =>
+                    {
+                        Ok(DefinedType::String)
+                    }
                     ast::Operator::Plus
                     | ast::Operator::Minus
                     | ast::Operator::Multiply
-                    | ast::Operator::Divide => {
+                    | ast::Operator::Divide
+                    | ast::Operator::Modulo => {
                         if is_numeric(&lhs) && is_numeric(&rhs) {
                             Ok(match (lhs, rhs) {
                                 (DefinedType::I64, DefinedType::I64) => DefinedType::I64,
@@ -418,8 +1348,37 @@ impl Typechecker {
                             })
                         }
                     }
+                    ast::Operator::And | ast::Operator::Or => {
+                        if lhs == DefinedType::Bool && rhs == DefinedType::Bool {
+                            Ok(DefinedType::Bool)
+                        } else {
+                            Err(TypecheckerError::TypeMismatchMulti {
+                                expected: "bool".to_owned(),
+                                got: vec![lhs.to_string(), rhs.to_string()],
+                            })
+                        }
+                    }
+                    ast::Operator::BitAnd
+                    | ast::Operator::BitOr
+                    | ast::Operator::BitXor
+                    | ast::Operator::ShiftLeft
+                    | ast::Operator::ShiftRight => {
+                        if lhs == DefinedType::I64 && rhs == DefinedType::I64 {
+                            Ok(DefinedType::I64)
+                        } else {
+                            Err(TypecheckerError::TypeMismatchMulti {
+                                expected: "int".to_owned(),
+                                got: vec![lhs.to_string(), rhs.to_string()],
+                            })
+                        }
+                    }
                     ast::Operator::Equal | ast::Operator::NotEqual => {
-                        if lhs == rhs {
+                        // anything can be compared against nil
+                        if lhs.accepts(&rhs)
+                            || rhs.accepts(&lhs)
+                            || lhs == DefinedType::Nil
+                            || rhs == DefinedType::Nil
+                        {
                             Ok(DefinedType::Bool)
                         } else {
                             Err(TypecheckerError::TypeMismatch {
@@ -429,90 +1388,241 @@ impl Typechecker {
                         }
                     }
                     _ => unreachable!(),
-                }
+                }?;
+
+                Ok(TypedExpression::Infix {
+                    op,
+                    lhs: Box::new(typed_lhs),
+                    rhs: Box::new(typed_rhs),
+                    defined_type,
+                })
             }
             Expression::Literal(literal) => {
-                let defined_type = match literal {
-                    crate::types::Literal::String(_) => DefinedType::String,
-                    crate::types::Literal::Float(_) => DefinedType::F64,
-                    crate::types::Literal::Integer(_) => DefinedType::I64,
-                    crate::types::Literal::Boolean(_) => DefinedType::Bool,
-                };
+                let defined_type = DefinedType::from(literal);
 
                 self.add_validated_types_for_debug(format!(
                     "{:8} -> defined: {literal:?}, expression: {defined_type}",
                     "literal"
                 ));
 
-                Ok(defined_type)
+                Ok(TypedExpression::Literal {
+                    value: literal,
+                    defined_type,
+                })
             }
             Expression::Variable(name) => {
-                self.resolve_type(name)
-                    .cloned()
-                    .ok_or_else(|| TypecheckerError::NotFound {
-                        val: name.to_owned(),
-                        what: "variable",
-                    })
+                self.mark_read(name);
+                let defined_type =
+                    self.resolve_type(name)
+                        .cloned()
+                        .ok_or_else(|| TypecheckerError::NotFound {
+                            val: name.to_owned(),
+                            what: "variable",
+                        })?;
+
+                Ok(TypedExpression::Variable { name, defined_type })
             }
-            Expression::FunctionCall { name, args } => {
+            Expression::FunctionCall { name, args, spans } => {
+                let mut typed_args = Vec::with_capacity(args.len());
                 for arg in args {
-                    self.typecheck_expression(arg)?;
+                    typed_args.push(self.typecheck_expression(arg)?);
+                }
+
+                // the error is reported once, it stops the check either way
+                if let Some((error, span)) = self.failed_functions.remove(name) {
+                    self.error_span = span;
+                    return Err(error);
                 }
 
-                self.resolve_function_return_type(name)
+                let mut defined_type = self
+                    .resolve_function_return_type(name)
                     .cloned()
-                    .ok_or_else(|| TypecheckerError::NotFound {
-                        val: name.to_owned(),
-                        what: "function",
-                    })
+                    .ok_or_else(|| {
+                        if self.undeclared_functions.contains(name) {
+                            TypecheckerError::UnknownReturnType {
+                                name: name.to_owned(),
+                            }
+                        } else {
+                            TypecheckerError::NotFound {
+                                val: name.to_owned(),
+                                what: "function",
+                            }
+                        }
+                    })?;
+
+                if let Some(&(required, total)) = self.resolve_arity(name) {
+                    if typed_args.len() < required {
+                        return Err(TypecheckerError::TooFewArguments {
+                            name: name.to_owned(),
+                            expected: required,
+                            got: typed_args.len(),
+                        });
+                    }
+
+                    if typed_args.len() > total {
+                        return Err(TypecheckerError::TooManyArguments {
+                            name: name.to_owned(),
+                            expected: total,
+                            got: typed_args.len(),
+                        });
+                    }
+                }
+
+                if let Some((fixed, element_type)) = self.resolve_variadic(name) {
+                    // parameters with defaults before the variadic one can be left out
+                    let required = self
+                        .resolve_arity(name)
+                        .map_or(*fixed, |&(required, _)| required);
+                    if typed_args.len() < required {
+                        return Err(TypecheckerError::TooFewArguments {
+                            name: name.to_owned(),
+                            expected: required,
+                            got: typed_args.len(),
+                        });
+                    }
+
+                    for arg in typed_args.iter().skip(*fixed) {
+                        if arg.defined_type() != element_type {
+                            return Err(TypecheckerError::TypeMismatch {
+                                expected: element_type.to_string(),
+                                got: arg.defined_type().to_string(),
+                            });
+                        }
+                    }
+                }
+
+                if let Some((parameters, repeats)) = self.resolve_parameters(name) {
+                    let repeated = parameters.last().filter(|_| *repeats);
+                    for (i, arg) in typed_args.iter().enumerate() {
+                        let Some(parameter) = parameters.get(i).or(repeated) else {
+                            break;
+                        };
+
+                        if !parameter.accepts(arg.defined_type()) {
+                            if let ("int" | "float", Some(&span)) = (name.as_str(), spans.get(i)) {
+                                return Err(Self::cast_argument(name, arg.defined_type(), span));
+                            }
+
+                            return Err(TypecheckerError::TypeMismatch {
+                                expected: parameter.to_string(),
+                                got: arg.defined_type().to_string(),
+                            });
+                        }
+                    }
+                }
+
+                // the compiler widens ints passed for floats, `[]` takes the parameter's type
+                if let Some(parameter_types) = self.resolve_parameter_types(name) {
+                    for (parameter_type, arg) in parameter_types.iter().zip(&typed_args) {
+                        let empty_array_fits =
+                            is_empty_array(arg) && matches!(parameter_type, DefinedType::Array(_));
+                        if !empty_array_fits && !parameter_type.accepts_widening(arg.defined_type())
+                        {
+                            return Err(TypecheckerError::TypeMismatch {
+                                expected: parameter_type.to_string(),
+                                got: arg.defined_type().to_string(),
+                            });
+                        }
+                    }
+                }
+
+                // abs keeps ints as ints
+                if name == "abs" {
+                    if let Some(DefinedType::I64) =
+                        typed_args.first().map(TypedExpression::defined_type)
+                    {
+                        defined_type = DefinedType::I64;
+                    }
+                }
+
+                // pop gives back whatever the array holds
+                if name == "pop" {
+                    if let Some(DefinedType::Array(inner)) =
+                        typed_args.first().map(TypedExpression::defined_type)
+                    {
+                        defined_type = *inner.clone();
+                    }
+                }
+
+                Ok(TypedExpression::FunctionCall {
+                    name,
+                    args: typed_args,
+                    defined_type,
+                })
             }
             Expression::Object { fields } => {
-                let mut typed_fields = OrderMap::<String, DefinedType>::default();
+                let mut field_types = OrderMap::<String, DefinedType>::default();
+                let mut typed_fields = OrderMap::default();
+                let mut spreads = Vec::new();
 
-                for (name, expr) in fields {
-                    typed_fields.insert(name.to_owned(), self.typecheck_expression(expr)?);
+                for field in fields {
+                    match field {
+                        ObjectField::Named(name, expr) => {
+                            let typed_expr = self.typecheck_expression(expr)?;
+                            field_types.insert(name.to_owned(), typed_expr.defined_type().clone());
+                            typed_fields.insert(name.as_str(), typed_expr);
+                        }
+                        // the spread object's fields are merged in, anything after overrides them
+                        ObjectField::Spread(expr) => {
+                            let typed_expr = self.typecheck_expression(expr)?;
+                            let DefinedType::Object { fields } = typed_expr.defined_type() else {
+                                return Err(TypecheckerError::UnexpectedType {
+                                    got: typed_expr.defined_type().to_string(),
+                                });
+                            };
+
+                            for (name, defined_type) in fields {
+                                field_types.insert(name.to_owned(), defined_type.clone());
+                            }
+                            spreads.push(typed_expr);
+                        }
+                    }
                 }
 
-                Ok(DefinedType::Object {
+                Ok(TypedExpression::Object {
                     fields: typed_fields,
+                    spreads,
+                    defined_type: DefinedType::Object {
+                        fields: field_types,
+                    },
                 })
             }
             Expression::ObjectAccess { path } => {
                 let object_name = path.first().unwrap();
-                let obj_type = self.resolve_type(object_name);
-                if let Some(obj_type) = obj_type {
-                    match obj_type {
-                        DefinedType::Object { fields } => {
-                            let path_to_take = path.iter().skip(1);
-                            let mut last_item = None;
-                            for item in path_to_take {
-                                let new_item = fields.get(item);
-                                // FIXME: i think this is weird
-                                if new_item.is_some() {
-                                    last_item = new_item;
-                                }
-                            }
+                self.mark_read(object_name);
+                let mut defined_type =
+                    self.resolve_type(object_name).cloned().ok_or_else(|| {
+                        TypecheckerError::NotFound {
+                            val: object_name.to_owned(),
+                            what: "variable",
+                        }
+                    })?;
 
-                            if let Some(last_item) = last_item {
-                                Ok(last_item.clone())
-                            } else {
-                                unreachable!()
-                            }
+                // each step goes into the object the previous one gave back,
+                // a field the object doesn't have reads as nil like it does in the vm
+                for field in path.iter().skip(1) {
+                    defined_type = match defined_type {
+                        DefinedType::Object { fields } => {
+                            fields.get(field).cloned().unwrap_or(DefinedType::Nil)
                         }
-                        t => Err(TypecheckerError::UnexpectedType { got: t.to_string() }),
-                    }
-                } else {
-                    unreachable!();
+                        t => return Err(TypecheckerError::UnexpectedType { got: t.to_string() }),
+                    };
                 }
+
+                Ok(TypedExpression::ObjectAccess { path, defined_type })
             }
             Expression::Array { this } => {
-                let mut defined_types = Vec::new();
+                let mut typed_values = Vec::with_capacity(this.len());
                 for value in this {
-                    defined_types.push(self.typecheck_expression(value)?);
+                    typed_values.push(self.typecheck_expression(value)?);
                 }
 
-                let all_equal = defined_types.into_iter().all_equal_value();
-                match all_equal {
+                let all_equal = typed_values
+                    .iter()
+                    .map(|v| v.defined_type().clone())
+                    .all_equal_value();
+
+                let defined_type = match all_equal {
                     Ok(inferred_type) => {
                         self.add_validated_types_for_debug(format!(
                             "{:8} -> inferred: {inferred_type}",
@@ -532,61 +1642,219 @@ impl Typechecker {
                             Ok(DefinedType::Array(Box::new(DefinedType::Nil)))
                         }
                     }
+                }?;
+
+                Ok(TypedExpression::Array {
+                    this: typed_values,
+                    defined_type,
+                })
+            }
+            Expression::ArrayAccess { name, index } => {
+                self.mark_read(name);
+                let defined_type = self
+                    .resolve_type(name)
+                    .ok_or_else(|| TypecheckerError::NotFound {
+                        val: name.to_owned(),
+                        what: "variable",
+                    })
+                    .and_then(|t| match t {
+                        DefinedType::Array(defined_type) => Ok(*defined_type.clone()),
+                        t => Err(TypecheckerError::UnexpectedType { got: t.to_string() }),
+                    })?;
+
+                Ok(TypedExpression::ArrayAccess {
+                    name,
+                    index: Box::new(self.typecheck_expression(index)?),
+                    defined_type,
+                })
+            }
+            Expression::ArraySlice { name, start, end } => {
+                self.mark_read(name);
+                let defined_type = self
+                    .resolve_type(name)
+                    .ok_or_else(|| TypecheckerError::NotFound {
+                        val: name.to_owned(),
+                        what: "variable",
+                    })
+                    .and_then(|t| match t {
+                        DefinedType::Array(_) => Ok(t.clone()),
+                        t => Err(TypecheckerError::UnexpectedType { got: t.to_string() }),
+                    })?;
+
+                let start = self.typecheck_expression(start)?;
+                let end = self.typecheck_expression(end)?;
+                for bound in [&start, &end] {
+                    if *bound.defined_type() != DefinedType::I64 {
+                        return Err(TypecheckerError::TypeMismatch {
+                            expected: DefinedType::I64.to_string(),
+                            got: bound.defined_type().to_string(),
+                        });
+                    }
                 }
+
+                Ok(TypedExpression::ArraySlice {
+                    name,
+                    start: Box::new(start),
+                    end: Box::new(end),
+                    defined_type,
+                })
             }
-            Expression::ArrayAccess { name, index: _ } => self
-                .resolve_type(name)
-                .ok_or_else(|| TypecheckerError::NotFound {
-                    val: name.to_owned(),
-                    what: "variable",
+            Expression::Cast { expr, type_name } => {
+                let typed_expr = self.typecheck_expression(expr)?;
+                // only primitives convert, objects and arrays have no sensible mapping
+                if matches!(
+                    typed_expr.defined_type(),
+                    DefinedType::Object { .. } | DefinedType::Array(_)
+                ) {
+                    return Err(TypecheckerError::UnexpectedType {
+                        got: typed_expr.defined_type().to_string(),
+                    });
+                }
+
+                Ok(TypedExpression::Cast {
+                    expr: Box::new(typed_expr),
+                    defined_type: DefinedType::try_from(type_name)?,
                 })
-                .and_then(|t| match t {
-                    DefinedType::Array(defined_type) => Ok(*defined_type.clone()),
-                    t => Err(TypecheckerError::UnexpectedType { got: t.to_string() }),
-                }),
+            }
+            Expression::If {
+                condition,
+                body,
+                else_body,
+            } => {
+                let condition = self.typecheck_expression(condition)?;
+                let t = condition.defined_type();
+                if *t != DefinedType::Bool {
+                    return Err(TypecheckerError::TypeMismatch {
+                        expected: "bool".to_string(),
+                        got: t.to_string(),
+                    });
+                }
+
+                // without an else there'd be nothing to produce when the condition is false
+                let else_body = else_body.as_ref().ok_or(TypecheckerError::MissingElse)?;
+
+                let body = self.typecheck_expression(body)?;
+                let else_body = self.typecheck_expression(else_body)?;
+                if body.defined_type() != else_body.defined_type() {
+                    return Err(TypecheckerError::TypeMismatch {
+                        expected: body.defined_type().to_string(),
+                        got: else_body.defined_type().to_string(),
+                    });
+                }
+
+                Ok(TypedExpression::If {
+                    defined_type: body.defined_type().clone(),
+                    condition: Box::new(condition),
+                    body: Box::new(body),
+                    else_body: Box::new(else_body),
+                })
+            }
         }
     }
 
-    fn typecheck_let(
+    fn typecheck_let<'a>(
         &mut self,
         name: &String,
-        value: &Expression,
-        type_name: &Option<String>,
+        value: &'a Expression,
+        type_name: &Option<ast::TypeName>,
+        is_mutable: bool,
+        span: Span,
         #[cfg(debug_assertions)] in_statement: &'static str,
-    ) -> Result<(), TypecheckerError> {
-        let expression_type_name = self.typecheck_expression(value)?;
+    ) -> Result<TypedExpression<'a>, TypecheckerError> {
+        let mut value = self.typecheck_expression(value)?;
+        let expression_type_name = value.defined_type().clone();
         match type_name {
-            None => self.define_type_current_scope(name.to_owned(), expression_type_name),
+            None if is_empty_array(&value) => {
+                return Err(TypecheckerError::EmptyArray {
+                    name: name.to_owned(),
+                });
+            }
+            None => self.define_type_current_scope(
+                name.to_owned(),
+                expression_type_name,
+                is_mutable,
+                span,
+            ),
             Some(s) => {
                 let defined_type = DefinedType::try_from(s)?;
-                if defined_type == expression_type_name {
+                let empty_array_fits =
+                    is_empty_array(&value) && matches!(defined_type, DefinedType::Array(_));
+                if let (
+                    true,
+                    TypedExpression::Array {
+                        defined_type: t, ..
+                    },
+                ) = (empty_array_fits, &mut value)
+                {
+                    *t = defined_type.clone();
+                }
+
+                if empty_array_fits || defined_type.accepts_widening(&expression_type_name) {
                     #[cfg(debug_assertions)]
                     self.add_validated_types_for_debug(format!(
                         "{:8} -> defined: {defined_type}, expression: {expression_type_name}",
                         in_statement
                     ));
 
-                    self.define_type_current_scope(name.to_owned(), defined_type)
+                    self.define_type_current_scope(name.to_owned(), defined_type, is_mutable, span)
                 } else {
                     return Err(TypecheckerError::TypeMismatch {
-                        expected: type_name.as_ref().unwrap().to_owned(),
+                        expected: s.to_string(),
                         got: expression_type_name.to_string(),
                     });
                 }
             }
         };
 
-        Ok(())
+        Ok(value)
     }
 
-    // Once day we get "typechecked" AST, not yet...
-    pub fn check(mut self, statements: &[Statement]) -> Result<(), TypecheckerError> {
-        for statement in statements {
-            self.typecheck_statement(statement)?;
+    // the typed ast borrows from the input, callers that only want validation can drop it
+    pub fn check(
+        self,
+        statements: &[Statement],
+    ) -> Result<Vec<TypedStatement<'_>>, TypecheckerError> {
+        self.analyze(statements).typed_statements
+    }
+
+    // symbols defined before an error are still returned, warnings are only complete on success
+    pub fn analyze(mut self, statements: &[Statement]) -> Analysis<'_> {
+        self.declare_functions(statements);
+
+        let typed_statements = statements
+            .iter()
+            .enumerate()
+            .map(|(i, statement)| {
+                let span = self.statement_spans.get(i).copied();
+                let typed_statement = self
+                    .typecheck_statement(statement)
+                    .inspect_err(|_| self.failed_at(span))?;
+                self.warn_unused_result(&typed_statement, span);
+                Ok(typed_statement)
+            })
+            .collect::<Result<Vec<_>, _>>();
+
+        if typed_statements.is_ok() {
+            self.print_validation_if_debug();
+
+            // whatever is left in the outer scopes is never going to be read now
+            while let Some(mut scope) = self.scope_stack.pop() {
+                self.warn_unread(&mut scope);
+            }
         }
 
-        self.print_validation_if_debug();
-        Ok(())
+        self.warnings.sort_by_key(|w| {
+            w.labels
+                .first()
+                .map(|label| (label.file_id, label.range.start))
+        });
+
+        Analysis {
+            typed_statements,
+            symbols: self.symbols,
+            warnings: self.warnings,
+            error_span: self.error_span,
+        }
     }
 }
 
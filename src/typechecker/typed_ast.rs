@@ -0,0 +1,152 @@
+use super::DefinedType;
+use crate::{
+    ast::{Operator, Pattern},
+    types::Literal,
+};
+use ordermap::OrderMap;
+
+// mirrors ast::Statement, but every expression carries the type it resolved to
+// only read through Debug by the dump target for now
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum TypedStatement<'a> {
+    Const {
+        name: &'a str,
+        value: TypedExpression<'a>,
+    },
+    Let {
+        name: &'a str,
+        value: TypedExpression<'a>,
+        is_mutable: bool,
+    },
+    Reassignment {
+        name: &'a str,
+        value: TypedExpression<'a>,
+    },
+    ObjectMutation {
+        path: TypedExpression<'a>,
+        value: TypedExpression<'a>,
+    },
+    If {
+        condition: TypedExpression<'a>,
+        body: Box<TypedStatement<'a>>,
+        else_statement: Option<Box<TypedStatement<'a>>>,
+    },
+    Block {
+        body: Vec<TypedStatement<'a>>,
+    },
+    Loop {
+        body: Box<TypedStatement<'a>>,
+    },
+    For {
+        binding: &'a str,
+        iterable: TypedIterable<'a>,
+        body: Box<TypedStatement<'a>>,
+    },
+    Match {
+        value: TypedExpression<'a>,
+        arms: Vec<(&'a Pattern, TypedStatement<'a>)>,
+    },
+    Return(TypedExpression<'a>),
+    Function {
+        name: &'a str,
+        parameters: Vec<(&'a str, DefinedType)>,
+        return_type: DefinedType,
+        body: Vec<TypedStatement<'a>>,
+    },
+    Expression(TypedExpression<'a>),
+    Break(Option<TypedExpression<'a>>),
+    Continue,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum TypedIterable<'a> {
+    Array(TypedExpression<'a>),
+    Range {
+        start: TypedExpression<'a>,
+        end: TypedExpression<'a>,
+    },
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum TypedExpression<'a> {
+    Prefix {
+        op: &'a Operator,
+        expr: Box<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+    Infix {
+        op: &'a Operator,
+        lhs: Box<TypedExpression<'a>>,
+        rhs: Box<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+    Literal {
+        value: &'a Literal,
+        defined_type: DefinedType,
+    },
+    Variable {
+        name: &'a str,
+        defined_type: DefinedType,
+    },
+    FunctionCall {
+        name: &'a str,
+        args: Vec<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+    Object {
+        fields: OrderMap<&'a str, TypedExpression<'a>>,
+        spreads: Vec<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+    Array {
+        this: Vec<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+    ObjectAccess {
+        path: &'a [String],
+        defined_type: DefinedType,
+    },
+    ArrayAccess {
+        name: &'a str,
+        index: Box<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+    ArraySlice {
+        name: &'a str,
+        start: Box<TypedExpression<'a>>,
+        end: Box<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+    Cast {
+        expr: Box<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+    If {
+        condition: Box<TypedExpression<'a>>,
+        body: Box<TypedExpression<'a>>,
+        else_body: Box<TypedExpression<'a>>,
+        defined_type: DefinedType,
+    },
+}
+
+impl TypedExpression<'_> {
+    pub fn defined_type(&self) -> &DefinedType {
+        match self {
+            Self::Prefix { defined_type, .. }
+            | Self::Infix { defined_type, .. }
+            | Self::Literal { defined_type, .. }
+            | Self::Variable { defined_type, .. }
+            | Self::FunctionCall { defined_type, .. }
+            | Self::Object { defined_type, .. }
+            | Self::Array { defined_type, .. }
+            | Self::ObjectAccess { defined_type, .. }
+            | Self::ArrayAccess { defined_type, .. }
+            | Self::ArraySlice { defined_type, .. }
+            | Self::Cast { defined_type, .. }
+            | Self::If { defined_type, .. } => defined_type,
+        }
+    }
+}
@@ -1,13 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use itertools::Itertools;
 use ordermap::OrderMap;
 
-use super::TypecheckerError;
+use super::{warnings::Unread, TypecheckerError};
+use crate::{ast, lexer::Span, types::Literal};
+use codespan_reporting::diagnostic::{Diagnostic, Label};
 
 #[derive(Default)]
 pub struct TypecheckerScope {
     type_map: HashMap<String, DefinedType>,
     function_map: HashMap<String, DefinedType>,
+    // fixed parameter count and element type of each variadic function
+    variadic: HashMap<String, (usize, DefinedType)>,
+    // required and total parameter count of each function with default values
+    arity: HashMap<String, (usize, usize)>,
+    // what each argument to a native has to be, and whether the last one repeats
+    parameters: HashMap<String, (Vec<ParameterType>, bool)>,
+    // the same for functions written in sol, up to any variadic one
+    parameter_types: HashMap<String, Vec<DefinedType>>,
+    mutable: HashSet<String>,
+    // names declared with `const`, a later `let` of the same name takes it out again
+    constants: HashSet<String>,
+    // where each `let` in this scope was declared, for duplicate warnings
+    declared_at: HashMap<String, Span>,
+    // in definition order so warnings come out in a stable order
+    unread: Vec<Unread>,
 }
 
 impl TypecheckerScope {
@@ -17,14 +35,87 @@ impl TypecheckerScope {
         }
     }
 
-    pub fn define(&mut self, name: String, type_name: DefinedType) {
+    pub fn define(&mut self, name: String, type_name: DefinedType, is_mutable: bool) {
+        if is_mutable {
+            self.mutable.insert(name.clone());
+        } else {
+            self.mutable.remove(&name);
+        }
+
+        self.constants.remove(&name);
         self.type_map.insert(name, type_name);
     }
 
+    pub fn define_constant(&mut self, name: String) {
+        self.constants.insert(name);
+    }
+
+    pub fn is_constant(&self, name: &str) -> bool {
+        self.constants.contains(name)
+    }
+
+    pub fn is_mutable(&self, name: &str) -> bool {
+        self.mutable.contains(name)
+    }
+
+    // returns where the name was previously declared in this scope, if it was
+    pub fn declare(&mut self, name: &str, span: Span) -> Option<Span> {
+        self.declared_at.insert(name.to_owned(), span)
+    }
+
+    pub fn declared_at(&self, name: &str) -> Option<Span> {
+        self.declared_at.get(name).copied()
+    }
+
     pub fn define_function_return(&mut self, name: String, type_name: DefinedType) {
         self.function_map.insert(name, type_name);
     }
 
+    pub fn define_variadic(&mut self, name: String, variadic: Option<(usize, DefinedType)>) {
+        match variadic {
+            Some(variadic) => self.variadic.insert(name, variadic),
+            None => self.variadic.remove(&name),
+        };
+    }
+
+    pub fn get_variadic_for(&self, name: &str) -> Option<&(usize, DefinedType)> {
+        self.variadic.get(name)
+    }
+
+    pub fn define_arity(&mut self, name: String, arity: Option<(usize, usize)>) {
+        match arity {
+            Some(arity) => self.arity.insert(name, arity),
+            None => self.arity.remove(&name),
+        };
+    }
+
+    pub fn get_arity_for(&self, name: &str) -> Option<&(usize, usize)> {
+        self.arity.get(name)
+    }
+
+    pub fn define_parameters(
+        &mut self,
+        name: String,
+        parameters: Option<(Vec<ParameterType>, bool)>,
+    ) {
+        match parameters {
+            Some(parameters) => self.parameters.insert(name, parameters),
+            None => self.parameters.remove(&name),
+        };
+    }
+
+    pub fn get_parameters_for(&self, name: &str) -> Option<&(Vec<ParameterType>, bool)> {
+        self.parameters.get(name)
+    }
+
+    pub fn define_parameter_types(&mut self, name: String, parameter_types: Vec<DefinedType>) {
+        self.parameter_types.insert(name, parameter_types);
+    }
+
+    pub fn get_parameter_types_for(&self, name: &str) -> Option<&Vec<DefinedType>> {
+        self.parameter_types.get(name)
+    }
+
     pub fn get_type_for(&self, name: &str) -> Option<&DefinedType> {
         self.type_map.get(name)
     }
@@ -32,6 +123,43 @@ impl TypecheckerScope {
     pub fn get_function_return_for(&self, name: &str) -> Option<&DefinedType> {
         self.function_map.get(name)
     }
+
+    // returns the definition this one shadows if that was never read
+    pub fn track_unread(&mut self, unread: Unread) -> Option<Unread> {
+        let shadowed = self.take_unread_named(&unread.name);
+        self.unread.push(unread);
+
+        shadowed
+    }
+
+    pub fn mark_read(&mut self, name: &str) {
+        self.take_unread_named(name);
+    }
+
+    fn take_unread_named(&mut self, name: &str) -> Option<Unread> {
+        let position = self.unread.iter().position(|u| u.name == name)?;
+        Some(self.unread.remove(position))
+    }
+
+    pub fn take_unread(&mut self) -> Vec<Unread> {
+        std::mem::take(&mut self.unread)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Function,
+}
+
+// every name the typechecker defined, in the order it defined them
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub defined_type: DefinedType,
+    // where the name is written in its definition
+    pub span: Span,
 }
 
 #[derive(Clone, Debug)]
@@ -46,6 +174,8 @@ pub enum DefinedType {
         fields: OrderMap<String, DefinedType>,
     },
     Array(Box<DefinedType>),
+    // `int?`, either the inner type or nil
+    Optional(Box<DefinedType>),
 }
 
 impl PartialEq for DefinedType {
@@ -67,6 +197,44 @@ impl PartialEq for DefinedType {
                 DefinedType::Array(other_defined_type) => defined_type.eq(other_defined_type),
                 _ => false,
             },
+            DefinedType::Optional(defined_type) => match other {
+                DefinedType::Optional(other_defined_type) => defined_type.eq(other_defined_type),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl DefinedType {
+    // whether a value of type other can be stored where this type is expected
+    pub fn accepts(&self, other: &DefinedType) -> bool {
+        match self {
+            DefinedType::Optional(inner) => {
+                self == other || *other == DefinedType::Nil || **inner == *other
+            }
+            _ => self == other,
+        }
+    }
+
+    // where a type is written down an int can go in place of a float, the compiler converts it
+    pub fn accepts_widening(&self, other: &DefinedType) -> bool {
+        let widens = |t: &DefinedType| *t == DefinedType::F64 && *other == DefinedType::I64;
+
+        match self {
+            DefinedType::Optional(inner) => self.accepts(other) || widens(inner),
+            _ => self.accepts(other) || widens(self),
+        }
+    }
+}
+
+impl From<&Literal> for DefinedType {
+    fn from(value: &Literal) -> Self {
+        match value {
+            Literal::String(_) => Self::String,
+            Literal::Float(_) => Self::F64,
+            Literal::Integer(_) => Self::I64,
+            Literal::Boolean(_) => Self::Bool,
+            Literal::Nil => Self::Nil,
         }
     }
 }
@@ -75,6 +243,19 @@ impl TryFrom<&String> for DefinedType {
     type Error = TypecheckerError;
 
     fn try_from(value: &String) -> Result<Self, Self::Error> {
+        if let Some(inner) = value.strip_suffix('?') {
+            let inner = Self::try_from(&inner.to_owned())?;
+            return Ok(Self::Optional(Box::new(inner)));
+        }
+
+        if let Some(inner) = value
+            .strip_prefix('[')
+            .and_then(|inner| inner.strip_suffix(']'))
+        {
+            let inner = Self::try_from(&inner.to_owned())?;
+            return Ok(Self::Array(Box::new(inner)));
+        }
+
         match value.as_str() {
             "int" => Ok(Self::I64),
             "float" => Ok(Self::F64),
@@ -88,8 +269,141 @@ impl TryFrom<&String> for DefinedType {
     }
 }
 
+// the names a type annotation can use
+const BUILTIN_TYPE_NAMES: [&str; 4] = ["int", "float", "bool", "string"];
+
+impl TryFrom<&ast::TypeName> for DefinedType {
+    type Error = TypecheckerError;
+
+    fn try_from(type_name: &ast::TypeName) -> Result<Self, Self::Error> {
+        Self::try_from(&type_name.name).map_err(|_| unknown_type(type_name))
+    }
+}
+
+fn unknown_type(type_name: &ast::TypeName) -> TypecheckerError {
+    let (base, optional) = match type_name.name.strip_suffix('?') {
+        Some(base) => (base, "?"),
+        None => (type_name.name.as_str(), ""),
+    };
+
+    let mut diagnostic = Diagnostic::error()
+        .with_message(format!("unknown type `{}`", type_name.name))
+        .with_labels(vec![
+            Label::primary(type_name.span.file_id, type_name.span).with_message("not a known type")
+        ]);
+
+    if let Some(closest) = closest_type_name(base) {
+        diagnostic = diagnostic.with_notes(vec![format!("did you mean `{closest}{optional}`?")]);
+    }
+
+    TypecheckerError::Diagnostic(diagnostic)
+}
+
+// close enough to be a typo, anything further off is probably not meant to be a builtin
+fn closest_type_name(name: &str) -> Option<&'static str> {
+    BUILTIN_TYPE_NAMES
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2 && *distance < candidate.len())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+// levenshtein, one row at a time
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
 impl std::fmt::Display for DefinedType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+// natives can take more than any one type that can be written in sol
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterType {
+    Any,
+    Type(DefinedType),
+    // an array of anything
+    Array,
+    OneOf(Vec<ParameterType>),
+}
+
+impl ParameterType {
+    #[allow(unused)]
+    pub fn number() -> Self {
+        Self::OneOf(vec![
+            Self::Type(DefinedType::I64),
+            Self::Type(DefinedType::F64),
+        ])
+    }
+
+    pub fn accepts(&self, other: &DefinedType) -> bool {
+        match self {
+            ParameterType::Any => true,
+            ParameterType::Type(defined_type) => defined_type.accepts(other),
+            ParameterType::Array => matches!(other, DefinedType::Array(_)),
+            ParameterType::OneOf(types) => types.iter().any(|t| t.accepts(other)),
+        }
+    }
+}
+
+impl std::fmt::Display for ParameterType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterType::Any => write!(f, "Any"),
+            ParameterType::Type(defined_type) => write!(f, "{defined_type}"),
+            ParameterType::Array => write!(f, "Array"),
+            ParameterType::OneOf(types) => write!(f, "{}", types.iter().join(" or ")),
+        }
+    }
+}
+
+// what the typechecker needs to know about a function the vm provides
+#[derive(Clone, Debug)]
+pub struct NativeSignature {
+    pub parameters: Vec<ParameterType>,
+    // the rest can be left out
+    pub required: usize,
+    // the last parameter takes any number of arguments
+    pub variadic: bool,
+    pub return_type: DefinedType,
+}
+
+impl NativeSignature {
+    #[allow(unused)]
+    pub fn new(parameters: Vec<ParameterType>, return_type: DefinedType) -> Self {
+        Self {
+            required: parameters.len(),
+            parameters,
+            variadic: false,
+            return_type,
+        }
+    }
+
+    #[allow(unused)]
+    pub fn with_required(mut self, required: usize) -> Self {
+        self.required = required;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn with_variadic(mut self) -> Self {
+        self.variadic = true;
+        self
+    }
+}
@@ -0,0 +1,58 @@
+use crate::lexer::Span;
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+// a variable or parameter nothing has read yet
+#[derive(Debug)]
+pub struct Unread {
+    pub name: String,
+    pub span: Span,
+    pub what: &'static str,
+}
+
+pub fn unused(unread: Unread) -> Diagnostic<usize> {
+    Diagnostic::warning()
+        .with_message(format!("unused {} `{}`", unread.what, unread.name))
+        .with_labels(vec![
+            Label::primary(unread.span.file_id, unread.span).with_message("never read")
+        ])
+        .with_notes(vec![format!(
+            "prefix it with an underscore to silence this: `_{}`",
+            unread.name
+        )])
+}
+
+pub fn unreachable(exit: Span, statement: Span) -> Diagnostic<usize> {
+    Diagnostic::warning()
+        .with_message("unreachable statement")
+        .with_labels(vec![
+            Label::primary(statement.file_id, statement).with_message("this will never run"),
+            Label::secondary(exit.file_id, exit)
+                .with_message("any code following this is unreachable"),
+        ])
+}
+
+pub fn duplicate(name: &str, span: Span, previous: Span) -> Diagnostic<usize> {
+    Diagnostic::warning()
+        .with_message(format!("`{name}` is already declared in this scope"))
+        .with_labels(vec![
+            Label::primary(span.file_id, span).with_message("redeclared here"),
+            Label::secondary(previous.file_id, previous).with_message("first declared here"),
+        ])
+        .with_notes(vec![format!(
+            "use `let mut {name}` and reassign it if the old value isn't needed"
+        )])
+}
+
+// only reported when asked for, plenty of calls are made for what they do rather than what they give back
+pub fn unused_result(name: &str, span: Option<Span>) -> Diagnostic<usize> {
+    Diagnostic::warning()
+        .with_message(format!("return value of `{name}` is unused"))
+        .with_labels(
+            span.map(|span| Label::primary(span.file_id, span).with_message("result is dropped"))
+                .into_iter()
+                .collect(),
+        )
+        .with_notes(vec![format!(
+            "assign it to `_` to discard it on purpose: `let _ = {name}(...);`"
+        )])
+}
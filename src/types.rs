@@ -1,6 +1,10 @@
 use crate::vm::{VMArray, VMFunction, VMObject, VMObjectValue};
 use ordermap::OrderMap;
-use std::{fmt::Display, rc::Rc};
+use std::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
@@ -8,6 +12,22 @@ pub enum Literal {
     Float(f64),
     Integer(i64),
     Boolean(bool),
+    Nil,
+}
+
+// f64 has no Hash, go by the bits instead. 0.0 == -0.0 so they have to hash the same
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Literal::String(s) => s.hash(state),
+            Literal::Float(n) if *n == 0.0 => 0.0f64.to_bits().hash(state),
+            Literal::Float(n) => n.to_bits().hash(state),
+            Literal::Integer(n) => n.hash(state),
+            Literal::Boolean(b) => b.hash(state),
+            Literal::Nil => {}
+        }
+    }
 }
 
 impl Display for Literal {
@@ -17,10 +37,19 @@ impl Display for Literal {
             Literal::Float(n) => write!(f, "{}", n),
             Literal::Integer(n) => write!(f, "{}", n),
             Literal::Boolean(b) => write!(f, "{}", b),
+            Literal::Nil => write!(f, "nil"),
         }
     }
 }
 
+// strings inside an array or object keep their quotes, otherwise "1" and 1 look the same
+fn write_nested(f: &mut std::fmt::Formatter<'_>, value: &ObjectValue) -> std::fmt::Result {
+    match value {
+        ObjectValue::Literal(Literal::String(s)) => write!(f, "{s:?}"),
+        value => write!(f, "{value}"),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Array {
     this: Vec<VMObjectValue>,
@@ -28,9 +57,14 @@ pub struct Array {
 
 impl Display for Array {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_list()
-            .entries(self.this.iter().map(|v| v.borrow().to_string()))
-            .finish()
+        write!(f, "[")?;
+        for (i, value) in self.this.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_nested(f, &value.borrow())?;
+        }
+        write!(f, "]")
     }
 }
 
@@ -44,10 +78,10 @@ impl Array {
         )
     }
 
+    // gaps are filled with nil, the vec's own growth keeps appends cheap
     pub fn set(&mut self, idx: usize, v: VMObjectValue) {
-        if idx >= self.this.len() || self.this.is_empty() {
-            self.this
-                .resize(2 * (idx + 1), Rc::new(ObjectValue::Nil.into()));
+        if idx >= self.this.len() {
+            self.this.resize(idx + 1, Rc::new(ObjectValue::Nil.into()));
         }
 
         self.this[idx] = v;
@@ -56,6 +90,23 @@ impl Array {
     pub fn index(&self, idx: usize) -> Option<VMObjectValue> {
         self.this.get(idx).cloned()
     }
+
+    pub fn push(&mut self, v: VMObjectValue) {
+        self.this.push(v);
+    }
+
+    pub fn pop(&mut self) -> Option<VMObjectValue> {
+        self.this.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.this.len()
+    }
+
+    #[allow(unused)]
+    pub fn is_empty(&self) -> bool {
+        self.this.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -63,12 +114,21 @@ pub struct Object {
     fields: OrderMap<String, VMObjectValue>,
 }
 
-// FIXME: nesting leads to extra quotes
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_map()
-            .entries(self.fields.iter().map(|(k, v)| (k, v.borrow().to_string())))
-            .finish()
+        if self.fields.is_empty() {
+            return write!(f, "{{}}");
+        }
+
+        write!(f, "{{ ")?;
+        for (i, (key, value)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{key}: ")?;
+            write_nested(f, &value.borrow())?;
+        }
+        write!(f, " }}")
     }
 }
 
@@ -86,12 +146,21 @@ impl Object {
         self.fields.insert(k, v);
     }
 
+    // fields in the order they were first set
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &VMObjectValue)> {
+        self.fields.iter()
+    }
+
     pub fn index(&self, idx: &Literal) -> Option<VMObjectValue> {
         match idx {
             Literal::String(s) => self.fields.get(s).cloned(),
             _ => unreachable!(),
         }
     }
+
+    pub fn fields(&self) -> &OrderMap<String, VMObjectValue> {
+        &self.fields
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -109,7 +178,7 @@ impl Display for ObjectValue {
         match self {
             ObjectValue::Object(rc) => write!(f, "{}", rc.borrow()),
             ObjectValue::Literal(literal) => write!(f, "{}", literal),
-            ObjectValue::Function(func) => write!(f, "{}", func),
+            ObjectValue::Function(func) => write!(f, "<function: {}>", func.name),
             ObjectValue::Array(rc) => write!(f, "{}", rc.borrow()),
             ObjectValue::Nil => write!(f, "nil"),
         }
@@ -1,15 +1,23 @@
 use crate::compiler;
-use crate::types::{Array, Literal, Object, ObjectValue};
+use crate::types::{Array, Literal, Object};
 use crate::{
     compiler::CompiledProgram,
-    impl_binary_comparator, impl_binary_op,
-    instructions::Instruction,
-    stdlib::{NativeFunctionType, STANDARD_LIBRARY},
+    impl_binary_comparator, impl_binary_op, impl_integer_op,
+    instructions::{CastTarget, Instruction, Register},
+    stdlib::{NativeContext, NativeFunctionType, STANDARD_LIBRARY},
     types,
 };
-use std::rc::Rc;
+use std::{
+    any::Any,
+    cell::{Ref, RefCell},
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    rc::Rc,
+    time::{Duration, Instant},
+};
 use std::{borrow::Cow, collections::HashMap};
 use thiserror::Error;
+use tracing::Level;
 
 mod registers;
 mod value;
@@ -17,29 +25,253 @@ pub use registers::*;
 pub use value::*;
 
 struct SavedCallFrame {
-    // FIXME: we could store the register array with each callframe
-    // right now each function is allowed 256 registers by the compiler
-    // but the VM only has 256 total, a deep call stack will easily go
-    // beyond this number, we'd need more memory but could allocate
-    // the right number of registers for each function
-    // or make the window resizable... abstract the window away and resize on access
     pub ip: usize,
     pub function: VMFunction,
-    pub register_count: u8,
-    pub function_return_value: u8,
+    pub function_return_value: Register,
 }
 
 #[derive(Error, Debug)]
 pub enum ExecutionError {
     #[error("{cause}")]
     InvalidOperation { cause: String },
+    #[error("exit requested with code {code}")]
+    Exit { code: i64 },
+    #[error("instruction limit exceeded after {executed} instructions")]
+    InstructionLimitExceeded { executed: u64 },
+    #[error("execution aborted by step hook")]
+    Aborted,
+    #[error("cannot cast {from} to {to}")]
+    InvalidCast { from: &'static str, to: CastTarget },
+    #[error("index {index} is out of bounds for array of length {length}")]
+    IndexOutOfBounds { index: i64, length: usize },
+    #[error("stack overflow, call depth exceeded {depth}")]
+    StackOverflow { depth: usize },
+    #[error("ran out of fuel")]
+    FuelExhausted,
+    #[error("{message}")]
+    AssertionFailed { message: String },
+}
+
+// what the step hook sees before each instruction runs
+#[allow(dead_code)]
+pub struct StepInfo<'s, 'a> {
+    pub instruction: Instruction,
+    pub ip: usize,
+    pub function_name: &'s str,
+    // registers of the current function only
+    pub registers: &'s [VMValue<'a>],
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepControl {
+    Continue,
+    // hand control back to the host, VM::resume picks up at the same instruction
+    Pause,
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Execution {
+    Finished,
+    Paused,
+}
+
+pub type StepHook = Box<dyn FnMut(&StepInfo) -> StepControl>;
+
+// everything the run loop needs, kept outside so a paused run can be resumed
+pub struct ExecutionState<'a> {
+    ip: usize,
+    current_function: VMFunction,
+    saved_call_frames: Vec<SavedCallFrame>,
+    registers: Registers<'a>,
+    stats: VMStats,
+    // set on pause so the hook isn't asked twice about the same instruction
+    resuming: bool,
 }
 
+impl<'a> ExecutionState<'a> {
+    #[allow(unused)]
+    pub fn registers(&self) -> &Registers<'a> {
+        &self.registers
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VMStats {
+    pub instructions_executed: u64,
+    // the rest is only collected with VM::with_stats
+    pub instruction_counts: BTreeMap<&'static str, u64>,
+    pub function_calls: BTreeMap<String, u64>,
+    pub peak_call_depth: usize,
+}
+
+impl std::fmt::Display for VMStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<24}{:>12}",
+            "instructions executed", self.instructions_executed
+        )?;
+        writeln!(f, "{:<24}{:>12}", "peak call depth", self.peak_call_depth)?;
+
+        // most executed first
+        let mut instruction_counts = self.instruction_counts.iter().collect::<Vec<_>>();
+        instruction_counts.sort_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+
+        writeln!(f)?;
+        writeln!(f, "{:<24}{:>12}", "instruction", "count")?;
+        for (name, count) in instruction_counts {
+            writeln!(f, "{:<24}{:>12}", name, count)?;
+        }
+
+        let mut function_calls = self.function_calls.iter().collect::<Vec<_>>();
+        function_calls.sort_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+
+        writeln!(f)?;
+        writeln!(f, "{:<24}{:>12}", "function", "calls")?;
+        for (name, count) in function_calls {
+            writeln!(f, "{:<24}{:>12}", name, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+// what a single run took, returned with its registers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunStats {
+    pub instructions: u64,
+    pub elapsed: Duration,
+}
+
+const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
 pub struct VM {
     functions: Vec<VMFunction>,
     native_functions: HashMap<String, NativeFunctionType>,
+    // kept across calls and runs, native functions keep their state in here
+    native_context: RefCell<NativeContext>,
     global_function: VMFunction,
     literals: Vec<types::Literal>,
+    global_result: Option<Register>,
+    global_symbols: BTreeMap<String, Register>,
+    instruction_limit: Option<u64>,
+    fuel: Option<u64>,
+    max_call_depth: usize,
+    collect_stats: bool,
+    stats: RefCell<VMStats>,
+    step_hook: Option<RefCell<StepHook>>,
+}
+
+fn is_string(value: &VMValue) -> bool {
+    matches!(value, VMValue::Literal(literal) if matches!(literal.as_ref(), Literal::String(_)))
+}
+
+fn concat_strings<'a>(lhs: &VMValue, rhs: &VMValue) -> Result<VMValue<'a>, ExecutionError> {
+    match (lhs, rhs) {
+        (VMValue::Literal(lhs), VMValue::Literal(rhs)) => match (lhs.as_ref(), rhs.as_ref()) {
+            (Literal::String(lhs), Literal::String(rhs)) => Ok(VMValue::Literal(Cow::Owned(
+                Literal::String(lhs.to_owned() + rhs),
+            ))),
+            _ => Err(ExecutionError::InvalidOperation {
+                cause: "string concat requires string operands".to_owned(),
+            }),
+        },
+        _ => Err(ExecutionError::InvalidOperation {
+            cause: "string concat requires string operands".to_owned(),
+        }),
+    }
+}
+
+// negative indexes count back from the end, `xs[-1]` is the last element
+fn resolve_index(index: i64, length: usize) -> Result<usize, ExecutionError> {
+    if index >= 0 {
+        return Ok(index as usize);
+    }
+
+    let resolved = length as i64 + index;
+    if resolved < 0 {
+        return Err(ExecutionError::IndexOutOfBounds { index, length });
+    }
+
+    Ok(resolved as usize)
+}
+
+// negative bounds count back from the end, both are clamped to the array
+// so out of range or reversed bounds give a shorter or empty slice
+fn slice_bounds(start: i64, end: i64, length: usize) -> (usize, usize) {
+    let clamp = |bound: i64| {
+        let bound = if bound < 0 {
+            length as i64 + bound
+        } else {
+            bound
+        };
+
+        bound.clamp(0, length as i64) as usize
+    };
+
+    let start = clamp(start);
+    (start, clamp(end).max(start))
+}
+
+// adds `delta` in place, used for loop counters
+fn step(value: &mut VMValue, delta: i64) -> Result<(), ExecutionError> {
+    let type_name = value.type_name();
+    let what = if delta < 0 { "decrement" } else { "increment" };
+    let invalid_step = || ExecutionError::InvalidOperation {
+        cause: format!("cannot {what} {type_name}"),
+    };
+
+    let VMValue::Literal(literal) = value else {
+        return Err(invalid_step());
+    };
+
+    match literal.to_mut() {
+        Literal::Integer(i) => *i += delta,
+        Literal::Float(f) => *f += delta as f64,
+        _ => return Err(invalid_step()),
+    }
+
+    Ok(())
+}
+
+// nil, 0, 0.0 and "" are falsy, everything else is truthy
+fn cast<'a>(value: &VMValue, target_type: CastTarget) -> Result<VMValue<'a>, ExecutionError> {
+    let invalid_cast = || ExecutionError::InvalidCast {
+        from: value.type_name(),
+        to: target_type,
+    };
+
+    let literal = match (value, target_type) {
+        (VMValue::Literal(literal), CastTarget::Int) => match literal.as_ref() {
+            Literal::Integer(i) => Literal::Integer(*i),
+            Literal::Float(f) => Literal::Integer(f.trunc() as i64),
+            Literal::Boolean(b) => Literal::Integer(*b as i64),
+            Literal::String(s) => Literal::Integer(s.trim().parse().map_err(|_| invalid_cast())?),
+            Literal::Nil => return Err(invalid_cast()),
+        },
+        (VMValue::Literal(literal), CastTarget::Float) => match literal.as_ref() {
+            Literal::Integer(i) => Literal::Float(*i as f64),
+            Literal::Float(f) => Literal::Float(*f),
+            Literal::String(s) => Literal::Float(s.trim().parse().map_err(|_| invalid_cast())?),
+            Literal::Boolean(_) | Literal::Nil => return Err(invalid_cast()),
+        },
+        (VMValue::Function(_), CastTarget::String) => return Err(invalid_cast()),
+        (value, CastTarget::String) => Literal::String(value.to_string()),
+        (VMValue::Empty | VMValue::Nil, CastTarget::Bool) => Literal::Boolean(false),
+        (VMValue::Literal(literal), CastTarget::Bool) => Literal::Boolean(match literal.as_ref() {
+            Literal::Integer(i) => *i != 0,
+            Literal::Float(f) => *f != 0.0,
+            Literal::String(s) => !s.is_empty(),
+            Literal::Boolean(b) => *b,
+            Literal::Nil => false,
+        }),
+        (VMValue::Object(_) | VMValue::Array(_), CastTarget::Bool) => Literal::Boolean(true),
+        _ => return Err(invalid_cast()),
+    };
+
+    Ok(VMValue::Literal(Cow::Owned(literal)))
 }
 
 impl VM {
@@ -51,6 +283,7 @@ impl VM {
                 .map(Rc::new)
                 .collect(),
             native_functions: Default::default(),
+            native_context: Default::default(),
             global_function: compiler::Function {
                 name: "global".to_owned(),
                 code: compiled_program.global_code,
@@ -58,9 +291,73 @@ impl VM {
             }
             .into(),
             literals: compiled_program.literals,
+            global_result: compiled_program.global_result,
+            global_symbols: compiled_program.global_symbols,
+            instruction_limit: None,
+            fuel: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            collect_stats: false,
+            stats: Default::default(),
+            step_hook: None,
         }
     }
 
+    // stops runaway scripts, unlimited unless set
+    #[allow(unused)]
+    pub fn with_instruction_limit(mut self, limit: u64) -> Self {
+        self.instruction_limit = Some(limit);
+
+        self
+    }
+
+    // an instruction limit that reports running out as FuelExhausted, each instruction burns one unit
+    #[allow(unused)]
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+
+        self
+    }
+
+    // deep recursion errors instead of eating all memory
+    #[allow(unused)]
+    pub fn with_max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = depth;
+
+        self
+    }
+
+    // per instruction and per function counts, off by default to keep the loop lean
+    pub fn with_stats(mut self, enabled: bool) -> Self {
+        self.collect_stats = enabled;
+
+        self
+    }
+
+    // counters from the last run
+    pub fn stats(&self) -> VMStats {
+        self.stats.borrow().clone()
+    }
+
+    // called before every instruction, for debuggers and the like
+    #[allow(unused)]
+    pub fn with_step_hook(
+        mut self,
+        step_hook: impl FnMut(&StepInfo) -> StepControl + 'static,
+    ) -> Self {
+        self.step_hook = Some(RefCell::new(Box::new(step_hook)));
+
+        self
+    }
+
+    // a step hook that only watches, called with the ip and instruction before each one runs
+    #[allow(unused)]
+    pub fn with_trace(self, trace_fn: impl Fn(usize, &Instruction) + 'static) -> Self {
+        self.with_step_hook(move |step| {
+            trace_fn(step.ip, &step.instruction);
+            StepControl::Continue
+        })
+    }
+
     #[allow(unused)]
     pub fn define_native_function(mut self, name: String, function: NativeFunctionType) -> Self {
         self.native_functions.insert(name, function);
@@ -68,10 +365,43 @@ impl VM {
         self
     }
 
+    #[allow(unused)]
+    pub fn with_user_data(self, user_data: impl Any) -> Self {
+        self.native_context.borrow_mut().user_data = Some(Box::new(user_data));
+
+        self
+    }
+
+    // print writes here instead of stdout
+    #[allow(unused)]
+    pub fn with_output(self, output: impl Write + 'static) -> Self {
+        self.native_context.borrow_mut().output = Box::new(output);
+
+        self
+    }
+
+    // input reads from here instead of stdin
+    #[allow(unused)]
+    pub fn with_input(self, input: impl BufRead + 'static) -> Self {
+        self.native_context.borrow_mut().input = Box::new(input);
+
+        self
+    }
+
+    // whatever native functions left in the context
+    #[allow(unused)]
+    pub fn user_data<T: Any>(&self) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.native_context.borrow(), |context| {
+            context.user_data.as_ref()?.downcast_ref()
+        })
+        .ok()
+    }
+
     fn print_registers(window: &Registers<'_>) {
-        for (i, item) in window.regs().iter().enumerate() {
+        for (i, item) in window.current().iter().enumerate() {
             match item {
                 VMValue::Empty => {}
+                VMValue::Nil => tracing::debug!("{i} nil"),
                 VMValue::Literal(l) => tracing::debug!("{i} {:?}", l),
                 VMValue::Function(f) => tracing::debug!("{i} {:?}", f.name),
                 VMValue::Object(object) => tracing::debug!("{i} {:?}", object),
@@ -82,19 +412,192 @@ impl VM {
         tracing::debug!("");
     }
 
-    pub fn run_with_registers_returned(&self) -> Result<Registers, ExecutionError> {
-        let mut registers = Registers::default();
+    // fresh state at the start of the global code, drive it with resume
+    pub fn start(&self) -> ExecutionState<'_> {
+        ExecutionState {
+            ip: 0,
+            current_function: self.global_function.clone(),
+            saved_call_frames: Vec::new(),
+            registers: Registers::new(self.global_function.register_count),
+            stats: VMStats::default(),
+            resuming: false,
+        }
+    }
+
+    pub fn run_with_registers_returned(&self) -> Result<(Registers<'_>, RunStats), ExecutionError> {
+        let started = Instant::now();
+        let mut state = self.start();
+
+        // without a host to hand control to, a pause just carries on
+        while self.resume(&mut state)? == Execution::Paused {}
+
+        let run_stats = RunStats {
+            instructions: state.stats.instructions_executed,
+            elapsed: started.elapsed(),
+        };
+
+        Ok((state.registers, run_stats))
+    }
+
+    // reads a top level variable of a paused or finished run, objects and
+    // arrays are shared so changing them changes what the script sees.
+    // `run` drops its state when it returns, so a host that wants the globals
+    // afterwards runs the program with `start` and `resume` itself
+    #[allow(unused)]
+    pub fn get_global<'a>(&self, state: &ExecutionState<'a>, name: &str) -> Option<VMValue<'a>> {
+        let register = self.global_symbols.get(name)?;
+
+        state.registers.global().get(*register as usize).cloned()
+    }
+
+    // changes a top level variable in a state from `start`, the script sees
+    // the new value when it's resumed
+    #[allow(unused)]
+    pub fn set_global<'a>(
+        &self,
+        state: &mut ExecutionState<'a>,
+        name: &str,
+        value: VMValue<'a>,
+    ) -> Result<(), ExecutionError> {
+        let global = self
+            .global_symbols
+            .get(name)
+            .and_then(|register| state.registers.global_mut().get_mut(*register as usize))
+            .ok_or_else(|| ExecutionError::InvalidOperation {
+                cause: "global not found".to_owned(),
+            })?;
+
+        *global = value;
+
+        Ok(())
+    }
+
+    // calls a compiled function from the host, which stands in as the caller
+    #[allow(unused)]
+    pub fn call_function<'a>(
+        &'a self,
+        name: &str,
+        args: &[VMValue<'a>],
+    ) -> Result<VMValue<'a>, ExecutionError> {
+        let function = self
+            .functions
+            .iter()
+            .rev()
+            .find(|f| f.name == name)
+            .ok_or_else(|| ExecutionError::InvalidOperation {
+                cause: "function not found".to_owned(),
+            })?;
+
+        // one register after the arguments is needed for the return value
+        let arg_count = Register::try_from(args.len())
+            .ok()
+            .filter(|count| *count < Register::MAX)
+            .ok_or_else(|| ExecutionError::InvalidOperation {
+                cause: format!("too many arguments to `{name}`"),
+            })?;
+        let return_val = arg_count;
+
+        let mut registers = Registers::new(arg_count + 1);
+        for (i, arg) in args.iter().enumerate() {
+            registers[i as Register] = arg.clone();
+        }
+        registers.push_frame(function.register_count, 0..arg_count);
+
+        // returning lands past the end of this, which finishes the run
+        let host = compiler::Function {
+            name: "host".to_owned(),
+            code: Vec::new(),
+            register_count: arg_count + 1,
+        };
+
+        let mut state = ExecutionState {
+            ip: 0,
+            current_function: function.clone(),
+            saved_call_frames: vec![SavedCallFrame {
+                ip: 0,
+                function: host.into(),
+                function_return_value: return_val,
+            }],
+            registers,
+            stats: VMStats::default(),
+            resuming: false,
+        };
+
+        while self.resume(&mut state)? == Execution::Paused {}
+
+        Ok(std::mem::take(&mut state.registers[return_val]))
+    }
+
+    // runs until the program finishes, the step hook pauses or an error occurs
+    pub fn resume<'a>(
+        &'a self,
+        state: &mut ExecutionState<'a>,
+    ) -> Result<Execution, ExecutionError> {
+        let result = self.execute(state);
+
+        // stats are kept even when execution fails
+        self.stats.replace(state.stats.clone());
+
+        result
+    }
+
+    fn execute<'a>(&'a self, state: &mut ExecutionState<'a>) -> Result<Execution, ExecutionError> {
+        let ExecutionState {
+            ip,
+            current_function,
+            saved_call_frames,
+            registers,
+            stats,
+            resuming,
+        } = state;
+
+        // checked once up front, the register dump walks every register
+        let print_registers = tracing::enabled!(Level::DEBUG);
+        let instruction_limit = self.instruction_limit.unwrap_or(u64::MAX);
+        let fuel = self.fuel.unwrap_or(u64::MAX);
+
+        // both count against instructions_executed, which only goes up once the hook lets an
+        // instruction run, so a pause doesn't charge the same instruction twice
+        while let Some(&current_instruction) = current_function.code.get(*ip) {
+            if stats.instructions_executed >= fuel {
+                return Err(ExecutionError::FuelExhausted);
+            }
+
+            if stats.instructions_executed >= instruction_limit {
+                return Err(ExecutionError::InstructionLimitExceeded {
+                    executed: stats.instructions_executed,
+                });
+            }
 
-        let mut saved_call_frames = Vec::<SavedCallFrame>::new();
-        let mut current_function = self.global_function.clone();
+            // the instruction a pause stopped on was already shown to the hook
+            if let Some(step_hook) = &self.step_hook {
+                if !std::mem::take(resuming) {
+                    let step_info = StepInfo {
+                        instruction: current_instruction,
+                        ip: *ip,
+                        function_name: &current_function.name,
+                        registers: registers.current(),
+                    };
+
+                    match (step_hook.borrow_mut())(&step_info) {
+                        StepControl::Continue => {}
+                        StepControl::Pause => {
+                            *resuming = true;
+                            return Ok(Execution::Paused);
+                        }
+                        StepControl::Abort => return Err(ExecutionError::Aborted),
+                    }
+                }
+            }
 
-        let mut ip = 0;
-        loop {
-            if ip >= current_function.code.len() {
-                break;
+            stats.instructions_executed += 1;
+            if self.collect_stats {
+                *stats
+                    .instruction_counts
+                    .entry(current_instruction.name())
+                    .or_default() += 1;
             }
 
-            let current_instruction = current_function.code[ip];
             tracing::debug!("executing: {:?}", current_instruction);
             // tracing::info!("ip: {:?}", ip);
             // tracing::info!("code: {:?}", current_code);
@@ -102,42 +605,39 @@ impl VM {
             // tracing::info!("base_reg: {:?}", base_register);
 
             match current_instruction {
+                Instruction::Increment { reg } => {
+                    step(&mut registers[reg], 1)?;
+
+                    *ip += 1;
+                }
+
+                Instruction::Decrement { reg } => {
+                    step(&mut registers[reg], -1)?;
+
+                    *ip += 1;
+                }
+
                 Instruction::FunctionReturn => {
                     if let Some(saved_call_frame) = saved_call_frames.pop() {
-                        let mut base_register = registers.base_register();
-                        if let Some(current_call_frame) = saved_call_frames.last() {
-                            base_register -= current_call_frame.register_count as usize;
-                        } else {
-                            base_register -= self.global_function.register_count as usize;
-                        }
+                        registers.pop_frame();
+                        // falling off the end returns nil
+                        registers[saved_call_frame.function_return_value] = VMValue::Nil;
 
-                        registers.update_base_register(base_register);
-
-                        ip = saved_call_frame.ip + 1;
-                        current_function = saved_call_frame.function;
+                        *ip = saved_call_frame.ip + 1;
+                        *current_function = saved_call_frame.function;
                         continue;
                     };
                 }
                 Instruction::Return { val } => {
                     if let Some(saved_call_frame) = saved_call_frames.pop() {
-                        let mut base_register = registers.base_register();
-                        if let Some(current_call_frame) = saved_call_frames.last() {
-                            base_register -= current_call_frame.register_count as usize;
-                        } else {
-                            base_register -= self.global_function.register_count as usize;
-                        }
-
-                        let register_to_copy_to = saved_call_frame.function_return_value;
-                        let register_to_copy_from = val;
-
-                        let from = registers[register_to_copy_from].clone();
-
-                        registers.update_base_register(base_register);
+                        // the callee frame is dead after this, no need to clone
+                        let from = std::mem::take(&mut registers[val]);
+                        registers.pop_frame();
 
-                        registers[register_to_copy_to] = from;
+                        registers[saved_call_frame.function_return_value] = from;
 
-                        ip = saved_call_frame.ip + 1;
-                        current_function = saved_call_frame.function;
+                        *ip = saved_call_frame.ip + 1;
+                        *current_function = saved_call_frame.function;
                         continue;
                     };
                 }
@@ -145,7 +645,7 @@ impl VM {
                     let func = self.functions[src as usize].clone();
                     registers[dest] = VMValue::Function(func);
 
-                    ip += 1;
+                    *ip += 1;
                 }
                 Instruction::CallNativeFunction {
                     src,
@@ -185,30 +685,35 @@ impl VM {
 
                     let native_function = native_function.unwrap();
 
-                    let arg_start = src - arg_count;
+                    if self.collect_stats {
+                        *stats
+                            .function_calls
+                            .entry(function_name.to_owned())
+                            .or_default() += 1;
+                    }
+
+                    let arg_start = src - arg_count as Register;
                     let arg_end = src;
 
+                    // argument registers are copies made for this call, move them out
                     let mut arg_values = Vec::with_capacity((arg_end - arg_start) as usize);
-                    let registers_to_copy = &registers[arg_start..arg_end];
-                    for register in registers_to_copy {
-                        arg_values.push(register.clone());
+                    let registers_to_move = &mut registers[arg_start..arg_end];
+                    for register in registers_to_move {
+                        arg_values.push(std::mem::take(register));
                     }
 
-                    // TODO: return value?
-                    let return_value = (native_function)(arg_values);
-                    if let Some(return_value) = return_value {
-                        registers[return_val] = return_value
-                    }
+                    let return_value =
+                        (native_function)(arg_values, &mut self.native_context.borrow_mut())?;
+                    registers[return_val] = return_value.unwrap_or(VMValue::Nil);
 
-                    ip += 1;
+                    *ip += 1;
                 }
                 Instruction::CallFunction {
                     src,
                     arg_count,
                     return_val,
                 } => {
-                    let func = &registers[src];
-                    let func = match func {
+                    let func = match &registers[src] {
                         VMValue::Function(f) => f.clone(),
                         _ => unreachable!(),
                     };
@@ -217,48 +722,24 @@ impl VM {
                     // eprintln!("DEBUGPRINT[3]: vm.rs:124: arg_end={:#?}", arg_end);
                     // tracing::info!("func: {:?}", func);
 
-                    let old_function = current_function;
-                    let old_ip = ip;
-
-                    current_function = func.clone();
-                    ip = 0;
-
-                    let mut base_register = registers.base_register();
-                    let old_base = base_register;
-                    let register_count = func.register_count;
-                    if let Some(current_call_frame) = saved_call_frames.last() {
-                        base_register += current_call_frame.register_count as usize;
-                    } else {
-                        base_register += self.global_function.register_count as usize;
+                    if saved_call_frames.len() >= self.max_call_depth {
+                        return Err(ExecutionError::StackOverflow {
+                            depth: self.max_call_depth,
+                        });
                     }
 
-                    registers.update_base_register(base_register);
-
-                    let (old_function_regs, new_function_regs) =
-                        registers.regs_mut().split_at_mut(base_register);
-
-                    // tracing::warn!("FUNCTION CALL: OLD");
-                    // Self::print_registers(old_function);
-
-                    let arg_start = old_base + src as usize - arg_count as usize;
-                    let arg_end = old_base + src as usize;
-                    let registers_to_copy = &old_function_regs[arg_start..arg_end];
+                    let register_count = func.register_count;
+                    let old_function = std::mem::replace(current_function, func);
+                    let old_ip = *ip;
 
-                    // tracing::warn!("FUNCTION CALL: COPY");
-                    // Self::print_registers(registers_to_copy);
+                    *ip = 0;
 
-                    for (index, register) in registers_to_copy.iter().enumerate() {
-                        new_function_regs[index + 1] = register.clone();
-                    }
+                    // arguments sit in the registers just before the function
+                    registers.push_frame(register_count, src - arg_count as Register..src);
 
-                    // tracing::warn!("FUNCTION CALL: NEW");
-                    // Self::print_registers(new_function);
-
-                    registers.update_base_register(base_register);
                     saved_call_frames.push(SavedCallFrame {
                         ip: old_ip,
                         function: old_function,
-                        register_count,
                         function_return_value: return_val,
                     });
 
@@ -266,81 +747,278 @@ impl VM {
                     // tracing::warn!("register: {:?}", self.global_register_count);
                     // tracing::warn!("register: {:?}", register_count);
 
-                    Self::print_registers(&registers);
+                    if self.collect_stats {
+                        *stats
+                            .function_calls
+                            .entry(current_function.name.clone())
+                            .or_default() += 1;
+                        stats.peak_call_depth = stats.peak_call_depth.max(saved_call_frames.len());
+                    }
+
+                    if print_registers {
+                        Self::print_registers(registers);
+                    }
+
                     continue;
                 }
 
+                Instruction::ArrayLength { dest, src } => {
+                    let Some(length) = registers[src].length() else {
+                        return Err(ExecutionError::InvalidOperation {
+                            cause: format!("cannot take length of {}", registers[src].type_name()),
+                        });
+                    };
+
+                    registers[dest] = VMValue::Literal(Cow::Owned(Literal::Integer(length as i64)));
+
+                    *ip += 1;
+                }
+                Instruction::AppendArray { array, value } => {
+                    let array = match registers[array] {
+                        VMValue::Array(ref array) => array.clone(),
+                        ref other => {
+                            return Err(ExecutionError::InvalidOperation {
+                                cause: format!("cannot push to {}", other.type_name()),
+                            })
+                        }
+                    };
+
+                    let value = registers[value].to_object_value();
+                    array.borrow_mut().push(Rc::new(value.into()));
+
+                    *ip += 1;
+                }
+                Instruction::PopArray { dest, array } => {
+                    let array = match registers[array] {
+                        VMValue::Array(ref array) => array.clone(),
+                        ref other => {
+                            return Err(ExecutionError::InvalidOperation {
+                                cause: format!("cannot pop from {}", other.type_name()),
+                            })
+                        }
+                    };
+
+                    // popping an empty array gives nil
+                    let popped = array.borrow_mut().pop();
+                    registers[dest] = match popped {
+                        Some(obj) => VMValue::from(&*obj.borrow()),
+                        None => VMValue::Nil,
+                    };
+
+                    *ip += 1;
+                }
+
+                Instruction::Cast {
+                    dest,
+                    src,
+                    target_type,
+                } => {
+                    registers[dest] = cast(&registers[src], target_type)?;
+
+                    *ip += 1;
+                }
+                Instruction::IntToFloat { dest, src } => {
+                    registers[dest] = match &registers[src] {
+                        VMValue::Literal(literal) => match literal.as_ref() {
+                            Literal::Integer(n) => {
+                                VMValue::Literal(Cow::Owned(Literal::Float(*n as f64)))
+                            }
+                            _ => registers[src].clone(),
+                        },
+                        value => value.clone(),
+                    };
+
+                    *ip += 1;
+                }
+                Instruction::FloatToInt { dest, src } => {
+                    registers[dest] = match &registers[src] {
+                        VMValue::Literal(literal) => match literal.as_ref() {
+                            Literal::Float(f) => {
+                                VMValue::Literal(Cow::Owned(Literal::Integer(f.trunc() as i64)))
+                            }
+                            _ => registers[src].clone(),
+                        },
+                        value => value.clone(),
+                    };
+
+                    *ip += 1;
+                }
+                Instruction::TypeOf { dest, src } => {
+                    let type_name = registers[src].type_name();
+                    registers[dest] =
+                        VMValue::Literal(Cow::Owned(Literal::String(type_name.to_owned())));
+
+                    *ip += 1;
+                }
+                Instruction::LoadNil { dest } => {
+                    registers[dest] = VMValue::Nil;
+
+                    *ip += 1;
+                }
                 Instruction::LoadLiteral { dest, src } => {
                     let literal = &self.literals[src as usize];
                     registers[dest] = VMValue::Literal(Cow::Borrowed(literal));
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::Add { dest, lhs, rhs } => {
-                    impl_binary_op!(registers, dest, lhs, +, rhs);
+                    // the compiler can't always tell strings apart, e.g. two variables
+                    if is_string(&registers[lhs]) && is_string(&registers[rhs]) {
+                        registers[dest] = concat_strings(&registers[lhs], &registers[rhs])?;
+                    } else {
+                        impl_binary_op!(registers, dest, lhs, +, rhs);
+                    }
 
-                    ip += 1;
+                    *ip += 1;
+                }
+
+                Instruction::StringConcat { dest, lhs, rhs } => {
+                    registers[dest] = concat_strings(&registers[lhs], &registers[rhs])?;
+
+                    *ip += 1;
                 }
 
                 Instruction::Sub { dest, lhs, rhs } => {
                     impl_binary_op!(registers, dest, lhs, -, rhs);
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::Mul { dest, lhs, rhs } => {
                     impl_binary_op!(registers, dest, lhs, *, rhs);
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::Div { dest, lhs, rhs } => {
                     impl_binary_op!(registers, dest, lhs, /, rhs);
 
-                    ip += 1;
+                    *ip += 1;
+                }
+
+                Instruction::Modulo { dest, lhs, rhs } => {
+                    let result = match (&registers[lhs], &registers[rhs]) {
+                        (VMValue::Literal(lhs), VMValue::Literal(rhs)) => {
+                            match (lhs.as_ref(), rhs.as_ref()) {
+                                (Literal::Integer(lhs), Literal::Integer(rhs)) => lhs
+                                    .checked_rem(*rhs)
+                                    .map(Literal::Integer)
+                                    .ok_or_else(|| ExecutionError::InvalidOperation {
+                                        cause: "integer modulo by zero".to_owned(),
+                                    })?,
+                                (Literal::Float(lhs), Literal::Float(rhs)) => {
+                                    Literal::Float(lhs % rhs)
+                                }
+                                (Literal::Float(lhs), Literal::Integer(rhs)) => {
+                                    Literal::Float(lhs % *rhs as f64)
+                                }
+                                (Literal::Integer(lhs), Literal::Float(rhs)) => {
+                                    Literal::Float(*lhs as f64 % rhs)
+                                }
+                                _ => {
+                                    return Err(ExecutionError::InvalidOperation {
+                                        cause: "'%' must be used on number types".to_owned(),
+                                    })
+                                }
+                            }
+                        }
+                        _ => {
+                            return Err(ExecutionError::InvalidOperation {
+                                cause: "'%' must be used on literals only".to_owned(),
+                            })
+                        }
+                    };
+
+                    registers[dest] = VMValue::Literal(Cow::Owned(result));
+
+                    *ip += 1;
+                }
+
+                Instruction::BitAnd { dest, lhs, rhs } => {
+                    impl_integer_op!(registers, dest, lhs, "&", rhs, |l: i64, r: i64| Ok(l & r));
+
+                    *ip += 1;
+                }
+
+                Instruction::BitOr { dest, lhs, rhs } => {
+                    impl_integer_op!(registers, dest, lhs, "|", rhs, |l: i64, r: i64| Ok(l | r));
+
+                    *ip += 1;
+                }
+
+                Instruction::BitXor { dest, lhs, rhs } => {
+                    impl_integer_op!(registers, dest, lhs, "^", rhs, |l: i64, r: i64| Ok(l ^ r));
+
+                    *ip += 1;
+                }
+
+                Instruction::Shl { dest, lhs, rhs } => {
+                    impl_integer_op!(registers, dest, lhs, "<<", rhs, |l: i64, r: i64| {
+                        u32::try_from(r)
+                            .ok()
+                            .and_then(|r| l.checked_shl(r))
+                            .ok_or_else(|| ExecutionError::InvalidOperation {
+                                cause: format!("cannot shift by {r}, must be between 0 and 63"),
+                            })
+                    });
+
+                    *ip += 1;
+                }
+
+                Instruction::Shr { dest, lhs, rhs } => {
+                    impl_integer_op!(registers, dest, lhs, ">>", rhs, |l: i64, r: i64| {
+                        u32::try_from(r)
+                            .ok()
+                            .and_then(|r| l.checked_shr(r))
+                            .ok_or_else(|| ExecutionError::InvalidOperation {
+                                cause: format!("cannot shift by {r}, must be between 0 and 63"),
+                            })
+                    });
+
+                    *ip += 1;
                 }
 
                 Instruction::Equals { dest, lhs, rhs } => {
                     impl_binary_comparator!(registers, dest, lhs, ==, rhs);
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::NotEquals { dest, lhs, rhs } => {
                     impl_binary_comparator!(registers, dest, lhs, !=, rhs);
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::GreaterThan { dest, lhs, rhs } => {
                     impl_binary_comparator!(registers, dest, lhs, >, rhs);
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::GreaterThanOrEquals { dest, lhs, rhs } => {
                     impl_binary_comparator!(registers, dest, lhs, >=, rhs);
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::LessThan { dest, lhs, rhs } => {
                     impl_binary_comparator!(registers, dest, lhs, <, rhs);
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::LessThanOrEquals { dest, lhs, rhs } => {
                     impl_binary_comparator!(registers, dest, lhs, <=, rhs);
 
-                    ip += 1;
+                    *ip += 1;
                 }
 
                 Instruction::Copy { dest, src } => {
                     registers[dest] = registers[src].clone();
 
-                    ip += 1;
+                    *ip += 1;
                 }
                 Instruction::PrefixNot { dest, rhs } => {
                     let rhs = &registers[rhs];
@@ -364,7 +1042,31 @@ impl VM {
                         }
                     }
 
-                    ip += 1;
+                    *ip += 1;
+                }
+                Instruction::BitNot { dest, rhs } => {
+                    let rhs = &registers[rhs];
+
+                    match rhs {
+                        VMValue::Literal(literal) => match literal.as_ref() {
+                            types::Literal::Integer(v) => {
+                                registers[dest] = VMValue::Literal(Cow::Owned(Literal::Integer(!v)))
+                            }
+
+                            _ => {
+                                return Err(ExecutionError::InvalidOperation {
+                                    cause: "'~' must be used on integers".to_owned(),
+                                })
+                            }
+                        },
+                        _ => {
+                            return Err(ExecutionError::InvalidOperation {
+                                cause: "'~' must be used on literals only".to_owned(),
+                            })
+                        }
+                    }
+
+                    *ip += 1;
                 }
                 Instruction::PrefixSub { dest, rhs } => {
                     let rhs = &registers[rhs];
@@ -396,35 +1098,59 @@ impl VM {
                         }
                     }
 
-                    ip += 1;
+                    *ip += 1;
                 }
                 Instruction::JumpIfFalse { src, offset } => {
                     let register_value = &registers[src];
-                    // FIXME: are we type checked?
-                    match register_value {
-                        VMValue::Object(_)
-                        | VMValue::Function(_)
-                        | VMValue::Empty
-                        | VMValue::Array(_) => {
-                            unreachable!()
+                    // not guaranteed to be typechecked, --no-typecheck or native functions
+                    let condition = match register_value {
+                        VMValue::Literal(l) => match l.as_ref() {
+                            Literal::Boolean(b) => Some(*b),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+
+                    match condition {
+                        Some(true) => *ip += 1,
+                        Some(false) => *ip = ip.wrapping_add_signed(offset as isize),
+                        None => {
+                            return Err(ExecutionError::InvalidOperation {
+                                cause: format!(
+                                    "if condition evaluated to {}, expected bool",
+                                    register_value.type_name()
+                                ),
+                            })
                         }
+                    }
+                }
+                Instruction::JumpIfTrue { src, offset } => {
+                    let register_value = &registers[src];
+                    let condition = match register_value {
                         VMValue::Literal(l) => match l.as_ref() {
-                            Literal::Boolean(b) => {
-                                if *b {
-                                    ip += 1;
-                                } else {
-                                    ip += offset as usize;
-                                }
-                            }
-                            _ => unreachable!(),
+                            Literal::Boolean(b) => Some(*b),
+                            _ => None,
                         },
+                        _ => None,
+                    };
+
+                    match condition {
+                        Some(true) => *ip = ip.wrapping_add_signed(offset as isize),
+                        Some(false) => *ip += 1,
+                        None => {
+                            return Err(ExecutionError::InvalidOperation {
+                                cause: format!(
+                                    "condition evaluated to {}, expected bool",
+                                    register_value.type_name()
+                                ),
+                            })
+                        }
                     }
                 }
-                Instruction::Jump { offset } => ip += offset as usize,
-                Instruction::JumpReverse { offset } => ip -= offset as usize,
+                Instruction::Jump { offset } => *ip = ip.wrapping_add_signed(offset as isize),
                 Instruction::AllocateObject { dest } => {
                     registers[dest] = VMValue::Object(Object::create_for_vm());
-                    ip += 1;
+                    *ip += 1;
                 }
                 Instruction::SetObjectField {
                     object,
@@ -444,16 +1170,33 @@ impl VM {
                         _ => unreachable!(),
                     };
 
-                    let value = match &registers[value] {
-                        VMValue::Literal(lit) => ObjectValue::Literal(lit.as_ref().clone()),
-                        VMValue::Object(object) => ObjectValue::Object(object.clone()),
-                        VMValue::Function(f) => ObjectValue::Function(f.clone()),
-                        VMValue::Array(array) => ObjectValue::Array(array.clone()),
+                    let value = registers[value].to_object_value();
+
+                    obj.borrow_mut().insert(key, Rc::new(value.into()));
+                    *ip += 1;
+                }
+                Instruction::SpreadObject { object, src } => {
+                    let obj = match &registers[object] {
+                        VMValue::Object(object) => object,
                         _ => unreachable!(),
                     };
 
-                    obj.borrow_mut().insert(key, Rc::new(value.into()));
-                    ip += 1;
+                    let src = match &registers[src] {
+                        VMValue::Object(src) => src,
+                        other => {
+                            return Err(ExecutionError::InvalidOperation {
+                                cause: format!("cannot spread {}", other.type_name()),
+                            })
+                        }
+                    };
+
+                    // each field gets its own cell, nested objects and arrays are still shared
+                    for (key, value) in src.borrow().iter() {
+                        obj.borrow_mut()
+                            .insert(key.clone(), Rc::new(value.borrow().clone().into()));
+                    }
+
+                    *ip += 1;
                 }
                 Instruction::GetObjectField {
                     object,
@@ -474,30 +1217,45 @@ impl VM {
                         let obj_value = obj.index(key);
 
                         match obj_value {
-                            Some(obj) => {
-                                let obj = obj.clone();
-                                let obj = obj.borrow();
-
-                                match &*obj {
-                                    ObjectValue::Object(rc) => VMValue::Object(rc.clone()),
-                                    ObjectValue::Literal(literal) => {
-                                        VMValue::Literal(Cow::Owned(literal.clone()))
-                                    }
-                                    ObjectValue::Function(func) => VMValue::Function(func.clone()),
-                                    ObjectValue::Array(rc) => VMValue::Array(rc.clone()),
-                                    ObjectValue::Nil => VMValue::Empty,
-                                }
-                            }
-                            None => VMValue::Empty,
+                            Some(obj) => VMValue::from(&*obj.borrow()),
+                            None => VMValue::Nil,
                         }
                     };
 
                     registers[return_val] = register_value;
-                    ip += 1;
+                    *ip += 1;
+                }
+                Instruction::SliceArray { dest, array, start } => {
+                    let bound = |register: Register| match &registers[register] {
+                        VMValue::Literal(lit) => match lit.as_ref() {
+                            Literal::Integer(integer) => *integer,
+                            _ => unreachable!(),
+                        },
+                        _ => unreachable!(),
+                    };
+
+                    let array = match registers[array] {
+                        VMValue::Array(ref a) => a.clone(),
+                        _ => unreachable!(),
+                    };
+                    let array = array.borrow();
+
+                    let (start, end) = slice_bounds(bound(start), bound(start + 1), array.len());
+                    let slice = Array::create_for_vm();
+                    for (i, index) in (start..end).enumerate() {
+                        if let Some(value) = array.index(index) {
+                            let value = value.borrow().clone();
+                            slice.borrow_mut().set(i, Rc::new(value.into()));
+                        }
+                    }
+
+                    registers[dest] = VMValue::Array(slice);
+                    *ip += 1;
                 }
+
                 Instruction::AllocateArray { dest } => {
                     registers[dest] = VMValue::Array(Array::create_for_vm());
-                    ip += 1;
+                    *ip += 1;
                 }
                 Instruction::SetArrayIndex {
                     array,
@@ -517,19 +1275,12 @@ impl VM {
                         _ => unreachable!(),
                     };
 
-                    let value = match &registers[value] {
-                        VMValue::Literal(lit) => ObjectValue::Literal(lit.as_ref().clone()),
-                        VMValue::Object(object) => ObjectValue::Object(object.clone()),
-                        VMValue::Function(f) => ObjectValue::Function(f.clone()),
-                        VMValue::Array(array) => ObjectValue::Array(array.clone()),
-                        _ => unreachable!(),
-                    };
+                    let value = registers[value].to_object_value();
 
-                    array
-                        .borrow_mut()
-                        .set((*index) as usize, Rc::new(value.into()));
+                    let index = resolve_index(*index, array.borrow().len())?;
+                    array.borrow_mut().set(index, Rc::new(value.into()));
 
-                    ip += 1;
+                    *ip += 1;
                 }
                 Instruction::GetArrayIndex {
                     array,
@@ -550,41 +1301,39 @@ impl VM {
                             _ => unreachable!(),
                         };
                         let array = array.borrow();
-                        let array_value = array.index((*index) as usize);
+                        let array_value = array.index(resolve_index(*index, array.len())?);
 
                         match array_value {
-                            Some(obj) => {
-                                let obj = obj.clone();
-                                let obj = obj.borrow();
-
-                                match &*obj {
-                                    ObjectValue::Object(rc) => VMValue::Object(rc.clone()),
-                                    ObjectValue::Literal(literal) => {
-                                        VMValue::Literal(Cow::Owned(literal.clone()))
-                                    }
-                                    ObjectValue::Function(func) => VMValue::Function(func.clone()),
-                                    ObjectValue::Array(rc) => VMValue::Array(rc.clone()),
-                                    ObjectValue::Nil => VMValue::Empty,
-                                }
-                            }
-                            None => VMValue::Empty,
+                            Some(obj) => VMValue::from(&*obj.borrow()),
+                            None => VMValue::Nil,
                         }
                     };
 
                     registers[return_val] = register_value;
-                    ip += 1;
+                    *ip += 1;
                 }
             }
 
-            Self::print_registers(&registers);
+            if print_registers {
+                Self::print_registers(registers);
+            }
         }
 
         // dbg!(registers);
 
-        Ok(registers)
+        Ok(Execution::Finished)
     }
 
     pub fn run(&self) -> Result<(), ExecutionError> {
         self.run_with_registers_returned().map(|_| ())
     }
+
+    // value of the last top level expression statement, None if the program doesn't end in one
+    pub fn run_with_result(&self) -> Result<Option<VMValue<'_>>, ExecutionError> {
+        let (mut registers, _) = self.run_with_registers_returned()?;
+
+        Ok(self
+            .global_result
+            .map(|register| std::mem::take(&mut registers[register])))
+    }
 }
@@ -1,55 +1,65 @@
-use std::{
-    cell::RefCell,
-    ops::{Index, IndexMut, Range},
-};
+use std::ops::{Index, IndexMut, Range};
 
 use crate::instructions::Register;
 
 use super::VMValue;
 
 pub struct Registers<'a> {
-    registers: Vec<VMValue<'a>>,
-    base_register: RefCell<usize>,
+    // one window per active call, sized to the function, the last one is running
+    frames: Vec<Vec<VMValue<'a>>>,
 }
 
 impl<'a> Registers<'a> {
-    pub fn new() -> Self {
-        let mut s = Self {
-            registers: Vec::with_capacity(u8::MAX as usize * 4),
-            base_register: RefCell::new(0),
-        };
+    pub fn new(register_count: Register) -> Self {
+        Self {
+            frames: vec![Self::frame(register_count)],
+        }
+    }
 
-        s.registers.resize_with(u8::MAX as usize, Default::default);
+    fn frame(register_count: Register) -> Vec<VMValue<'a>> {
+        let mut frame = Vec::new();
+        frame.resize_with(register_count as usize, Default::default);
 
-        s
+        frame
     }
 
-    pub fn regs_mut(&mut self) -> &mut Vec<VMValue<'a>> {
-        &mut self.registers
+    // arguments are moved out of the caller into r1 onwards, r0 is never used,
+    // without the typechecker there can be more arguments than parameters
+    pub fn push_frame(&mut self, register_count: Register, arguments: Range<Register>) {
+        let mut frame = Self::frame(register_count);
+        let caller = self.current_mut();
+        for (parameter, register) in frame.iter_mut().skip(1).zip(arguments) {
+            *parameter = std::mem::take(&mut caller[register as usize]);
+        }
+
+        self.frames.push(frame);
     }
 
-    pub fn regs(&self) -> &Vec<VMValue<'a>> {
-        &self.registers
+    pub fn pop_frame(&mut self) {
+        self.frames.pop();
     }
 
-    pub fn base_register(&self) -> usize {
-        *self.base_register.borrow_mut()
+    pub fn current(&self) -> &[VMValue<'a>] {
+        self.frames.last().unwrap()
     }
 
-    pub fn update_base_register(&self, new_base: usize) {
-        self.base_register.replace(new_base);
+    // the top level code's window, whichever function is running
+    pub fn global(&self) -> &[VMValue<'a>] {
+        self.frames.first().unwrap()
+    }
+
+    pub fn global_mut(&mut self) -> &mut [VMValue<'a>] {
+        self.frames.first_mut().unwrap()
     }
-}
 
-impl Default for Registers<'_> {
-    fn default() -> Self {
-        Self::new()
+    fn current_mut(&mut self) -> &mut [VMValue<'a>] {
+        self.frames.last_mut().unwrap()
     }
 }
 
 impl std::fmt::Debug for Registers<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.registers)
+        write!(f, "{:?}", self.current())
     }
 }
 
@@ -57,8 +67,7 @@ impl<'a> Index<Register> for Registers<'a> {
     type Output = VMValue<'a>;
 
     fn index(&self, index: Register) -> &Self::Output {
-        let base_register = self.base_register();
-        &self.registers[base_register + index as usize]
+        &self.current()[index as usize]
     }
 }
 
@@ -66,15 +75,18 @@ impl<'a> Index<Range<Register>> for Registers<'a> {
     type Output = [VMValue<'a>];
 
     fn index(&self, index: Range<Register>) -> &Self::Output {
-        let base_register = self.base_register();
-        &self.registers
-            [(base_register + index.start as usize)..(base_register + index.end as usize)]
+        &self.current()[index.start as usize..index.end as usize]
+    }
+}
+
+impl IndexMut<Range<Register>> for Registers<'_> {
+    fn index_mut(&mut self, index: Range<Register>) -> &mut Self::Output {
+        &mut self.current_mut()[index.start as usize..index.end as usize]
     }
 }
 
 impl IndexMut<Register> for Registers<'_> {
     fn index_mut(&mut self, index: Register) -> &mut Self::Output {
-        let base_register = self.base_register();
-        &mut self.registers[base_register + index as usize]
+        &mut self.current_mut()[index as usize]
     }
 }
@@ -2,7 +2,7 @@ use crate::{
     compiler,
     types::{self, Array, Literal, Object, ObjectValue},
 };
-use std::{borrow::Cow, cell::RefCell, cmp::Ordering, rc::Rc};
+use std::{borrow::Cow, cell::RefCell, cmp::Ordering, collections::HashSet, rc::Rc};
 
 // we reference count all objects :)
 pub type VMObject = Rc<RefCell<Object>>;
@@ -10,17 +10,89 @@ pub type VMArray = Rc<RefCell<Array>>;
 pub type VMObjectValue = Rc<RefCell<ObjectValue>>;
 pub type VMFunction = Rc<compiler::Function>;
 
+// pairs of containers currently being compared, seeing one again means a cycle
+type ComparisonPath = HashSet<(*const (), *const ())>;
+
 // FIXME: is this too big?
 #[derive(Default, Debug, Clone)]
 pub enum VMValue<'a> {
+    // never written to, as opposed to holding nil
     #[default]
     Empty,
+    Nil,
     Literal(Cow<'a, types::Literal>),
     Object(VMObject),
     Array(VMArray),
     Function(VMFunction),
 }
 
+impl std::fmt::Display for VMValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VMValue::Empty | VMValue::Nil => write!(f, "nil"),
+            VMValue::Literal(literal) => write!(f, "{}", literal.as_ref()),
+            VMValue::Function(function) => write!(f, "<function: {}>", function.name),
+            VMValue::Object(object) => write!(f, "{}", object.borrow()),
+            VMValue::Array(array) => write!(f, "{}", array.borrow()),
+        }
+    }
+}
+
+impl VMValue<'_> {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            VMValue::Empty | VMValue::Nil => "nil",
+            VMValue::Literal(literal) => match literal.as_ref() {
+                Literal::String(_) => "string",
+                Literal::Float(_) => "float",
+                Literal::Integer(_) => "int",
+                Literal::Boolean(_) => "bool",
+                Literal::Nil => "nil",
+            },
+            VMValue::Object(_) => "object",
+            VMValue::Array(_) => "array",
+            VMValue::Function(_) => "function",
+        }
+    }
+}
+
+impl VMValue<'_> {
+    // how the value is stored inside an array or object
+    pub fn to_object_value(&self) -> ObjectValue {
+        match self {
+            VMValue::Literal(lit) => ObjectValue::Literal(lit.as_ref().clone()),
+            VMValue::Object(object) => ObjectValue::Object(object.clone()),
+            VMValue::Function(f) => ObjectValue::Function(f.clone()),
+            VMValue::Array(array) => ObjectValue::Array(array.clone()),
+            VMValue::Empty | VMValue::Nil => ObjectValue::Nil,
+        }
+    }
+
+    // elements of an array or chars of a string, None for anything else
+    pub fn length(&self) -> Option<usize> {
+        match self {
+            VMValue::Array(array) => Some(array.borrow().len()),
+            VMValue::Literal(literal) => match literal.as_ref() {
+                Literal::String(s) => Some(s.chars().count()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl From<&ObjectValue> for VMValue<'_> {
+    fn from(value: &ObjectValue) -> Self {
+        match value {
+            ObjectValue::Object(rc) => VMValue::Object(rc.clone()),
+            ObjectValue::Literal(literal) => VMValue::Literal(Cow::Owned(literal.clone())),
+            ObjectValue::Function(func) => VMValue::Function(func.clone()),
+            ObjectValue::Array(rc) => VMValue::Array(rc.clone()),
+            ObjectValue::Nil => VMValue::Nil,
+        }
+    }
+}
+
 impl PartialEq for VMValue<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.partial_cmp(other) == Some(Ordering::Equal)
@@ -31,6 +103,12 @@ impl PartialOrd for VMValue<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
             (VMValue::Empty, VMValue::Empty) => Some(Ordering::Equal),
+            (VMValue::Nil, VMValue::Nil) => Some(Ordering::Equal),
+            (VMValue::Nil, VMValue::Literal(l)) | (VMValue::Literal(l), VMValue::Nil)
+                if matches!(l.as_ref(), Literal::Nil) =>
+            {
+                Some(Ordering::Equal)
+            }
             (VMValue::Literal(l1), VMValue::Literal(l2)) => match (l1.as_ref(), l2.as_ref()) {
                 (Literal::String(l1), Literal::String(l2)) => l1.partial_cmp(l2),
                 (Literal::Float(l1), Literal::Float(l2)) => l1.partial_cmp(l2),
@@ -39,8 +117,81 @@ impl PartialOrd for VMValue<'_> {
 
                 _ => None,
             },
+            (VMValue::Object(o1), VMValue::Object(o2)) => {
+                compare_objects(o1, o2, &mut ComparisonPath::new())
+            }
+            (VMValue::Array(a1), VMValue::Array(a2)) => {
+                compare_arrays(a1, a2, &mut ComparisonPath::new())
+            }
+            (VMValue::Function(f1), VMValue::Function(f2)) => (f1 == f2).then_some(Ordering::Equal),
 
             _ => None,
         }
     }
 }
+
+// objects and arrays have no ordering, so anything but equal is incomparable
+fn compare_objects(o1: &VMObject, o2: &VMObject, path: &mut ComparisonPath) -> Option<Ordering> {
+    if Rc::ptr_eq(o1, o2) {
+        return Some(Ordering::Equal);
+    }
+
+    let pair = (Rc::as_ptr(o1) as *const (), Rc::as_ptr(o2) as *const ());
+    if !path.insert(pair) {
+        return None;
+    }
+
+    let (o1, o2) = (o1.borrow(), o2.borrow());
+    let equal = o1.fields().len() == o2.fields().len()
+        && o1.fields().iter().all(|(name, v1)| {
+            o2.fields()
+                .get(name)
+                .is_some_and(|v2| compare_object_values(&v1.borrow(), &v2.borrow(), path).is_some())
+        });
+
+    path.remove(&pair);
+    equal.then_some(Ordering::Equal)
+}
+
+fn compare_arrays(a1: &VMArray, a2: &VMArray, path: &mut ComparisonPath) -> Option<Ordering> {
+    if Rc::ptr_eq(a1, a2) {
+        return Some(Ordering::Equal);
+    }
+
+    let pair = (Rc::as_ptr(a1) as *const (), Rc::as_ptr(a2) as *const ());
+    if !path.insert(pair) {
+        return None;
+    }
+
+    let (a1, a2) = (a1.borrow(), a2.borrow());
+    let equal = a1.len() == a2.len()
+        && (0..a1.len()).all(|i| match (a1.index(i), a2.index(i)) {
+            (Some(v1), Some(v2)) => {
+                let ordering = compare_object_values(&v1.borrow(), &v2.borrow(), path);
+                ordering.is_some()
+            }
+            _ => false,
+        });
+
+    path.remove(&pair);
+    equal.then_some(Ordering::Equal)
+}
+
+fn compare_object_values(
+    v1: &ObjectValue,
+    v2: &ObjectValue,
+    path: &mut ComparisonPath,
+) -> Option<Ordering> {
+    let equal = match (v1, v2) {
+        (ObjectValue::Nil, ObjectValue::Nil) => true,
+        (ObjectValue::Literal(l1), ObjectValue::Literal(l2)) => l1 == l2,
+        (ObjectValue::Function(f1), ObjectValue::Function(f2)) => f1 == f2,
+        (ObjectValue::Object(o1), ObjectValue::Object(o2)) => {
+            return compare_objects(o1, o2, path);
+        }
+        (ObjectValue::Array(a1), ObjectValue::Array(a2)) => return compare_arrays(a1, a2, path),
+        _ => false,
+    };
+
+    equal.then_some(Ordering::Equal)
+}
@@ -0,0 +1,171 @@
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use sol::{
+    bytecode::{decode, encode, BytecodeError},
+    compiler::{CompiledProgram, Compiler, Function},
+    instructions::{CastTarget, Instruction},
+    lexer::Lexer,
+    parser::Parser,
+};
+use std::path::PathBuf;
+
+#[rstest]
+fn round_trip(#[files("tests/files/success/*.sol")] path: PathBuf) {
+    let input = std::fs::read_to_string(path).unwrap();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let bytes = program.to_bytes();
+
+    assert_eq!(CompiledProgram::from_bytes(&bytes), Ok(program));
+}
+
+#[test]
+fn instruction_round_trip() {
+    // operands chosen so a swapped byte would show up
+    let instructions = [
+        Instruction::Copy { dest: 1, src: 2 },
+        Instruction::Copy {
+            dest: 258,
+            src: 65535,
+        },
+        Instruction::LoadFunction { dest: 1, src: 513 },
+        Instruction::LoadLiteral { dest: 1, src: 1027 },
+        Instruction::LoadNil { dest: 7 },
+        Instruction::CallFunction {
+            src: 1,
+            arg_count: 2,
+            return_val: 3,
+        },
+        Instruction::SetArrayIndex {
+            array: 1,
+            index: 2,
+            value: 3,
+        },
+        Instruction::Cast {
+            dest: 1,
+            src: 2,
+            target_type: CastTarget::Bool,
+        },
+        Instruction::JumpIfFalse {
+            src: 4,
+            offset: 300,
+        },
+        Instruction::JumpIfTrue { src: 4, offset: 5 },
+        Instruction::Jump { offset: -300 },
+        Instruction::Jump { offset: 70000 },
+        Instruction::Jump {
+            offset: i32::MIN + 2,
+        },
+        Instruction::Shr {
+            dest: 1,
+            lhs: 2,
+            rhs: 3,
+        },
+        Instruction::SliceArray {
+            dest: 1,
+            array: 2,
+            start: 3,
+        },
+        Instruction::IntToFloat { dest: 1, src: 2 },
+        Instruction::FloatToInt { dest: 2, src: 1 },
+        Instruction::SpreadObject { object: 3, src: 4 },
+        Instruction::Return { val: 9 },
+        Instruction::FunctionReturn,
+    ];
+
+    for instruction in instructions {
+        assert_eq!(decode(encode(&instruction)), Ok(instruction));
+    }
+}
+
+#[test]
+fn invalid_bytes() {
+    assert_eq!(
+        CompiledProgram::from_bytes(b"NOPE"),
+        Err(BytecodeError::InvalidMagic)
+    );
+
+    let bytes = CompiledProgram::default().to_bytes();
+    assert_eq!(
+        CompiledProgram::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(BytecodeError::UnexpectedEof)
+    );
+
+    assert_eq!(
+        decode([255, 0, 0, 0, 0, 0, 0]),
+        Err(BytecodeError::InvalidOpcode { opcode: 255 })
+    );
+
+    // arg counts are still a single byte
+    assert_eq!(
+        decode([3, 1, 0, 0, 1, 2, 0]),
+        Err(BytecodeError::OperandOutOfRange { operand: 256 })
+    );
+}
+
+#[test]
+fn operands_out_of_range() {
+    let program_with = |code: Vec<Instruction>| CompiledProgram {
+        functions: vec![Function {
+            name: "f".to_owned(),
+            code,
+            register_count: 2,
+        }],
+        global_register_count: 2,
+        ..Default::default()
+    };
+
+    let cases = [
+        (
+            Instruction::LoadLiteral { dest: 1, src: 0 },
+            BytecodeError::InvalidLiteral { literal: 0 },
+        ),
+        (
+            Instruction::LoadFunction { dest: 1, src: 1 },
+            BytecodeError::InvalidFunction { function: 1 },
+        ),
+        (
+            Instruction::Copy { dest: 1, src: 2 },
+            BytecodeError::InvalidRegister { register: 2 },
+        ),
+        (
+            Instruction::CallFunction {
+                src: 1,
+                arg_count: 2,
+                return_val: 1,
+            },
+            BytecodeError::InvalidRegister { register: 1 },
+        ),
+        (
+            Instruction::SliceArray {
+                dest: 0,
+                array: 0,
+                start: 1,
+            },
+            BytecodeError::InvalidRegister { register: 2 },
+        ),
+        (
+            Instruction::Jump { offset: 2 },
+            BytecodeError::InvalidJump { ip: 0, offset: 2 },
+        ),
+        (
+            Instruction::Jump { offset: -1 },
+            BytecodeError::InvalidJump { ip: 0, offset: -1 },
+        ),
+    ];
+
+    for (instruction, error) in cases {
+        let bytes = program_with(vec![instruction]).to_bytes();
+        assert_eq!(CompiledProgram::from_bytes(&bytes), Err(error));
+    }
+
+    // jumping to just past the end finishes the function
+    let bytes = program_with(vec![Instruction::Jump { offset: 1 }]).to_bytes();
+    assert!(CompiledProgram::from_bytes(&bytes).is_ok());
+}
@@ -0,0 +1,15 @@
+use std::fmt::Debug;
+
+// debug output with every span blanked out, for comparing asts by shape only
+pub fn without_spans(value: &impl Debug) -> String {
+    let mut debug = format!("{value:#?}");
+
+    let mut from = 0;
+    while let Some(start) = debug[from..].find("Span {").map(|i| from + i) {
+        let end = start + debug[start..].find('}').unwrap() + 1;
+        debug.replace_range(start..end, "Span");
+        from = start + "Span".len();
+    }
+
+    debug
+}
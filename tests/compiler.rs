@@ -1,5 +1,11 @@
 use insta::assert_debug_snapshot;
-use sol::{compiler::Compiler, lexer::Lexer, parser::Parser};
+use sol::{
+    compiler::{Compiler, CompilerError},
+    instructions::Instruction,
+    lexer::Lexer,
+    parser::Parser,
+};
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 #[test]
 fn small_input() {
@@ -41,12 +47,13 @@ main();
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let output = compiler.compile(&statements).unwrap();
 
@@ -67,12 +74,13 @@ x = 2;
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let output = compiler.compile(&statements).unwrap();
 
@@ -93,12 +101,13 @@ let y = -(x + 3);
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let output = compiler.compile(&statements).unwrap();
 
@@ -119,12 +128,13 @@ let y = !x;
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let output = compiler.compile(&statements).unwrap();
 
@@ -150,12 +160,13 @@ if false {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let output = compiler.compile(&statements).unwrap();
 
@@ -218,12 +229,13 @@ if false {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let output = compiler.compile(&statements).unwrap();
 
@@ -263,18 +275,68 @@ loop {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let output = compiler.compile(&statements).unwrap();
 
+    // both `x = x + 1` and `y = y + 1` become an in place increment
+    let increments = output
+        .global_code
+        .iter()
+        .filter(|instruction| matches!(instruction, Instruction::Increment { .. }))
+        .count();
+    assert_eq!(increments, 2);
+
     assert_debug_snapshot!(output);
 }
 
+#[test]
+fn compound_assignment_steps_in_place() {
+    let input = r#"
+let mut i = 0;
+i += 1;
+i -= 1;
+i += 2;
+print(i);
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let output = compiler.compile(&statements).unwrap();
+
+    // `i += 2` is a plain add
+    let steps = output
+        .global_code
+        .iter()
+        .filter(|instruction| {
+            matches!(
+                instruction,
+                Instruction::Increment { .. } | Instruction::Decrement { .. }
+            )
+        })
+        .count();
+    assert_eq!(steps, 2);
+}
+
 #[test]
 fn objects() {
     let input = r#"
@@ -314,14 +376,279 @@ print(x.test5.test6.test7);
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let output = compiler.compile(&statements).unwrap();
+
+    assert_debug_snapshot!(output);
+}
+
+#[test]
+fn short_circuit() {
+    let input = r#"
+let a = true;
+let b = false;
+let c = a || b;
+let d = a && b;
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let output = compiler.compile(&statements).unwrap();
+
+    assert_debug_snapshot!(output);
+}
+
+#[test]
+fn two_arg_call() {
+    let input = r#"
+        fn add(a: int, b: int) {
+            return a + b;
+        }
+
+        let x = add(1, 2 * 3);
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let output = compiler.compile(&statements).unwrap();
+
+    assert_debug_snapshot!(output);
+}
+
+fn compile_loop_of_breaks(count: usize) -> Result<Vec<Instruction>, CompilerError> {
+    let input = format!("loop {{ {} }}", "break; ".repeat(count));
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+
+    compiler
+        .compile(&statements)
+        .map(|program| program.global_code)
+}
+
+#[test]
+fn loop_past_16_bit_jumps() {
+    // the first break has to jump over every other break plus the backwards jump
+    let code = compile_loop_of_breaks(40000).unwrap();
+
+    assert_eq!(code.first(), Some(&Instruction::Jump { offset: 40001 }));
+    assert_eq!(code.last(), Some(&Instruction::Jump { offset: -40000 }));
+}
+
+#[test]
+fn match_literals() {
+    let input = r#"
+        let x = 2;
+        match x {
+            1 => { print("one"); },
+            2 => { print("two"); },
+            _ => { print("other"); },
         }
+        "#
+    .to_owned();
 
-        statements.push(token.unwrap());
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
     }
     let output = compiler.compile(&statements).unwrap();
 
     assert_debug_snapshot!(output);
 }
+
+#[test]
+fn identical_programs_hash_equally() {
+    let hash_of = |input: &str| {
+        let lexer = Lexer::new(0, input);
+        let parser = Parser::new(lexer, input);
+        let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+        let output = Compiler::new().compile(&statements).unwrap();
+
+        let mut hasher = DefaultHasher::new();
+        output.hash(&mut hasher);
+        hasher.finish()
+    };
+
+    let input = r#"
+        fn add(a: int, b: float) -> float {
+            return a + b;
+        }
+        let x = add(1, 2.5);
+        "#;
+
+    assert_eq!(hash_of(input), hash_of(input));
+    assert_ne!(hash_of(input), hash_of(&input.replace("2.5", "3.5")));
+}
+
+#[test]
+fn object_fields_in_source_order() {
+    let input = r#"
+        let config = { zeta: 1, alpha: "two", mid: 3.5, beta: true, omega: nil };
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let output = compiler.compile(&statements).unwrap();
+
+    assert_debug_snapshot!(output);
+}
+
+#[test]
+fn const_expressions_are_folded() {
+    let input = r#"
+        const KIB = 1024;
+        const SIZE = 4 * KIB;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let output = compiler.compile(&statements).unwrap();
+
+    // both are a single LoadLiteral, no Mul at runtime
+    assert_eq!(
+        output.global_code,
+        vec![
+            Instruction::LoadLiteral { dest: 1, src: 0 },
+            Instruction::LoadLiteral { dest: 2, src: 1 },
+        ]
+    );
+    assert_debug_snapshot!(output.literals);
+}
+
+#[test]
+fn object_field_mutation_needs_mut() {
+    let input = r#"
+        let x = { a: 1 };
+        x.a = 2;
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let error = compiler.compile(&statements).unwrap_err();
+
+    assert_debug_snapshot!(error);
+}
+
+#[test]
+fn ints_widen_where_floats_are_written() {
+    let input = r#"
+        fn half(n: float) -> float {
+            return n / 2;
+        }
+
+        let x: float = 1;
+        let y = half(x);
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    assert!(program
+        .global_code
+        .contains(&Instruction::IntToFloat { dest: 2, src: 1 }));
+    // the parameter on the way in and the return value on the way out
+    let function_code = &program.functions[0].code;
+    assert_eq!(
+        function_code[0],
+        Instruction::IntToFloat { dest: 1, src: 1 }
+    );
+    assert_eq!(
+        function_code
+            .iter()
+            .filter(|i| matches!(i, Instruction::IntToFloat { .. }))
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn int_and_float_compile_to_casts() {
+    let input = r#"
+        let a = int(3.7);
+        let b = float(a);
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    assert!(program
+        .global_code
+        .iter()
+        .any(|i| matches!(i, Instruction::FloatToInt { .. })));
+    assert!(program
+        .global_code
+        .iter()
+        .any(|i| matches!(i, Instruction::IntToFloat { .. })));
+    assert!(!program
+        .global_code
+        .iter()
+        .any(|i| matches!(i, Instruction::CallNativeFunction { .. })));
+}
@@ -0,0 +1,77 @@
+use pretty_assertions::assert_eq;
+use sol::{compiler::Compiler, disassembler::disassemble, lexer::Lexer, parser::Parser};
+
+fn disassemble_source(input: &str) -> String {
+    let lexer = Lexer::new(0, input);
+    let parser = Parser::new(lexer, input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    disassemble(&program)
+}
+
+#[test]
+fn function_and_global() {
+    let input = r#"
+fn add(a: int, b: int) -> int {
+    return a + b;
+}
+
+let x = add(40, 2);
+"#;
+
+    let expected = "\
+literals:
+    lit[0]: 40 (int)
+    lit[1]: 2 (int)
+
+fn add (registers: 4)
+    0000  Add r3, r1, r2
+    0001  Return r3
+    0002  FunctionReturn
+
+global (registers: 5)
+    0000  LoadLiteral r1, lit[0] (40)
+    0001  LoadLiteral r2, lit[1] (2)
+    0002  LoadFunction r3, fn[0] (add)
+    0003  CallFunction r3, args: 2, r4
+";
+
+    assert_eq!(disassemble_source(input), expected);
+}
+
+#[test]
+fn jump_targets_and_result() {
+    let input = r#"
+let mut i = 0;
+loop {
+    if i == 3 {
+        break;
+    }
+    i = i + 1;
+}
+"done";
+"#;
+
+    let expected = "\
+literals:
+    lit[0]: 0 (int)
+    lit[1]: 3 (int)
+    lit[2]: \"done\" (string)
+
+global (registers: 4)
+    0000  LoadLiteral r1, lit[0] (0)
+    0001  LoadLiteral r2, lit[1] (3)
+    0002  Equals r3, r1, r2
+    0003  JumpIfFalse r3, +2 (-> 0005)
+    0004  Jump +3 (-> 0007)
+    0005  Increment r1
+    0006  Jump -5 (-> 0001)
+    0007  LoadLiteral r2, lit[2] (\"done\")
+    result: r2
+";
+
+    assert_eq!(disassemble_source(input), expected);
+}
@@ -0,0 +1,2 @@
+// 256 would wrap around to 0 and look like success
+exit(256);
@@ -0,0 +1 @@
+let xs: [int] = ["one", "two"];
@@ -0,0 +1,6 @@
+fn twice(n: int) -> int {
+    return n * 2;
+}
+
+let maybe: int? = 3;
+print(twice(maybe));
@@ -0,0 +1,2 @@
+assert_eq([1, 2], [1, 2]);
+assert_eq([1, 2], [2, 1]);
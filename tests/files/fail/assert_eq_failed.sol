@@ -0,0 +1,3 @@
+assert_eq(2 * 2, 4);
+assert_eq(1 + 1, 3);
+print("unreachable");
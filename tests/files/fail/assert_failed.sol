@@ -0,0 +1,4 @@
+let total = 1 + 2;
+assert(total == 3);
+assert(total == 4, "total should be 4");
+print("unreachable");
@@ -0,0 +1,11 @@
+let mut i = 0;
+loop {
+    i = i + 1;
+    if i == 5 {
+        break i;
+    }
+
+    if i == 10 {
+        break "ten";
+    }
+}
@@ -0,0 +1 @@
+const LENGTH = len("abc") * 2;
@@ -0,0 +1,2 @@
+let mut size = 4;
+const DOUBLE = size * 2;
@@ -1 +0,0 @@
-const test = 2 + 2;
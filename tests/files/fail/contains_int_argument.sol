@@ -0,0 +1 @@
+print(contains("abc", 1));
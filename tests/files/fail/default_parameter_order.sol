@@ -0,0 +1,5 @@
+fn greet(greeting: string = "hello", name: string) {
+    print(greeting, " ", name);
+}
+
+greet("bob");
@@ -0,0 +1,5 @@
+fn greet(name: string, greeting: string = "hello") {
+    print(greeting, " ", name);
+}
+
+greet("bob", "hi", "extra");
@@ -0,0 +1,5 @@
+let point = {
+    x: 1,
+    y: 2,
+    x: 3,
+};
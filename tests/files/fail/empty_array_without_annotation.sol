@@ -0,0 +1,2 @@
+let mut xs = [];
+push(xs, 1);
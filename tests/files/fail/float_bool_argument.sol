@@ -0,0 +1 @@
+let f = float(true);
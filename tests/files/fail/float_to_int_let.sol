@@ -0,0 +1 @@
+let x: int = 1.5;
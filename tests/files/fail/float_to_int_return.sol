@@ -0,0 +1,9 @@
+fn truncate(n: float) -> int {
+    if n > 1.0 {
+        return 1;
+    }
+
+    return n;
+}
+
+truncate(0.5);
@@ -0,0 +1,4 @@
+let x = 5;
+for item in x {
+    print(item);
+}
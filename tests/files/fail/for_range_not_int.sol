@@ -0,0 +1,3 @@
+for i in 0.."ten" {
+    print(i);
+}
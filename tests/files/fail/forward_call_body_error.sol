@@ -0,0 +1,5 @@
+print(double(2));
+
+fn double(n: int) {
+    return n * "two";
+}
@@ -0,0 +1 @@
+let xs = [1, "two", true];
@@ -0,0 +1 @@
+let x = if true { 1 };
@@ -0,0 +1,7 @@
+let mut x = 1;
+{
+    let x = 2;
+    x = 3;
+    print(x);
+}
+print(x);
@@ -0,0 +1,2 @@
+let line = input();
+print(len(line));
@@ -0,0 +1 @@
+let n = int("12");
@@ -0,0 +1,3 @@
+let let = 3;
+fn match(x: int) {}
+print("unreachable");
@@ -0,0 +1 @@
+let n = len(true);
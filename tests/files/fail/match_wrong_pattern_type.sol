@@ -0,0 +1,7 @@
+let x = 5;
+match x {
+    "five" => {
+        print(x);
+    }
+    _ => {}
+}
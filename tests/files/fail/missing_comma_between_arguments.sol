@@ -0,0 +1,5 @@
+fn add(a: int, b: int) -> int {
+    return a + b;
+}
+
+print(add(1 2));
@@ -0,0 +1,3 @@
+fn add(a: int b: int) -> int {
+    return a + b;
+}
@@ -0,0 +1,2 @@
+let o = { a: 1 };
+print(o.b.c);
@@ -0,0 +1,2 @@
+// 🎉 multibyte characters before an error
+let = 3;
@@ -0,0 +1,6 @@
+let x = ;
+let y = 2;
+let = 3;
+print(y);
+let z 4;
+print(x);
@@ -0,0 +1,17 @@
+fn is_even(n: int) {
+    if n == 0 {
+        return true;
+    }
+
+    return is_odd(n - 1);
+}
+
+fn is_odd(n: int) {
+    if n == 0 {
+        return false;
+    }
+
+    return is_even(n - 1);
+}
+
+print(is_even(10));
@@ -0,0 +1,3 @@
+let mut inner = { b: 1 };
+let outer = { inner: inner };
+outer.inner.b = 2;
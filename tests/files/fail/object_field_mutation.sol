@@ -0,0 +1,2 @@
+let x = { a: 1 };
+x.a = 2;
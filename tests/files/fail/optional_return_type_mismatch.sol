@@ -0,0 +1,9 @@
+fn find(n: int) -> int? {
+    if n > 0 {
+        return n;
+    }
+
+    return "missing";
+}
+
+find(3);
@@ -0,0 +1,12 @@
+fn half(n: int) -> int? {
+    if n % 2 == 1 {
+        return nil;
+    }
+
+    return n / 2;
+}
+
+fn main() {
+    let h = half(4);
+    print(h + 1);
+}
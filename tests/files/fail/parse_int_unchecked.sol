@@ -0,0 +1,2 @@
+let n = parse_int("1");
+print(n + 1);
@@ -0,0 +1,2 @@
+let xs = [1];
+pop(xs, xs);
@@ -0,0 +1,9 @@
+fn countdown(n: int) {
+    if n == 0 {
+        return 0;
+    }
+
+    return countdown(n - 1);
+}
+
+print(countdown(3));
@@ -0,0 +1,5 @@
+fn half(n: float) -> int {
+    return n / 2.0;
+}
+
+half(3.0);
@@ -0,0 +1,9 @@
+fn describe(n: int) {
+    if n > 0 {
+        return "positive";
+    }
+
+    return 0;
+}
+
+describe(3);
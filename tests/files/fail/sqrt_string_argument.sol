@@ -0,0 +1 @@
+let root = sqrt("16");
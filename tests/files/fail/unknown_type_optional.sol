@@ -0,0 +1 @@
+let x: boool? = nil;
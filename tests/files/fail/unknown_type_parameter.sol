@@ -0,0 +1,5 @@
+fn double(n: itn) -> int {
+    return n * 2;
+}
+
+double(2);
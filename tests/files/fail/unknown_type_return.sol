@@ -0,0 +1,5 @@
+fn half(n: int) -> flaot {
+    return n / 2;
+}
+
+half(2);
@@ -0,0 +1,5 @@
+fn join(separator: string, parts: string...) -> string {
+    return separator;
+}
+
+join();
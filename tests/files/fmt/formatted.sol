@@ -0,0 +1,6 @@
+fn main() {
+    let x = 1 + 2;
+    print(x);
+}
+
+main();
@@ -0,0 +1,7 @@
+print("before");
+
+if 1 {
+    print("taken");
+}
+
+print("after");
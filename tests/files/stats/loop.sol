@@ -0,0 +1,16 @@
+fn square(x: int) -> int {
+    return x * x;
+}
+
+let mut i = 0;
+let mut total = 0;
+loop {
+    if i >= 10 {
+        break;
+    }
+
+    total = total + square(i);
+    i = i + 1;
+}
+
+print(total);
@@ -0,0 +1,22 @@
+let ints = [1, 2, 3];
+let words: [string] = ["a", "b"];
+let floats: [float] = [1.5, 2.5];
+print(ints);
+print(words);
+print(floats);
+let mut seen: [int] = [];
+push(seen, 4);
+push(seen, 5);
+print(seen);
+seen = [];
+print(len(seen));
+
+fn total(xs: [int]) -> int {
+    let mut sum = 0;
+    for x in xs {
+        sum = sum + x;
+    }
+    return sum;
+}
+
+print(total(ints));
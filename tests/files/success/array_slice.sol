@@ -0,0 +1,7 @@
+let xs = [1, 2, 3, 4, 5];
+let middle = xs[1..4];
+print(middle);
+
+let end = len(xs);
+print(xs[end - 2..end]);
+print(xs[3..1]);
@@ -0,0 +1,9 @@
+let total = 1 + 2;
+assert(total == 3);
+assert(total > 0, "total should be positive");
+assert_eq(total, 3);
+assert_eq("a" + "b", "ab");
+assert_eq({ x: 1 }, { x: 1 });
+assert_eq([1, 2, 3], [1, 2, 3]);
+assert_eq({ xs: ["a"] }, { xs: ["a"] });
+print("all assertions passed");
@@ -0,0 +1,17 @@
+fn greet(name: string) {
+    print("hi " + name)
+}
+
+fn double(x: int) -> int {
+    x * 2
+}
+
+let mut i = 0;
+loop {
+    i = i + 1;
+    if i > 2 { break }
+}
+if i == 3 { print("three") } else { print("not three") }
+greet("sol");
+print(double(4));
+{ let y = 1; print(y) }
@@ -0,0 +1,23 @@
+let mut x = 1;
+{
+    let x = "inner";
+    print(x);
+}
+
+x = x + 1;
+print(x);
+
+let mut y = x;
+y = y + 10;
+print("x: ", x, " y: ", y);
+
+let z = x;
+x = 100;
+print("x: ", x, " z: ", z);
+
+if x > 1 {
+    let x = 2.5;
+    print(x);
+}
+
+print(x);
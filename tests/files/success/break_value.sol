@@ -0,0 +1,17 @@
+let mut i = 0;
+loop {
+    i = i + 1;
+    if i > 2 {
+        break;
+    }
+}
+
+print(i);
+
+let mut j = 0;
+loop {
+    j = j + 2;
+    if j >= 8 {
+        break j + 1;
+    }
+}
@@ -0,0 +1,13 @@
+fn describe(n: int) -> string {
+    return label() + ": " + to_string(double(n));
+}
+
+fn double(n: int) {
+    return n * 2;
+}
+
+fn label() {
+    return "double";
+}
+
+print(describe(21));
@@ -0,0 +1,4 @@
+let x = 7;
+print(x as float / 2.0);
+print("x is " + x as string);
+print(0.0 as bool);
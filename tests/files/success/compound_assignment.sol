@@ -0,0 +1,17 @@
+let mut total = 10;
+total += 5;
+total -= 2 + 1;
+print(total);
+
+let mut scale = 1.5;
+scale += 1.0;
+print(scale);
+
+let mut i = 0;
+loop {
+    if i >= 3 {
+        break;
+    }
+    i += 1;
+}
+print(i);
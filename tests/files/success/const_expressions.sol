@@ -0,0 +1,10 @@
+const KIB = 1024;
+const SIZE = 4 * KIB;
+const HALF: float = SIZE / 2 + 0.5;
+const NEGATIVE = -SIZE + 1;
+const GREETING = "hello" + " " + "world";
+
+print(SIZE);
+print(HALF);
+print(NEGATIVE);
+print(GREETING);
@@ -0,0 +1,11 @@
+fn log(level: string, prefix: string = "app", parts: ...string) {
+    let mut line = "[" + level + "] " + prefix + ":";
+    for part in parts {
+        line += " " + part;
+    }
+    print(line);
+}
+
+log("info");
+log("warn", "db");
+log("error", "net", "timed", "out");
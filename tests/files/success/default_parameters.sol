@@ -0,0 +1,16 @@
+fn greet(name: string, greeting: string = "hello", times: int = 1) {
+    for i in 0..times {
+        print(greeting, " ", name);
+    }
+}
+
+greet("bob");
+greet("alice", "hi");
+greet("eve", "hey", 2);
+
+fn offset(n: int, by: int = -1) -> int {
+    return n + by;
+}
+
+print(offset(10));
+print(offset(10, 5));
@@ -0,0 +1,12 @@
+fn check(x: int) {
+    if x > 2 {
+        print("exiting");
+        exit(x);
+    }
+
+    print("not yet");
+}
+
+check(1);
+check(3);
+print("unreachable");
@@ -0,0 +1,43 @@
+let xs = [1, 2, 3, 4, 5];
+let mut total = 0;
+for x in xs {
+    total = total + x;
+}
+print(total);
+
+for i in 0..5 {
+    print(i);
+}
+
+let start = 2;
+for i in start..len(xs) {
+    if i == 3 {
+        continue;
+    }
+
+    print(xs[i]);
+}
+
+let mut odd = 0;
+for x in xs {
+    if x % 2 == 0 {
+        continue;
+    }
+
+    if x > 4 {
+        break;
+    }
+
+    odd = odd + x;
+}
+print(odd);
+
+for i in 0..3 {
+    for j in 0..3 {
+        if j > i {
+            break;
+        }
+
+        print(i * 10 + j);
+    }
+}
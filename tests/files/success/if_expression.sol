@@ -0,0 +1,16 @@
+fn grade(score: int) -> string {
+    return if score > 90 { "A" } else if score > 75 { "B" } else { "C" };
+}
+
+let score = 95;
+let letter = if score > 90 { "A" } else { "B" };
+print(letter);
+
+print(grade(80));
+print(grade(10));
+
+let nested = if score > 50 { if score > 99 { 2 } else { 1 } } else { 0 };
+print(nested);
+
+print(if letter == "A" { "top" } else { "rest" }, if false { 1 } else { 2 } + 3);
+(if true { 1 } else { 2 } + 3);
@@ -0,0 +1,36 @@
+fn add(a: int, b: int) -> int {
+    a + b
+}
+
+fn pick(flag: bool) -> int {
+    if flag {
+        return 1;
+    }
+    (2 * 3)
+}
+
+fn version() -> int {
+    let config = { version: 3 };
+    config.version
+}
+
+fn lit() -> string {
+    "hi"
+}
+
+fn cast_tail(x: int) -> float {
+    x as float
+}
+
+fn nested(x: int) -> int {
+    let y = if x > 1 { 10 } else { 20 };
+    y + 1
+}
+
+print(add(1, 2));
+print(pick(false));
+print(pick(true));
+print(version());
+print(lit());
+print(cast_tail(2) / 4.0);
+print(nested(5));
@@ -0,0 +1,6 @@
+let name = input("name? ");
+if is_nil(name) {
+    print("nobody there");
+} else {
+    print("hello ", name);
+}
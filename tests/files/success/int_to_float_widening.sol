@@ -0,0 +1,39 @@
+// an int goes anywhere a float is written down
+let x: float = 3;
+print(x / 2);
+
+let maybe: float? = 4;
+print(maybe);
+
+const SCALE: float = 10;
+print(SCALE / 4);
+
+fn half(n: float) -> float {
+    return n / 2;
+}
+
+print(half(5));
+
+fn scaled(n: float, by: float = 2) {
+    return n * by;
+}
+
+print(scaled(3));
+
+fn clamp(n: float) -> float {
+    if n < 0.0 {
+        return 0;
+    }
+
+    return n;
+}
+
+print(clamp(-5));
+print(clamp(1.5));
+
+fn whole() -> float {
+    return 7;
+}
+
+print(whole() / 2);
+print(type_of(whole()));
@@ -0,0 +1,13 @@
+let xs = [10, 20, 30, 40];
+print(len(xs));
+print(len("hello"));
+
+let mut i = 0;
+loop {
+    if i >= len(xs) {
+        break;
+    }
+
+    print(xs[i]);
+    i = i + 1;
+}
@@ -0,0 +1,21 @@
+let x = 1;
+let mut x = x + 1;
+x = x * 10;
+print(x);
+
+fn count(to: int) -> int {
+    let mut total = 0;
+    let mut i = 0;
+    loop {
+        if i >= to {
+            break;
+        }
+
+        total = total + i;
+        i = i + 1;
+    }
+
+    return total;
+}
+
+print(count(5));
@@ -0,0 +1,14 @@
+let a = 1;
+let b = 2;
+let c = a + b;
+let mut i = 0;
+loop {
+    if i > 3 {
+        break;
+    }
+
+    i = i + 1;
+}
+
+print(i);
+print(c);
@@ -0,0 +1,16 @@
+let mut i = 0;
+let mut skipped = 0;
+loop {
+    i = i + 1;
+    if i > 6 {
+        break;
+    }
+
+    if i % 3 == 0 {
+        skipped = skipped + 1;
+        continue;
+    }
+
+    print(i);
+}
+print(skipped);
@@ -0,0 +1,34 @@
+fn describe(n: int) -> string {
+    let mut result = "";
+    match n {
+        0 => {
+            result = "zero";
+        }
+        1 => {
+            result = "one";
+        },
+        -1 => {
+            result = "minus one";
+        }
+        _ => {
+            result = "many";
+        }
+    }
+
+    return result;
+}
+
+print(describe(0));
+print(describe(1));
+print(describe(-1));
+print(describe(42));
+
+let flag = 3 > 2;
+match flag {
+    true => {
+        print("yes");
+    }
+    false => {
+        print("no");
+    }
+}
@@ -0,0 +1,14 @@
+let commands = ["start", "stop", "jump"];
+for command in commands {
+    match command {
+        "start" => {
+            print("starting");
+        }
+        "stop" => {
+            print("stopping");
+        }
+        _ => {
+            print("unknown " + command);
+        }
+    }
+}
@@ -0,0 +1,9 @@
+let distance: float = sqrt(pow(3, 2) + pow(4, 2));
+print(distance);
+
+let steps: int = abs(-7) + 1;
+print(steps);
+
+print(floor(-1.5));
+print(ceil(-1.5));
+print(abs(-0.25));
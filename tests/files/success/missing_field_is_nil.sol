@@ -0,0 +1,6 @@
+let o = { a: 1 };
+if o.b == nil {
+    print("o has no b");
+}
+print(o.b);
+print(o.a != nil);
@@ -0,0 +1,19 @@
+fn is_even(n: int) -> bool {
+    if n == 0 {
+        return true;
+    }
+
+    return is_odd(n - 1);
+}
+
+fn is_odd(n: int) -> bool {
+    if n == 0 {
+        return false;
+    }
+
+    return is_even(n - 1);
+}
+
+print(is_even(10));
+print(is_odd(7));
+print(is_odd(4));
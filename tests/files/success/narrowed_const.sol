@@ -0,0 +1,6 @@
+const LIMIT: int? = 3;
+
+if LIMIT != nil {
+    const DOUBLE = LIMIT * 2;
+    print(DOUBLE);
+}
@@ -0,0 +1,4 @@
+let x = nil;
+print(is_nil(x));
+print(x);
+print(is_nil(1));
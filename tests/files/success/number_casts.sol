@@ -0,0 +1,8 @@
+let price = 19.99;
+let whole = int(price);
+print(whole);
+print(int(-3.7));
+
+let count = 7;
+print(float(count) / 2.0);
+print(int(float(count)) == count);
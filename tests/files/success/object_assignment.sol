@@ -1,4 +1,4 @@
-let x = {
+let mut x = {
     y: 1
 };
 
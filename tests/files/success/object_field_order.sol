@@ -0,0 +1,5 @@
+let mut config = { zeta: 1, alpha: "two", mid: 3, beta: true, omega: "last" };
+print(config);
+
+config.alpha = "changed";
+print(config);
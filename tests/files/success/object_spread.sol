@@ -0,0 +1,13 @@
+let defaults = { retries: 3, verbose: false, name: "job" };
+let config = { ...defaults, verbose: true };
+print(config);
+
+// a spread after a field wins
+let forced = { retries: 10, ...defaults };
+print(forced.retries);
+
+// fields are copied, the original is left alone
+let mut copy = { ...defaults };
+copy.retries = 5;
+print(defaults.retries);
+print(copy.retries);
@@ -0,0 +1,31 @@
+fn half(n: int) -> int? {
+    if n % 2 == 1 {
+        return nil;
+    }
+
+    return n / 2;
+}
+
+fn half_or_zero(n: int) -> int {
+    let h = half(n);
+    if h == nil {
+        return 0;
+    }
+
+    return h;
+}
+
+let h = half(4);
+if h != nil {
+    print(h + 1);
+}
+
+let o = half(3);
+if o == nil {
+    print("odd");
+} else {
+    print(o * 2);
+}
+
+print(half_or_zero(10));
+print(half_or_zero(7));
@@ -0,0 +1,11 @@
+let count = parse_int("12");
+if count != nil {
+    print(count + 1);
+}
+
+print(is_nil(parse_int("twelve")));
+
+let half = parse_float("0.5");
+if half != nil {
+    print(half * 2.0);
+}
@@ -0,0 +1,8 @@
+let xs = [1, 2];
+push(xs, 3);
+print(xs);
+print(len(xs));
+
+let last = pop(xs);
+print(last);
+print(xs);
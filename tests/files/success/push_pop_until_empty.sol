@@ -0,0 +1,23 @@
+let xs = [1];
+
+let mut i = 2;
+loop {
+    if i > 4 {
+        break;
+    }
+
+    push(xs, i);
+    print(len(xs));
+    i = i + 1;
+}
+
+loop {
+    if len(xs) == 0 {
+        break;
+    }
+
+    print(pop(xs));
+}
+
+print(xs);
+print(pop(xs));
@@ -0,0 +1,18 @@
+seed(42);
+print(random_int(1, 6));
+print(random_int(1, 6));
+print(random_int(1, 6));
+print(random_int(-100, 100));
+
+seed(1337);
+let a = random_int(0, 1000);
+let b = random_int(0, 1000);
+let f = random();
+
+seed(1337);
+print(a == random_int(0, 1000));
+print(b == random_int(0, 1000));
+print(f == random());
+
+print(f >= 0.0);
+print(f < 1.0);
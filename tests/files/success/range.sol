@@ -0,0 +1,22 @@
+print(range(3));
+print(range(1, 4));
+print(range(0, 10, 2));
+print(range(10, 0, -3));
+print(range(0));
+print(range(4, 4, -1));
+print(is_nil(range(0, 10, 0)));
+print(is_nil(range(0, 10, -1)));
+print(is_nil(range(5, 1)));
+let mut total = 0;
+let counting = range(1, 5);
+if counting != nil {
+    for i in counting {
+        total = total + i;
+    }
+}
+print(total);
+
+let hundred = range(100);
+if hundred != nil {
+    print(len(hundred));
+}
@@ -0,0 +1,7 @@
+print(replace("hello world", "world", "Rust"));
+print(replace_n("aaa", "a", "b", 2));
+
+// every match is replaced, or none at all
+print(replace("a-b-c", "-", ", "));
+print(replace("abc", "x", "y"));
+print(replace_n("aaa", "a", "b", 0));
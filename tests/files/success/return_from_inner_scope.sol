@@ -0,0 +1,12 @@
+fn pick(c: bool) -> int {
+    if c {
+        let y = 1;
+        return y;
+    }
+
+    let z = 2;
+    return z;
+}
+
+print(pick(true));
+print(pick(false));
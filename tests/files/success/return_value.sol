@@ -1,4 +1,4 @@
-fn test(x: bool) {
+fn test(x: string) {
     print(x);
     return true;
 }
@@ -0,0 +1,29 @@
+fn side_effect(value: bool) -> bool {
+    print("side effect");
+    return value;
+}
+
+if true || side_effect(true) {
+    print("or short circuited");
+}
+
+if false || side_effect(true) {
+    print("or evaluated rhs");
+}
+
+if false && side_effect(true) {
+    print("unreachable");
+} else {
+    print("and short circuited");
+}
+
+if true && side_effect(false) {
+    print("unreachable");
+} else {
+    print("and evaluated rhs");
+}
+
+let x = 3;
+if x > 1 && x < 5 || x == 10 {
+    print("compound");
+}
@@ -0,0 +1,15 @@
+let words: [string] = split("the quick brown fox", " ");
+print(len(words));
+
+for word in words {
+    print(word);
+}
+
+let mut total = 0;
+for n in split("1,2,3", ",") {
+    let parsed = parse_int(n);
+    if parsed != nil {
+        total = total + parsed;
+    }
+}
+print(total);
@@ -0,0 +1,6 @@
+let greeting = "hello";
+let name = "world";
+print(greeting + ", " + name);
+
+let full = greeting + name;
+print(full);
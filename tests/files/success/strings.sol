@@ -0,0 +1,11 @@
+let name = trim("   Sol   ");
+print(to_uppercase(name));
+print(to_lowercase(name));
+
+let path = "src/stdlib/strings.rs";
+if starts_with(path, "src/") && ends_with(path, ".rs") {
+    print("rust source");
+}
+
+print(contains("crème brûlée", "brû"));
+print(contains(path, "vm"));
@@ -0,0 +1,11 @@
+print(to_string(1));
+print(to_string(2.5));
+print(to_string(true));
+print(to_string("text"));
+print(to_string(nil));
+print(to_string([1, 2, 3]));
+print(to_string(["a", "b"]));
+print(to_string({ name: "sol", version: 1, tags: ["fast"], empty: {} }));
+
+let joined = "value: " + to_string(42);
+print(joined);
@@ -0,0 +1,25 @@
+fn add(
+    a: int,
+    b: int,
+) -> int {
+    return a + b;
+}
+
+fn sum(values: int...,) -> int {
+    let mut total = 0;
+    for v in values {
+        total = total + v;
+    }
+    return total;
+}
+
+let point = {
+    x: add(1, 2,),
+    y: [1, 2, 3,],
+};
+print(point);
+print(sum(1, 2, 3,));
+print(add(
+    4,
+    5,
+));
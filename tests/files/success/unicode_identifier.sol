@@ -0,0 +1,3 @@
+let π = 3.14;
+let τ = π * 2.0;
+print(τ);
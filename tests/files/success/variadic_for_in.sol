@@ -0,0 +1,24 @@
+fn sum(values: ...int) -> int {
+    let mut total = 0;
+    for value in values {
+        total = total + value;
+    }
+
+    return total;
+}
+
+fn log(level: string, parts: ...string) {
+    let mut line = "[" + level + "]";
+    for part in parts {
+        line = line + " " + part;
+    }
+
+    print(line, " (", len(parts), " parts)");
+}
+
+print(sum());
+print(sum(7));
+print(sum(1, 2, 3, 4, 5));
+
+log("info");
+log("warn", "disk", "almost", "full");
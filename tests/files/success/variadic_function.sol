@@ -0,0 +1,20 @@
+fn sum(label: string, nums: int...) -> int {
+    let mut total = 0;
+    let mut i = 0;
+    loop {
+        if i == len(nums) {
+            break;
+        }
+
+        total = total + nums[i];
+        i = i + 1;
+    }
+
+    print(label + ": " + total as string);
+    return total;
+}
+
+sum("none");
+sum("one", 4);
+let total = sum("three", 1, 2, 3);
+print(total);
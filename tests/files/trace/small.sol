@@ -0,0 +1,6 @@
+fn add(a: int, b: int) -> int {
+    return a + b;
+}
+
+let x = add(1, 2);
+print(x);
@@ -0,0 +1,10 @@
+fn answer() -> int {
+    return 42;
+}
+
+fn main() {
+    answer();
+}
+
+answer();
+main();
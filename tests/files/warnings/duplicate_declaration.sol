@@ -0,0 +1,10 @@
+let x = 1;
+print(x);
+let x = 2;
+print(x);
+{
+    let x = 3;
+    print(x);
+}
+let _ = len("discarded");
+let _ = len("also discarded");
@@ -0,0 +1,7 @@
+fn first(items: int) -> int {
+    let unused = 3;
+    return items;
+    print("never");
+}
+
+print(first(1));
@@ -0,0 +1,54 @@
+use insta::assert_snapshot;
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use sol::{ast::Statement, fmt::format, lexer::Lexer, parser::Parser};
+use std::path::PathBuf;
+
+mod common;
+use common::without_spans;
+
+fn parse(input: &str) -> Vec<Statement> {
+    let lexer = Lexer::new(0, input);
+    let parser = Parser::new(lexer, input);
+
+    parser.collect::<Result<Vec<_>, _>>().unwrap()
+}
+
+#[rstest]
+fn round_trip(
+    #[files("tests/files/success/*.sol")]
+    #[files("tests/files/no_typecheck/*.sol")]
+    path: PathBuf,
+) {
+    let input = std::fs::read_to_string(path).unwrap();
+
+    let statements = parse(&input);
+    let formatted = format(&statements);
+
+    assert_eq!(
+        without_spans(&parse(&formatted)),
+        without_spans(&statements)
+    );
+    // formatting formatted code changes nothing
+    assert_eq!(format(&parse(&formatted)), formatted);
+}
+
+#[test]
+fn layout() {
+    let input = r#"
+// comments are dropped
+const   LIMIT : int=3;
+fn add(a:int,b:int)->int{return a+b;}
+fn main(){
+let mut x=(1+2)*3-(4-5);
+let small={a:1,b:[1,2,3]};
+let large={first_field:"some long string",second_field:"another long string",third:3.5};
+x=-(x as float) as int;
+if x>1&&!false{print(x);}else if x==0{print("zero");}else{loop{break x;}}
+large.first_field="changed";
+(small.a+1);
+}
+"#;
+
+    assert_snapshot!(format(&parse(input)));
+}
@@ -1,4 +1,4 @@
-use assert_cmd::cargo::CommandCargoExt;
+use assert_cmd::{assert::OutputAssertExt, cargo::CommandCargoExt};
 use insta::assert_snapshot;
 use rstest::rstest;
 use std::{env::current_dir, path::PathBuf, process::Command};
@@ -50,3 +50,335 @@ fn run_fail(#[files("tests/files/fail/*.sol")] path: PathBuf) {
 
     assert_snapshot!(snapshot_name, output);
 }
+
+#[rstest]
+fn run_no_typecheck(#[files("tests/files/no_typecheck/*.sol")] path: PathBuf) {
+    let relative_path = pathdiff::diff_paths(&path, current_dir().unwrap()).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let cmd = cmd
+        .arg("run")
+        .arg("--no-typecheck")
+        .arg(&relative_path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info");
+
+    let output = cmd.output().unwrap();
+
+    let snapshot_name = format!(
+        "no_typecheck__{}",
+        path.file_name().unwrap().to_string_lossy()
+    );
+
+    let output = format!(
+        "{}\n\n{}",
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap()
+    );
+
+    assert_snapshot!(snapshot_name, output);
+}
+
+#[test]
+fn exit_code() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("run")
+        .arg("tests/files/success/exit.sol")
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info")
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn exit_code_out_of_range() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("run")
+        .arg("tests/files/exit/out_of_range.sol")
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info")
+        .assert()
+        .code(1);
+}
+
+#[rstest]
+fn run_stats(#[files("tests/files/stats/*.sol")] path: PathBuf) {
+    let relative_path = pathdiff::diff_paths(&path, current_dir().unwrap()).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let cmd = cmd
+        .arg("run")
+        .arg("--stats")
+        .arg(&relative_path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info");
+
+    let output = cmd.output().unwrap();
+
+    let snapshot_name = format!("stats__{}", path.file_name().unwrap().to_string_lossy());
+
+    let output = format!(
+        "{}\n\n{}",
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap()
+    );
+
+    assert_snapshot!(snapshot_name, output);
+}
+
+#[rstest]
+fn run_warnings(#[files("tests/files/warnings/*.sol")] path: PathBuf) {
+    let relative_path = pathdiff::diff_paths(&path, current_dir().unwrap()).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let cmd = cmd
+        .arg("run")
+        .arg(&relative_path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info");
+
+    let output = cmd.output().unwrap();
+
+    let snapshot_name = format!("warnings__{}", path.file_name().unwrap().to_string_lossy());
+
+    let output = format!(
+        "{}\n\n{}",
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap()
+    );
+
+    assert_snapshot!(snapshot_name, output);
+}
+
+#[rstest]
+fn run_unused_results(#[files("tests/files/unused_results/*.sol")] path: PathBuf) {
+    let relative_path = pathdiff::diff_paths(&path, current_dir().unwrap()).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let cmd = cmd
+        .arg("run")
+        .arg("--warn-unused-results")
+        .arg(&relative_path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info");
+
+    let output = cmd.output().unwrap();
+
+    let snapshot_name = format!(
+        "unused_results__{}",
+        path.file_name().unwrap().to_string_lossy()
+    );
+
+    let output = format!(
+        "{}\n\n{}",
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap()
+    );
+
+    assert_snapshot!(snapshot_name, output);
+}
+
+#[test]
+fn deny_warnings() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("run")
+        .arg("--deny-warnings")
+        .arg("tests/files/warnings/unused_and_unreachable.sol")
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info")
+        .assert()
+        .code(1)
+        .stdout("");
+}
+
+#[rstest]
+fn run_trace(#[files("tests/files/trace/*.sol")] path: PathBuf) {
+    let relative_path = pathdiff::diff_paths(&path, current_dir().unwrap()).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let cmd = cmd
+        .arg("run")
+        .arg("--trace")
+        .arg(&relative_path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info");
+
+    let output = cmd.output().unwrap();
+
+    let snapshot_name = format!("trace__{}", path.file_name().unwrap().to_string_lossy());
+
+    let output = format!(
+        "{}\n\n{}",
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap()
+    );
+
+    assert_snapshot!(snapshot_name, output);
+}
+
+#[rstest]
+fn dump(
+    #[files("tests/files/dump/*.sol")] path: PathBuf,
+    #[values("tokens", "ast", "typecheck", "bytecode")] target: &str,
+) {
+    let relative_path = pathdiff::diff_paths(&path, current_dir().unwrap()).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let cmd = cmd
+        .arg("dump")
+        .arg("--target")
+        .arg(target)
+        .arg(&relative_path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info");
+
+    let output = cmd.output().unwrap();
+
+    let snapshot_name = format!(
+        "dump__{}__{}",
+        target,
+        path.file_name().unwrap().to_string_lossy()
+    );
+
+    // the artifact alone, anything logged goes to stderr
+    let output = String::from_utf8(output.stdout).unwrap();
+
+    assert_snapshot!(snapshot_name, output);
+}
+
+#[rstest]
+fn dump_fail(#[files("tests/files/dump_fail/*.sol")] path: PathBuf) {
+    let relative_path = pathdiff::diff_paths(&path, current_dir().unwrap()).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let cmd = cmd
+        .arg("dump")
+        .arg("--target")
+        .arg("typecheck")
+        .arg(&relative_path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info");
+
+    let output = cmd.output().unwrap();
+
+    let snapshot_name = format!("dump_fail__{}", path.file_name().unwrap().to_string_lossy());
+
+    let output = format!(
+        "{}\n\n{}",
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap()
+    );
+
+    assert_snapshot!(snapshot_name, output);
+}
+
+#[test]
+fn dump_ast_syntax_error() {
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("dump")
+        .arg("--target")
+        .arg("ast")
+        .arg("tests/files/dump_fail/syntax_error.sol")
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    // the diagnostic has to name the file that was dumped
+    assert_snapshot!(String::from_utf8(output.stderr).unwrap());
+}
+
+#[test]
+fn run_compiled_bytecode() {
+    let output_path = std::env::temp_dir().join(format!("sol-{}.solc", std::process::id()));
+
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("dump")
+        .arg("--target")
+        .arg("bytecode")
+        .arg("--output")
+        .arg(&output_path)
+        .arg("tests/files/success/nested_function.sol")
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .assert()
+        .success();
+
+    let from_bytecode = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("run")
+        .arg(&output_path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .output()
+        .unwrap();
+
+    let from_source = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("run")
+        .arg("tests/files/success/nested_function.sol")
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .output()
+        .unwrap();
+
+    std::fs::remove_file(&output_path).unwrap();
+
+    assert!(from_bytecode.status.success());
+    assert_eq!(from_bytecode.stdout, from_source.stdout);
+}
+
+#[test]
+fn dump_output_needs_bytecode_target() {
+    let output_path = std::env::temp_dir().join(format!("sol-{}.ast", std::process::id()));
+
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("dump")
+        .arg("--target")
+        .arg("ast")
+        .arg("--output")
+        .arg(&output_path)
+        .arg("tests/files/success/nested_function.sol")
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .assert()
+        .failure();
+
+    assert!(!output_path.exists());
+}
+
+#[rstest]
+#[case("tests/files/fmt/formatted.sol", 0)]
+#[case("tests/files/fmt/unformatted.sol", 1)]
+fn fmt_check(#[case] path: &str, #[case] code: i32) {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("fmt")
+        .arg("--check")
+        .arg(path)
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .env("SOL_LOG", "info")
+        .assert()
+        .code(code);
+}
+
+#[test]
+fn input_reads_piped_stdin() {
+    // the snapshot of this file runs without any stdin
+    let mut cmd = assert_cmd::Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("run")
+        .arg("tests/files/success/input.sol")
+        .env("NO_COLOR", "true")
+        .env("SOL_TEST", "true")
+        .write_stdin("sol\n")
+        .assert()
+        .success()
+        .stdout("name? hello sol\n");
+}
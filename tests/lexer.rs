@@ -1,4 +1,5 @@
 use insta::assert_debug_snapshot;
+use pretty_assertions::assert_eq;
 use sol::lexer::Lexer;
 
 #[test]
@@ -148,3 +149,47 @@ print(x.test5.test6.test7);
 
     assert_debug_snapshot!(tokens);
 }
+
+#[test]
+fn unicode_identifier() {
+    let input = "let π = 3.14; let _ñame2 = π;";
+
+    let lexer = Lexer::new(0, input);
+    let tokens = lexer.into_iter().collect::<Vec<_>>();
+
+    // π is two bytes, the spans after it have to account for that
+    assert_eq!(tokens[1].text(input), "π");
+    assert_eq!(tokens[6].text(input), "_ñame2");
+    assert_eq!(tokens[8].text(input), "π");
+
+    assert_debug_snapshot!(tokens);
+}
+
+#[test]
+fn multi_line_string() {
+    let input = "let s = \"one\ntwo\";\nlet t = 1;";
+
+    let lexer = Lexer::new(0, input);
+    let tokens = lexer.into_iter().collect::<Vec<_>>();
+
+    // the string belongs to the line it opens on, whatever follows it is on the lines after
+    assert_eq!((tokens[3].span().line, tokens[3].span().column), (1, 9));
+    assert_eq!((tokens[4].span().line, tokens[4].span().column), (2, 5));
+    assert_eq!((tokens[5].span().line, tokens[5].span().column), (3, 1));
+
+    assert_debug_snapshot!(tokens);
+}
+
+#[test]
+fn multibyte_string_literal() {
+    let input = "let s = \"🎉 ünïcode\"; let t = s;";
+
+    let lexer = Lexer::new(0, input);
+    let tokens = lexer.into_iter().collect::<Vec<_>>();
+
+    // every token after the string is shifted by the extra bytes inside it
+    assert_eq!(tokens[3].text(input), "\"🎉 ünïcode\"");
+    assert_eq!(tokens[4].text(input), ";");
+    assert_eq!(tokens[6].text(input), "t");
+    assert_eq!(tokens[8].text(input), "s");
+}
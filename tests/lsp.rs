@@ -0,0 +1,276 @@
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
+use sol::lsp;
+use std::thread::{self, JoinHandle};
+
+const URI: &str = "file:///test.sol";
+
+struct Client {
+    connection: Connection,
+    server: JoinHandle<bool>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Self {
+        let (server, connection) = Connection::memory();
+        let server = thread::spawn(move || lsp::run(server).is_ok());
+
+        let mut client = Self {
+            connection,
+            server,
+            next_id: 0,
+        };
+
+        client.request("initialize", json!({ "capabilities": {} }));
+        client.notify("initialized", json!({}));
+
+        client
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let notification = Notification::new(method.to_owned(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))
+            .unwrap();
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Response {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+
+        let request = Request::new(id.clone(), method.to_owned(), params);
+        self.connection
+            .sender
+            .send(Message::Request(request))
+            .unwrap();
+
+        loop {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(response) if response.id == id => return response,
+                _ => continue,
+            }
+        }
+    }
+
+    fn open(&self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "sol", "version": 1, "text": text }
+            }),
+        );
+
+        loop {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Notification(n) if n.method == "textDocument/publishDiagnostics" => {
+                    return n.params["diagnostics"].clone()
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    fn shutdown(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+
+        assert!(self.server.join().unwrap());
+    }
+}
+
+#[test]
+fn type_error_diagnostic() {
+    let client = Client::start();
+
+    let diagnostics = client.open("let x: int = \"not an int\";\n");
+
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(
+        diagnostics[0]["message"],
+        "type error: expected int but got String"
+    );
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 0, "character": 26 }
+        })
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn type_error_in_function_points_at_statement() {
+    let client = Client::start();
+
+    let diagnostics = client.open(
+        "fn f() {
+    let a = 1;
+    print(a + true);
+}
+f();
+",
+    );
+
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({
+            "start": { "line": 2, "character": 4 },
+            "end": { "line": 2, "character": 20 }
+        })
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn syntax_error_diagnostic() {
+    let client = Client::start();
+
+    // the 🎉 takes two utf-16 code units
+    let diagnostics = client.open("// 🎉\nlet = 3;\n");
+
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({
+            "start": { "line": 1, "character": 4 },
+            "end": { "line": 1, "character": 5 }
+        })
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn half_typed_input_is_reported() {
+    let client = Client::start();
+
+    for (text, message, start, end) in [
+        (
+            "let s = \"hel",
+            "unterminated string: add a closing `\"`",
+            8,
+            12,
+        ),
+        (
+            "let s = 1 @ 2;",
+            "unknown character: this can't start a token",
+            10,
+            11,
+        ),
+        (
+            "let o = { a: 1 }; let y = o.b.c;",
+            "type error: unexpected Nil",
+            18,
+            32,
+        ),
+    ] {
+        let diagnostics = client.open(text);
+
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1, "{text}");
+        assert_eq!(diagnostics[0]["message"], message);
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({
+                "start": { "line": 0, "character": start },
+                "end": { "line": 0, "character": end }
+            })
+        );
+    }
+
+    client.shutdown();
+}
+
+#[test]
+fn document_symbols_and_hover() {
+    let mut client = Client::start();
+
+    let diagnostics = client.open("const LIMIT = 3;\nfn twice(a: int) -> int {\n    return a * 2;\n}\nlet y = twice(LIMIT);\nprint(y);\n");
+    assert_eq!(diagnostics, json!([]));
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let names = symbols
+        .response_result
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["LIMIT", "twice"]);
+
+    let hover = client.request(
+        "textDocument/hover",
+        json!({ "textDocument": { "uri": URI }, "position": { "line": 4, "character": 5 } }),
+    );
+    assert_eq!(
+        hover.response_result.unwrap()["contents"]["value"],
+        "```sol\ny: I64\n```"
+    );
+
+    let hover = client.request(
+        "textDocument/hover",
+        json!({ "textDocument": { "uri": URI }, "position": { "line": 4, "character": 10 } }),
+    );
+    assert_eq!(
+        hover.response_result.unwrap()["contents"]["value"],
+        "```sol\nfn twice -> I64\n```"
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn hover_finds_shadowed_definition() {
+    let mut client = Client::start();
+
+    client.open("let x = 1;\nprint(x);\nlet x = \"one\";\nprint(x);\n");
+
+    let hover = |client: &mut Client, line| {
+        client
+            .request(
+                "textDocument/hover",
+                json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": 6 } }),
+            )
+            .response_result
+            .unwrap()["contents"]["value"]
+            .clone()
+    };
+
+    assert_eq!(hover(&mut client, 1), "```sol\nx: I64\n```");
+    assert_eq!(hover(&mut client, 3), "```sol\nx: String\n```");
+
+    client.shutdown();
+}
+
+#[test]
+fn malformed_params_are_reported() {
+    let mut client = Client::start();
+
+    let hover = client.request("textDocument/hover", json!({ "position": "nowhere" }));
+    assert_eq!(
+        hover.response_result.unwrap_err().code,
+        lsp_server::ErrorCode::InvalidParams as i32
+    );
+
+    client.notify("textDocument/didOpen", json!({ "textDocument": 3 }));
+    loop {
+        match client.connection.receiver.recv().unwrap() {
+            Message::Notification(n) if n.method == "window/logMessage" => break,
+            _ => continue,
+        }
+    }
+
+    // still serving
+    let diagnostics = client.open("let x: int = 1;\nprint(x);\n");
+    assert_eq!(diagnostics, json!([]));
+
+    client.shutdown();
+}
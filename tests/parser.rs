@@ -1,5 +1,12 @@
 use insta::assert_debug_snapshot;
-use sol::{lexer::Lexer, parser::Parser};
+use sol::{
+    ast::Statement,
+    lexer::Lexer,
+    parser::{Parser, ParserError},
+};
+
+mod common;
+use common::without_spans;
 
 #[test]
 fn small_input() {
@@ -14,12 +21,13 @@ fn small_input() {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     let statements = statements.into_iter().collect::<Vec<_>>();
@@ -67,12 +75,13 @@ fn new_function(arg1: int, arg2: int, arg3: int) {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -92,12 +101,13 @@ fn complex_math() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -117,12 +127,13 @@ fn math() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -145,12 +156,13 @@ fn large_input() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -174,12 +186,13 @@ fn function_call_return() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -201,12 +214,13 @@ fn useless_expression() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -230,12 +244,13 @@ fn function_call_with_addition() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -258,12 +273,13 @@ fn variable_and_operation() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -284,12 +300,13 @@ fn variable_mutation() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     assert_debug_snapshot!(statements);
 }
@@ -309,12 +326,13 @@ fn prefix() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -335,12 +353,13 @@ fn prefix_boolean() {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -401,12 +420,13 @@ if false {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -444,12 +464,13 @@ loop {
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
 
     assert_debug_snapshot!(statements);
@@ -493,13 +514,290 @@ print(x.test5.test6.test7);
     let mut statements = Vec::new();
 
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
+    }
+
+    assert_debug_snapshot!(statements);
+}
+
+#[test]
+fn multibyte_comment_span() {
+    let input = "// 🎉 party\nlet = 3;";
+
+    let lexer = Lexer::new(0, input);
+    let parser = Parser::new(lexer, input);
+
+    let errors = parser.filter_map(Result::err).collect::<Vec<_>>();
+    let ParserError::Diagnostic(diagnostic) = &errors[0] else {
+        panic!("expected a diagnostic");
+    };
+
+    // the emoji is 4 bytes, the label has to land on `=` rather than somewhere before it
+    let range = diagnostic.labels[0].range.clone();
+    assert_eq!(&input[range], "=");
+}
 
-        statements.push(token.unwrap());
+#[test]
+fn array_slice() {
+    let input = r#"
+let xs = [1, 2, 3];
+let ys = xs[0..len(xs) - 1];
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let mut statements = Vec::new();
+
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
     }
 
     assert_debug_snapshot!(statements);
 }
+
+#[test]
+fn if_expression() {
+    let input = r#"
+let grade = if score > 90 { "A" } else if score > 75 { "B" } else { "C" };
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let mut statements = Vec::new();
+
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+
+    assert_debug_snapshot!(statements);
+}
+
+#[test]
+fn boolean_operators_bind_looser_than_comparisons() {
+    let input = r#"
+        let x = a == b && c == d;
+        let y = a < b || c != d && e;
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+    let mut statements = Vec::new();
+
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+
+    assert_debug_snapshot!(statements);
+}
+
+#[test]
+fn diagnostic_span_on_first_line() {
+    let input = "let = 3;\nlet x = 1;";
+
+    let lexer = Lexer::new(0, input);
+    let parser = Parser::new(lexer, input);
+
+    let errors = parser.filter_map(Result::err).collect::<Vec<_>>();
+    let ParserError::Diagnostic(diagnostic) = &errors[0] else {
+        panic!("expected a diagnostic");
+    };
+
+    assert_eq!(diagnostic.labels[0].range, 4..5);
+}
+
+#[test]
+fn diagnostic_span_at_end_of_file() {
+    let input = "let x = 1;\nlet y =";
+
+    let lexer = Lexer::new(3, input);
+    let parser = Parser::new(lexer, input);
+
+    let errors = parser.filter_map(Result::err).collect::<Vec<_>>();
+    let ParserError::Diagnostic(diagnostic) = &errors[0] else {
+        panic!("expected a diagnostic");
+    };
+
+    // nothing is left to point at, the label goes just past the last character
+    assert_eq!(diagnostic.labels[0].file_id, 3);
+    assert_eq!(diagnostic.labels[0].range, input.len()..input.len());
+}
+
+#[test]
+fn trailing_expression_is_returned() {
+    // spans differ between the two, so only the statements are compared
+    let body = |input: &str| {
+        let lexer = Lexer::new(0, input);
+        let mut statements = Parser::new(lexer, input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let Some(Statement::Function(function)) = statements.pop() else {
+            panic!("expected a function");
+        };
+        let Statement::Block { body, .. } = *function.body else {
+            panic!("expected a block");
+        };
+
+        body
+    };
+
+    for (implicit, explicit) in [
+        (
+            "fn f(a: int) -> int { let b = a * 2; b + 1 }",
+            "fn f(a: int) -> int { let b = a * 2; return b + 1; }",
+        ),
+        ("fn f() -> int { 1 }", "fn f() -> int { return 1; }"),
+        (
+            "fn f() -> int { (1 + 2) }",
+            "fn f() -> int { return (1 + 2); }",
+        ),
+        (
+            "fn f() -> int { let o = { a: 1 }; o.a }",
+            "fn f() -> int { let o = { a: 1 }; return o.a; }",
+        ),
+        ("fn f() -> int { g(1) }", "fn f() -> int { return g(1); }"),
+    ] {
+        assert_eq!(
+            without_spans(&body(implicit)),
+            without_spans(&body(explicit)),
+            "{implicit}"
+        );
+    }
+}
+
+#[test]
+fn trailing_expression_outside_function_is_not_returned() {
+    // the `;` can still be left off, it just doesn't make a return
+    for input in ["{ 1 }", "fn f() { if true { 1 } }"] {
+        let lexer = Lexer::new(0, input);
+        let statements = Parser::new(lexer, input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert!(!format!("{statements:?}").contains("Return"), "{input}");
+    }
+}
+
+#[test]
+fn field_access_is_an_expression_statement() {
+    // the same statements parse inside a function body and outside of one
+    for input in [
+        "let o = { a: 1 }; o.a; o.a + 1;",
+        "fn f() { let o = { a: 1 }; o.a; o.a + 1; }",
+    ] {
+        let lexer = Lexer::new(0, input);
+        let statements = Parser::new(lexer, input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let statements = format!("{statements:?}");
+        assert_eq!(statements.matches("Expression(").count(), 2, "{input}");
+        assert!(!statements.contains("Return"), "{input}");
+    }
+}
+
+#[test]
+fn last_statement_in_block_needs_no_semicolon() {
+    let with = "fn f() { let x = 1; print(x); loop { break; } }";
+    let without = "fn f() { let x = 1; print(x); loop { break } }";
+
+    let parse = |input: &str| {
+        let lexer = Lexer::new(0, input);
+        let mut statements = Parser::new(lexer, input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let Some(Statement::Function(function)) = statements.pop() else {
+            panic!("expected a function");
+        };
+        let Statement::Block { body, .. } = *function.body else {
+            panic!("expected a block");
+        };
+
+        body
+    };
+
+    assert_eq!(without_spans(&parse(with)), without_spans(&parse(without)));
+}
+
+#[test]
+fn missing_semicolon_points_after_previous_token() {
+    let input = "let x = 1\nprint(x);";
+
+    let lexer = Lexer::new(0, input);
+    let parser = Parser::new(lexer, input);
+
+    let errors = parser.filter_map(Result::err).collect::<Vec<_>>();
+    let ParserError::Diagnostic(diagnostic) = &errors[0] else {
+        panic!("expected a diagnostic");
+    };
+
+    assert_eq!(diagnostic.message, "missing semicolon");
+    assert_eq!(diagnostic.labels[0].range, 9..9);
+}
+
+#[test]
+fn trailing_commas() {
+    let input = r#"
+fn add(a: int, b: int,) -> int {
+    return a + b;
+}
+let x = { a: add(1, 2,), b: [1, 2,], };
+    "#;
+
+    let lexer = Lexer::new(0, input);
+    let statements = Parser::new(lexer, input)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_debug_snapshot!(statements);
+}
+
+#[test]
+fn missing_comma_points_between_items() {
+    for (input, gap) in [
+        ("f(1 2);", 3..4),
+        ("fn f(a: int b: int) {}", 11..12),
+        ("let x = { a: 1 b: 2 };", 14..15),
+        ("let x = [1  2];", 10..12),
+        // nothing in between, the label is empty but still in the right place
+        (r#"f(1"a");"#, 3..3),
+    ] {
+        let lexer = Lexer::new(0, input);
+        let parser = Parser::new(lexer, input);
+
+        let errors = parser.filter_map(Result::err).collect::<Vec<_>>();
+        let ParserError::Diagnostic(diagnostic) = &errors[0] else {
+            panic!("expected a diagnostic");
+        };
+
+        assert_eq!(diagnostic.labels[0].range, gap, "{input}");
+    }
+}
@@ -0,0 +1,57 @@
+use sol::scope::{Scope, ScopeType};
+use std::collections::BTreeMap;
+
+#[test]
+fn define_and_resolve() {
+    let scope = Scope::new(ScopeType::Local);
+    scope.define_immutable("a", 1);
+    scope.define_mutable("b", 2);
+
+    assert_eq!(scope.get_register_for("a"), Some(1));
+    assert_eq!(scope.get_register_for("b"), Some(2));
+    assert_eq!(scope.get_register_for("c"), None);
+    assert_eq!(
+        scope.registers(),
+        BTreeMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)])
+    );
+}
+
+#[test]
+fn mutability() {
+    let scope = Scope::new(ScopeType::Local);
+    scope.define_immutable("a", 1);
+    scope.define_mutable("b", 2);
+
+    assert_eq!(scope.is_mutable("a"), Some(false));
+    assert_eq!(scope.is_mutable("b"), Some(true));
+    assert_eq!(scope.is_mutable("c"), None);
+
+    // redefining takes the new mutability and register
+    scope.define_mutable("a", 3);
+    assert_eq!(scope.is_mutable("a"), Some(true));
+    assert_eq!(scope.get_register_for("a"), Some(3));
+}
+
+#[test]
+fn remove_hands_back_the_register() {
+    let scope = Scope::new(ScopeType::Global);
+    scope.define_immutable("a", 1);
+    scope.define_constant("a", sol::types::Literal::Integer(4));
+
+    assert_eq!(scope.remove("a"), Some(1));
+    assert_eq!(scope.remove("a"), None);
+    assert_eq!(scope.get_register_for("a"), None);
+    assert_eq!(scope.get_constant("a"), None);
+    assert!(scope.is_global());
+}
+
+#[test]
+fn functions() {
+    let scope = Scope::new(ScopeType::Local);
+    scope.define_function("f");
+
+    assert!(scope.contains_function("f"));
+    assert!(!scope.contains_function("g"));
+    // functions aren't variables
+    assert_eq!(scope.get_register_for("f"), None);
+}
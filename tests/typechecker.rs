@@ -0,0 +1,172 @@
+use insta::assert_debug_snapshot;
+use sol::{
+    lexer::Lexer,
+    parser::Parser,
+    typechecker::{DefinedType, NativeSignature, ParameterType, Typechecker, TypecheckerError},
+};
+
+#[test]
+fn annotated_ast() {
+    let input = r#"
+            let x = 1 + 2.5;
+            let y = [1, 2, 3];
+            let z = { a: y[0], b: "hello" };
+
+            fn add(a: int, b: int) {
+                return a + b;
+            }
+
+            if z.a > 1 && !false {
+                print(add(1, z.a));
+            }
+        "#
+    .to_owned();
+
+    let mut lexer = Lexer::new(0, &input);
+    let parser = Parser::new(&mut lexer, &input);
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+
+    let typechecker = Typechecker::default();
+    let typed_statements = typechecker.check(&statements).unwrap();
+
+    assert_debug_snapshot!(typed_statements);
+}
+
+fn check_with_native(input: &str) -> Result<(), TypecheckerError> {
+    let lexer = Lexer::new(0, input);
+    let statements = Parser::new(lexer, input)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let mut typechecker = Typechecker::default();
+    typechecker.declare_native(
+        "repeat",
+        NativeSignature::new(
+            vec![
+                ParameterType::Type(DefinedType::String),
+                ParameterType::Type(DefinedType::I64),
+            ],
+            DefinedType::String,
+        ),
+    );
+
+    typechecker.check(&statements).map(|_| ())
+}
+
+#[test]
+fn embedder_declared_native() {
+    check_with_native(r#"let s: string = repeat("ab", 3);"#).unwrap();
+
+    assert!(matches!(
+        check_with_native(r#"let s = repeat(3, "ab");"#),
+        Err(TypecheckerError::TypeMismatch { expected, got }) if expected == "String" && got == "I64"
+    ));
+    assert!(matches!(
+        check_with_native(r#"let s = repeat("ab");"#),
+        Err(TypecheckerError::TooFewArguments {
+            expected: 2,
+            got: 1,
+            ..
+        })
+    ));
+    assert!(matches!(
+        check_with_native(r#"let n: int = repeat("ab", 3);"#),
+        Err(TypecheckerError::TypeMismatch { .. })
+    ));
+}
+
+fn check(input: &str) -> Result<(), TypecheckerError> {
+    let lexer = Lexer::new(0, input);
+    let statements = Parser::new(lexer, input)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    Typechecker::default().check(&statements).map(|_| ())
+}
+
+#[test]
+fn object_spread_merges_field_types() {
+    let base = r#"let base = { name: "sol", version: 1 };"#;
+
+    check(&format!(
+        r#"{base} let next = {{ ...base, version: "two" }}; let v: string = next.version; let n: string = next.name;"#
+    ))
+    .unwrap();
+    // a spread after a field overrides it
+    check(&format!(
+        r#"{base} let next = {{ version: "two", ...base }}; let v: int = next.version;"#
+    ))
+    .unwrap();
+
+    assert!(matches!(
+        check(&format!(
+            r#"{base} let next = {{ ...base, version: "two" }}; let v: int = next.version;"#
+        )),
+        Err(TypecheckerError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        check("let x = { ...[1, 2] };"),
+        Err(TypecheckerError::UnexpectedType { .. })
+    ));
+}
+
+#[test]
+fn unused_result_warning_is_opt_in() {
+    let input = r#"
+        fn answer() -> int {
+            return 42;
+        }
+
+        fn nothing() {}
+
+        fn main() {
+            answer();
+            nothing();
+            let _ = answer();
+        }
+
+        answer();
+        main();
+    "#;
+
+    let lexer = Lexer::new(0, input);
+    let mut parser = Parser::new(lexer, input);
+    let mut statements = Vec::new();
+    let mut spans = Vec::new();
+    while let Some(statement) = parser.next() {
+        statements.push(statement.unwrap());
+        spans.extend(parser.statement_span());
+    }
+
+    let warnings = Typechecker::default().analyze(&statements).warnings;
+    assert!(warnings.is_empty());
+
+    let warnings = Typechecker::default()
+        .with_unused_result_warnings()
+        .with_statement_spans(spans)
+        .analyze(&statements)
+        .warnings;
+    let messages = warnings
+        .iter()
+        .map(|w| w.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "return value of `answer` is unused",
+            "return value of `answer` is unused"
+        ]
+    );
+    // with the top level spans passed in, both calls have a span to point at
+    assert!(warnings.iter().all(|w| !w.labels.is_empty()));
+}
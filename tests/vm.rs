@@ -1,11 +1,13 @@
 use insta::assert_compact_debug_snapshot;
 use sol::{
     compiler::Compiler,
+    instructions::Instruction,
     lexer::Lexer,
     parser::Parser,
     types,
-    vm::{VMValue, VM},
+    vm::{Execution, ExecutionError, StepControl, VMValue, VM},
 };
+use std::{cell::RefCell, io::Cursor, rc::Rc};
 
 #[test]
 fn complex_math() {
@@ -20,17 +22,20 @@ fn complex_math() {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let program = compiler.compile(&statements).unwrap();
 
     let vm = VM::new(program);
-    let register_state = vm.run_with_registers_returned();
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
 
     assert_compact_debug_snapshot!(register_state);
 }
@@ -48,17 +53,52 @@ fn math() {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn modulo() {
+    let input = r#"
+        let x = 10 % 3;
+        let y = 7.5 % 2;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
 
-        statements.push(token.unwrap());
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
     }
     let program = compiler.compile(&statements).unwrap();
 
     let vm = VM::new(program);
-    let register_state = vm.run_with_registers_returned();
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
 
     assert_compact_debug_snapshot!(register_state);
 }
@@ -77,17 +117,20 @@ fn prefix() {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let program = compiler.compile(&statements).unwrap();
 
     let vm = VM::new(program);
-    let register_state = vm.run_with_registers_returned();
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
 
     assert_compact_debug_snapshot!(register_state);
 }
@@ -106,17 +149,20 @@ fn prefix_boolean() {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let program = compiler.compile(&statements).unwrap();
 
     let vm = VM::new(program);
-    let register_state = vm.run_with_registers_returned();
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
 
     assert_compact_debug_snapshot!(register_state);
 }
@@ -134,17 +180,20 @@ fn native_function() {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let program = compiler.compile(&statements).unwrap();
 
-    let vm = VM::new(program).define_native_function("test_function".to_owned(), |_| None);
-    let register_state = vm.run_with_registers_returned();
+    let vm = VM::new(program).define_native_function("test_function".to_owned(), |_, _| Ok(None));
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
 
     assert_compact_debug_snapshot!(register_state);
 }
@@ -167,20 +216,60 @@ fn native_function_with_return_value() {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let program = compiler.compile(&statements).unwrap();
-    let vm = VM::new(program).define_native_function("test".to_owned(), |_| {
-        Some(VMValue::Literal(std::borrow::Cow::Owned(
+    let vm = VM::new(program).define_native_function("test".to_owned(), |_, _| {
+        Ok(Some(VMValue::Literal(std::borrow::Cow::Owned(
             types::Literal::Boolean(true),
-        )))
+        ))))
+    });
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn native_function_with_non_bool_condition() {
+    let input = r#"
+        let x = test();
+        if x {
+            print("fail");
+        }
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+    let vm = VM::new(program).define_native_function("test".to_owned(), |_, _| {
+        Ok(Some(VMValue::Literal(std::borrow::Cow::Owned(
+            types::Literal::Integer(1),
+        ))))
     });
-    let register_state = vm.run_with_registers_returned();
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
 
     assert_compact_debug_snapshot!(register_state);
 }
@@ -218,17 +307,20 @@ loop {
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
-
-        statements.push(token.unwrap());
     }
     let program = compiler.compile(&statements).unwrap();
 
     let vm = VM::new(program);
-    let register_state = vm.run_with_registers_returned();
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
 
     assert_compact_debug_snapshot!(register_state);
 }
@@ -272,17 +364,2171 @@ print(x.test5.test6.test7);
 
     let mut statements = Vec::new();
     for token in parser {
-        if token.is_err() {
-            tracing::error!("{}", token.unwrap_err());
-            break;
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn bitwise() {
+    let input = r#"
+        let flags = 0b1010 | 0b0101;
+        let value = 0x1234;
+        let masked = value & 0xFF;
+        let toggled = flags ^ 0b0011;
+        let inverted = ~0;
+        let shifted = 1 << 4;
+        let halved = shifted >> 1;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn bitwise_on_float() {
+    let input = r#"
+        let x = 1.5 & 1;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn global_result() {
+    let input = r#"
+        let x = 2;
+        fn double(n: int) {
+            return n * 2;
+        }
+
+        double(x) + 38;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let result = vm.run_with_result();
+
+    assert_compact_debug_snapshot!(result);
+}
+
+#[test]
+fn string_concat() {
+    let input = r#"
+        let s = "foo" + "bar";
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn string_concat_non_string() {
+    let input = r#"
+        let s = "foo" + 1;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn instruction_limit() {
+    let input = r#"
+        let mut x = 0;
+        loop {
+            x = x + 1;
+        }
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program).with_instruction_limit(10_000);
+    let register_state = vm.run_with_registers_returned().map(|_| ());
+
+    assert_compact_debug_snapshot!(register_state);
+    assert_eq!(vm.stats().instructions_executed, 10_000);
+}
+
+#[test]
+fn array_length() {
+    let input = r#"
+        let xs = [1, 2, 3];
+        let n = len(xs);
+        let s = len("héllo");
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn array_length_invalid() {
+    let input = r#"
+        let n = len(1);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
         }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn array_push() {
+    let input = r#"
+        let xs = [1, 2];
+        push(xs, 3);
+        push(xs, "four");
+        let n = len(xs);
+        let last = xs[3];
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
 
-        statements.push(token.unwrap());
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
     }
     let program = compiler.compile(&statements).unwrap();
 
     let vm = VM::new(program);
-    let register_state = vm.run_with_registers_returned();
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
 
     assert_compact_debug_snapshot!(register_state);
 }
+
+#[test]
+fn array_pop() {
+    let input = r#"
+        let xs = [1, 2];
+        let a = pop(xs);
+        let b = pop(xs);
+        let c = pop(xs);
+        let n = len(xs);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn single_step() {
+    let input = r#"
+        let x = 1;
+        let y = x + 2;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let hook_seen = seen.clone();
+    let vm = VM::new(program).with_step_hook(move |step| {
+        hook_seen.borrow_mut().push((
+            step.ip,
+            step.function_name.to_owned(),
+            step.instruction.name(),
+        ));
+
+        StepControl::Pause
+    });
+
+    // every instruction hands control back before it runs
+    let mut state = vm.start();
+    let mut pauses = 0;
+    while vm.resume(&mut state).unwrap() == Execution::Paused {
+        pauses += 1;
+    }
+
+    assert_eq!(pauses, seen.borrow().len());
+    assert_compact_debug_snapshot!(seen.borrow());
+    assert_compact_debug_snapshot!(state.registers());
+}
+
+#[test]
+fn step_hook_abort() {
+    let input = r#"
+        let mut x = 0;
+        loop {
+            x = x + 1;
+        }
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let mut steps = 0;
+    let vm = VM::new(program).with_step_hook(move |_| {
+        steps += 1;
+        if steps > 3 {
+            StepControl::Abort
+        } else {
+            StepControl::Continue
+        }
+    });
+
+    let result = vm.run();
+
+    assert!(matches!(result, Err(ExecutionError::Aborted)));
+    assert_eq!(vm.stats().instructions_executed, 3);
+}
+
+#[test]
+fn nil() {
+    let input = r#"
+        let x = nil;
+        let y = is_nil(x);
+        let z = is_nil(1);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn loop_break_value() {
+    let input = r#"
+        let mut i = 0;
+        loop {
+            i = i + 1;
+            if i == 5 {
+                break i * 10;
+            }
+        }
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let result = vm.run_with_result();
+
+    assert_compact_debug_snapshot!(result);
+}
+
+#[test]
+fn type_of() {
+    let input = r#"
+        let a = type_of(1);
+        let b = type_of(1.5);
+        let c = type_of("s");
+        let d = type_of(true);
+        let e = type_of({ x: 1 });
+        let g = type_of([1, 2]);
+        let i = type_of(nil);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn cast() {
+    let input = r#"
+        let a = 3 as float;
+        let b = 3.9 as int;
+        let c = -3.9 as int;
+        let d = "42" as int;
+        let e = 1.5 as string;
+        let f = true as string;
+        let g = 0 as bool;
+        let h = 2 as bool;
+        let i = "" as bool;
+        let j = nil as bool;
+        let k = 1 + 2 * 3 as float;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn cast_invalid() {
+    let input = r#"
+        let x = { a: 1 };
+        let y = x as int;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn array_negative_index() {
+    let input = r#"
+let xs = [10, 20, 30];
+let last = xs[-1];
+let first = xs[-3];
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn array_negative_index_out_of_bounds() {
+    let input = r#"
+let xs = [1];
+let x = xs[-5];
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn more_than_256_registers() {
+    // each let takes its own register, well past what a u8 could address
+    let locals = (0..300)
+        .map(|i| format!("    let x{i} = {i};\n"))
+        .collect::<String>();
+    let input = format!("fn many() -> int {{\n{locals}    return x299;\n}}\n\nmany();\n");
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+    assert!(program.functions[0].register_count > 300);
+
+    let vm = VM::new(program);
+    let result = vm.run_with_result();
+
+    assert_compact_debug_snapshot!(result);
+}
+
+#[test]
+fn counter_increment_decrement() {
+    let input = r#"
+let mut a = 1;
+a = a + 1;
+let mut b = 1;
+b = 1 + b;
+let mut c = 1;
+c = c - 1;
+let mut d = 1.5;
+d = d + 1;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn array_slice() {
+    let input = r#"
+let xs = [10, 20, 30, 40];
+let middle = xs[1..3];
+let tail = xs[-2..4];
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn array_slice_empty() {
+    let input = r#"
+let xs = [10, 20, 30];
+let same = xs[1..1];
+let reversed = xs[2..0];
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn array_slice_out_of_range() {
+    let input = r#"
+let xs = [10, 20, 30];
+let clamped = xs[1..100];
+let before = xs[-100..1];
+let after = xs[5..10];
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn stack_overflow() {
+    let input = r#"
+// top level functions can call each other before their definition
+fn ping(n: int) -> int {
+    return pong(n + 1);
+}
+
+fn pong(n: int) -> int {
+    return ping(n + 1);
+}
+
+ping(0);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program).with_max_call_depth(50).with_stats(true);
+    let result = vm.run();
+
+    assert!(matches!(
+        result,
+        Err(ExecutionError::StackOverflow { depth: 50 })
+    ));
+    assert_eq!(vm.stats().peak_call_depth, 50);
+}
+
+#[test]
+fn if_expression() {
+    let input = r#"
+let score = 80;
+let grade = if score > 90 { 1 } else { if score > 50 { 2 } else { 3 } };
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn fuel_exhausted() {
+    let input = r#"
+        loop {}
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program).with_fuel(500);
+    let result = vm.run_with_registers_returned();
+
+    assert!(matches!(result, Err(ExecutionError::FuelExhausted)));
+    assert_eq!(vm.stats().instructions_executed, 500);
+}
+
+#[test]
+fn for_range() {
+    let input = r#"
+let mut total = 0;
+for i in 1..4 {
+    total = total + i;
+}
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn trace() {
+    let input = r#"
+        let x = 1 + 2;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let traced = Rc::new(RefCell::new(Vec::new()));
+    let trace_traced = traced.clone();
+    let vm = VM::new(program)
+        .with_trace(move |ip, instruction| trace_traced.borrow_mut().push((ip, *instruction)));
+
+    vm.run().unwrap();
+
+    assert_eq!(
+        *traced.borrow(),
+        vec![
+            (0, Instruction::LoadLiteral { dest: 1, src: 0 }),
+            (1, Instruction::LoadLiteral { dest: 2, src: 1 }),
+            (
+                2,
+                Instruction::Add {
+                    dest: 3,
+                    lhs: 1,
+                    rhs: 2
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn nil_is_not_empty() {
+    assert_eq!(VMValue::Nil, VMValue::Nil);
+    assert_eq!(
+        VMValue::Nil,
+        VMValue::Literal(std::borrow::Cow::Owned(types::Literal::Nil))
+    );
+    assert_ne!(VMValue::Nil, VMValue::Empty);
+
+    // they only differ inside the vm, scripts see both as nil
+    assert_eq!(VMValue::Nil.to_string(), "nil");
+    assert_eq!(VMValue::Empty.to_string(), "nil");
+}
+
+#[test]
+fn missing_object_field_is_nil() {
+    let input = r#"
+        let person = { name: "sol" };
+        let missing = person.nickname;
+        let is_nil = missing == nil;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn array_index_out_of_bounds_is_nil() {
+    let input = r#"
+        let xs = [1, 2];
+        let missing = xs[5];
+        let is_nil = missing == nil;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn object_structural_equality() {
+    let input = r#"
+        let a = { name: "sol", inner: { items: [1, 2], flag: true } };
+        let b = { name: "sol", inner: { items: [1, 2], flag: true } };
+        let c = { name: "sol", inner: { items: [1, 3], flag: true } };
+        let same = a == b;
+        let different = a == c;
+        let identical = a == a;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn object_spread() {
+    let input = r#"
+        let base = { name: "sol", version: 1, tags: ["a"] };
+        let next = { ...base, version: 2, stable: true };
+        let reset = { version: 0, ...next };
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    let global = |name| vm.get_global(&state, name).unwrap().to_string();
+    // later fields override earlier ones but keep their place
+    assert_eq!(
+        global("next"),
+        r#"{ name: "sol", version: 2, tags: ["a"], stable: true }"#
+    );
+    assert_eq!(
+        global("reset"),
+        r#"{ version: 2, name: "sol", tags: ["a"], stable: true }"#
+    );
+    assert_eq!(
+        global("base"),
+        r#"{ name: "sol", version: 1, tags: ["a"] }"#
+    );
+}
+
+#[test]
+fn step_of_non_number_is_an_error() {
+    for (input, cause) in [
+        ("let mut s = \"a\"; s = s + 1;", "cannot increment string"),
+        ("let mut s = \"a\"; s = s - 1;", "cannot decrement string"),
+    ] {
+        let input = input.to_owned();
+        let lexer = Lexer::new(0, &input);
+        let parser = Parser::new(lexer, &input);
+        let compiler = Compiler::new();
+
+        let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+        let program = compiler.compile(&statements).unwrap();
+
+        let vm = VM::new(program);
+
+        assert!(matches!(
+            vm.run(),
+            Err(ExecutionError::InvalidOperation { cause: got }) if got == cause
+        ));
+    }
+}
+
+#[test]
+fn spread_of_non_object_is_an_error() {
+    let input = r#"
+        let x = { ...1 };
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+
+    assert!(matches!(
+        vm.run(),
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "cannot spread int"
+    ));
+}
+
+#[test]
+fn object_cycle_is_incomparable() {
+    let input = r#"
+        let mut a = { name: "a" };
+        let b = { parent: a };
+        a.child = b;
+        let mut c = { name: "a" };
+        let d = { parent: c };
+        c.child = d;
+        let cyclic = a == c;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let (register_state, _) = vm.run_with_registers_returned().unwrap();
+
+    // no snapshot here, debug printing a cycle never finishes
+    assert_eq!(
+        register_state.current().last(),
+        Some(&VMValue::Literal(std::borrow::Cow::Owned(
+            types::Literal::Boolean(false)
+        )))
+    );
+}
+
+#[test]
+fn compound_boolean_condition() {
+    let input = r#"
+        let a = 1;
+        let b = 2;
+        let mut result = 0;
+        if a == 1 && b == 2 || a == b {
+            result = 1;
+        }
+        if a == 2 || b == 3 && a == 1 {
+            result = 2;
+        }
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn call_function_from_host() {
+    let input = r#"
+        fn add(a: int, b: int) -> int {
+            return a + b;
+        }
+
+        fn fib(n: int) -> int {
+            if n < 2 {
+                return n;
+            }
+
+            return fib(n - 1) + fib(n - 2);
+        }
+
+        fn nothing() {}
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let integer = |i| VMValue::Literal(std::borrow::Cow::Owned(types::Literal::Integer(i)));
+
+    let sum = vm.call_function("add", &[integer(1), integer(2)]).unwrap();
+    assert_eq!(sum, integer(3));
+
+    // each call starts from a clean slate
+    let fib = vm.call_function("fib", &[integer(10)]).unwrap();
+    assert_eq!(fib, integer(55));
+    let fib = vm.call_function("fib", &[integer(5)]).unwrap();
+    assert_eq!(fib, integer(5));
+
+    assert_eq!(vm.call_function("nothing", &[]).unwrap(), VMValue::Nil);
+
+    let missing = vm.call_function("missing", &[]);
+    assert!(matches!(
+        missing,
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "function not found"
+    ));
+}
+
+#[test]
+fn trailing_expression_returns_like_return() {
+    let input = r#"
+        fn explicit(n: int) -> int {
+            let doubled = n * 2;
+            return doubled + 1;
+        }
+
+        fn implicit(n: int) -> int {
+            let doubled = n * 2;
+            doubled + 1
+        }
+
+        fn early(n: int) -> int {
+            if n < 0 {
+                return 0;
+            }
+            n
+        }
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let integer = |i| VMValue::Literal(std::borrow::Cow::Owned(types::Literal::Integer(i)));
+
+    for n in [-3, 0, 4] {
+        assert_eq!(
+            vm.call_function("explicit", &[integer(n)]).unwrap(),
+            vm.call_function("implicit", &[integer(n)]).unwrap()
+        );
+    }
+    assert_eq!(
+        vm.call_function("early", &[integer(-1)]).unwrap(),
+        integer(0)
+    );
+    assert_eq!(
+        vm.call_function("early", &[integer(7)]).unwrap(),
+        integer(7)
+    );
+}
+
+#[test]
+fn host_globals() {
+    let input = r#"
+        let config = { timeout: 10 };
+        let mut retries = 3;
+        let total = config.timeout * retries;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    // stop just before `config.timeout` is read so the host can change the inputs
+    let vm = VM::new(program).with_step_hook(|step| match step.instruction {
+        Instruction::GetObjectField { .. } => StepControl::Pause,
+        _ => StepControl::Continue,
+    });
+    let integer = |i| VMValue::Literal(std::borrow::Cow::Owned(types::Literal::Integer(i)));
+
+    let mut state = vm.start();
+    assert_eq!(vm.resume(&mut state).unwrap(), Execution::Paused);
+
+    assert_eq!(vm.get_global(&state, "retries"), Some(integer(3)));
+    assert_eq!(vm.get_global(&state, "missing"), None);
+
+    vm.set_global(&mut state, "retries", integer(5)).unwrap();
+    assert!(vm.set_global(&mut state, "missing", integer(5)).is_err());
+
+    let Some(VMValue::Object(config)) = vm.get_global(&state, "config") else {
+        panic!("config should be an object");
+    };
+    config.borrow_mut().insert(
+        "timeout".to_owned(),
+        Rc::new(types::ObjectValue::Literal(types::Literal::Integer(20)).into()),
+    );
+
+    assert_eq!(vm.resume(&mut state).unwrap(), Execution::Finished);
+    assert_eq!(vm.get_global(&state, "total"), Some(integer(100)));
+}
+
+#[test]
+fn host_reads_globals_after_finishing() {
+    let input = r#"
+        let mut total = 0;
+        for i in 0..4 {
+            total = total + i;
+        }
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let mut statements = Vec::new();
+    for token in parser {
+        match token {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                tracing::error!("{}", e);
+                break;
+            }
+        }
+    }
+    let program = compiler.compile(&statements).unwrap();
+
+    // `run` doesn't keep its state, resuming a state from `start` does the same work
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    assert_eq!(vm.resume(&mut state).unwrap(), Execution::Finished);
+
+    let total = VMValue::Literal(std::borrow::Cow::Owned(types::Literal::Integer(6)));
+    assert_eq!(vm.get_global(&state, "total"), Some(total));
+}
+
+#[test]
+fn instructions_executed_counts_every_instruction() {
+    let input = r#"
+        let x = 1 + 2;
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let (_, run_stats) = vm.run_with_registers_returned().unwrap();
+
+    // two literal loads and the add
+    assert_eq!(run_stats.instructions, 3);
+    assert_eq!(vm.stats().instructions_executed, 3);
+}
+
+#[test]
+fn native_function_keeps_state_in_context() {
+    let input = r#"
+        count();
+        count();
+        let total = count();
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program).define_native_function("count".to_owned(), |_, context| {
+        let counter = context.user_data.get_or_insert_with(|| Box::new(0i64));
+        let counter = counter.downcast_mut::<i64>().unwrap();
+        *counter += 1;
+
+        Ok(Some(VMValue::Literal(std::borrow::Cow::Owned(
+            types::Literal::Integer(*counter),
+        ))))
+    });
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    assert_compact_debug_snapshot!(register_state);
+    assert_eq!(vm.user_data::<i64>().as_deref(), Some(&3));
+}
+
+#[test]
+fn display_values() {
+    let function = VMValue::Function(Rc::new(sol::compiler::Function {
+        name: "add".to_owned(),
+        code: vec![],
+        register_count: 0,
+    }));
+
+    assert_eq!(function.to_string(), "<function: add>");
+    assert_eq!(
+        VMValue::Literal(std::borrow::Cow::Owned(types::Literal::Float(1.5))).to_string(),
+        "1.5"
+    );
+}
+
+// output that can still be read after the vm has taken it
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn print_writes_to_output() {
+    let input = r#"
+        let name = "sol";
+        print("hello ", name);
+        print([1, 2]);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let output = SharedBuffer::default();
+    let vm = VM::new(program).with_output(output.clone());
+    vm.run().unwrap();
+
+    assert_eq!(
+        String::from_utf8(output.0.take()).unwrap(),
+        "hello sol\n[1, 2]\n"
+    );
+}
+
+#[test]
+fn len_of_strings_and_arrays() {
+    let input = r#"
+        let word = len("héllo");
+        let empty = len("");
+        let items = len([1, 2, 3]);
+        let none = len([]);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let register_state = vm
+        .run_with_registers_returned()
+        .map(|(registers, _)| registers);
+
+    // é is two bytes but one char
+    assert_compact_debug_snapshot!(register_state);
+}
+
+#[test]
+fn len_of_other_values_is_an_error() {
+    let input = r#"
+        let n = len(3);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+
+    assert!(matches!(
+        vm.run(),
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "cannot take length of int"
+    ));
+}
+
+#[test]
+fn len_native_function() {
+    let input = r#"
+        let n = len("a", "b");
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+
+    // two arguments skip the instruction and reach the stdlib version
+    assert!(matches!(
+        vm.run(),
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "len expects exactly one argument"
+    ));
+}
+
+#[test]
+fn push_and_pop_native_functions() {
+    let input = r#"
+        let xs = [1];
+        push(xs);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+
+    // the wrong argument count skips the instruction and reaches the stdlib version
+    assert!(matches!(
+        vm.run(),
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "push expects an array and a value"
+    ));
+}
+
+#[test]
+fn range_native_function() {
+    let input = r#"
+        let to_three = range(3);
+        let one_to_four = range(1, 4);
+        let evens = range(0, 10, 2);
+        let down = range(5, 0, -2);
+        let empty = range(0);
+        let zero_step = range(0, 10, 0);
+        let wrong_way = range(0, 10, -1);
+        let backwards = range(3, 1);
+        let size = len(range(1000));
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    let global = |name| vm.get_global(&state, name).unwrap();
+    assert_eq!(global("to_three").to_string(), "[0, 1, 2]");
+    assert_eq!(global("one_to_four").to_string(), "[1, 2, 3]");
+    assert_eq!(global("evens").to_string(), "[0, 2, 4, 6, 8]");
+    assert_eq!(global("down").to_string(), "[5, 3, 1]");
+    assert_eq!(global("empty").to_string(), "[]");
+    assert!(matches!(global("zero_step"), VMValue::Nil));
+    assert!(matches!(global("wrong_way"), VMValue::Nil));
+    assert!(matches!(global("backwards"), VMValue::Nil));
+    assert!(
+        matches!(global("size"), VMValue::Literal(literal) if *literal == types::Literal::Integer(1000))
+    );
+}
+
+#[test]
+fn range_of_non_int_is_an_error() {
+    let input = r#"
+        let x = range(0, 2.5);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+
+    assert!(matches!(
+        vm.run(),
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "range expects an int but got float"
+    ));
+}
+
+#[test]
+fn split_native_function() {
+    let input = r#"
+        let csv = split("a,b,,c", ",");
+        let chars = split("héy", "");
+        let missing = split("abc", ";");
+        let multi = split("1::2::3", "::");
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    let global = |name| vm.get_global(&state, name).unwrap().to_string();
+    assert_eq!(global("csv"), r#"["a", "b", "", "c"]"#);
+    assert_eq!(global("chars"), r#"["h", "é", "y"]"#);
+    assert_eq!(global("missing"), r#"["abc"]"#);
+    assert_eq!(global("multi"), r#"["1", "2", "3"]"#);
+}
+
+#[test]
+fn split_of_non_string_is_an_error() {
+    let input = r#"
+        let x = split("1,2", 1);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+
+    assert!(matches!(
+        vm.run(),
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "split expects a string but got int"
+    ));
+}
+
+#[test]
+fn int_and_float_casts() {
+    let input = r#"
+        let truncated = int(3.7);
+        let negative = int(-3.7);
+        let widened = float(3);
+        let round_trip = int(float(42));
+        let float_round_trip = float(int(2.5));
+        let already_int = int(7);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    let global = |name| vm.get_global(&state, name).unwrap();
+    assert!(
+        matches!(global("truncated"), VMValue::Literal(literal) if *literal == types::Literal::Integer(3))
+    );
+    assert!(
+        matches!(global("negative"), VMValue::Literal(literal) if *literal == types::Literal::Integer(-3))
+    );
+    assert!(
+        matches!(global("widened"), VMValue::Literal(literal) if *literal == types::Literal::Float(3.0))
+    );
+    assert!(
+        matches!(global("round_trip"), VMValue::Literal(literal) if *literal == types::Literal::Integer(42))
+    );
+    assert!(
+        matches!(global("float_round_trip"), VMValue::Literal(literal) if *literal == types::Literal::Float(2.0))
+    );
+    assert!(
+        matches!(global("already_int"), VMValue::Literal(literal) if *literal == types::Literal::Integer(7))
+    );
+}
+
+#[test]
+fn string_functions() {
+    // there are no string escapes, the tab, ideographic space and no-break space are written as is
+    let input = r#"
+        let trimmed = trim("  sol 	 ");
+        let trimmed_unicode = trim("　héllo ");
+        let upper = to_uppercase("sol lang");
+        let upper_unicode = to_uppercase("straße ñ");
+        let lower = to_lowercase("SOL Lang");
+        let lower_unicode = to_lowercase("ÀÉÎ ΣΑ");
+        let has = contains("hello world", "lo w");
+        let has_not = contains("hello", "world");
+        let has_unicode = contains("日本語です", "本語");
+        let has_empty = contains("abc", "");
+        let starts = starts_with("sol lang", "sol");
+        let starts_not = starts_with("sol lang", "lang");
+        let starts_unicode = starts_with("émoji 🎉", "é");
+        let ends = ends_with("sol lang", "lang");
+        let ends_not = ends_with("sol lang", "sol");
+        let ends_unicode = ends_with("émoji 🎉", "🎉");
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    let global = |name| vm.get_global(&state, name).unwrap().to_string();
+    assert_eq!(global("trimmed"), "sol");
+    assert_eq!(global("trimmed_unicode"), "héllo");
+    assert_eq!(global("upper"), "SOL LANG");
+    assert_eq!(global("upper_unicode"), "STRASSE Ñ");
+    assert_eq!(global("lower"), "sol lang");
+    assert_eq!(global("lower_unicode"), "àéî σα");
+
+    for (name, expected) in [
+        ("has", true),
+        ("has_not", false),
+        ("has_unicode", true),
+        ("has_empty", true),
+        ("starts", true),
+        ("starts_not", false),
+        ("starts_unicode", true),
+        ("ends", true),
+        ("ends_not", false),
+        ("ends_unicode", true),
+    ] {
+        assert!(
+            matches!(
+                vm.get_global(&state, name),
+                Some(VMValue::Literal(literal)) if *literal == types::Literal::Boolean(expected)
+            ),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn string_functions_reject_non_strings() {
+    for (input, cause) in [
+        ("let x = trim(1);", "trim expects a string but got int"),
+        (
+            r#"let x = contains("abc", true);"#,
+            "contains expects a string but got bool",
+        ),
+        (
+            r#"let x = ends_with(nil, "c");"#,
+            "ends_with expects a string but got nil",
+        ),
+    ] {
+        let input = input.to_owned();
+        let lexer = Lexer::new(0, &input);
+        let parser = Parser::new(lexer, &input);
+        let compiler = Compiler::new();
+
+        let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+        let program = compiler.compile(&statements).unwrap();
+
+        let vm = VM::new(program);
+
+        assert!(matches!(
+            vm.run(),
+            Err(ExecutionError::InvalidOperation { cause: got }) if got == cause
+        ));
+    }
+}
+
+#[test]
+fn replace_and_replace_n() {
+    let input = r#"
+        let replaced = replace("hello world", "world", "Rust");
+        let every = replace("a-b-c", "-", "+");
+        let unicode = replace("naïve café", "é", "e");
+        let first_two = replace_n("aaa", "a", "b", 2);
+        let none = replace_n("aaa", "a", "b", 0);
+        let more_than_there = replace_n("aaa", "a", "b", 10);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    let global = |name| vm.get_global(&state, name).unwrap().to_string();
+    assert_eq!(global("replaced"), "hello Rust");
+    assert_eq!(global("every"), "a+b+c");
+    assert_eq!(global("unicode"), "naïve cafe");
+    assert_eq!(global("first_two"), "bba");
+    assert_eq!(global("none"), "aaa");
+    assert_eq!(global("more_than_there"), "bbb");
+}
+
+#[test]
+fn replace_rejects_bad_arguments() {
+    for (input, cause) in [
+        (
+            r#"let x = replace("abc", 1, "b");"#,
+            "replace expects a string but got int",
+        ),
+        (
+            r#"let x = replace("abc", "a", nil);"#,
+            "replace expects a string but got nil",
+        ),
+        (
+            r#"let x = replace_n("abc", "a", "b", "2");"#,
+            "replace_n expects an int but got string",
+        ),
+        (
+            r#"let x = replace_n("abc", "a", "b", -1);"#,
+            "replace_n expects a count of at least 0 but got -1",
+        ),
+    ] {
+        let input = input.to_owned();
+        let lexer = Lexer::new(0, &input);
+        let parser = Parser::new(lexer, &input);
+        let compiler = Compiler::new();
+
+        let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+        let program = compiler.compile(&statements).unwrap();
+
+        let vm = VM::new(program);
+
+        assert!(matches!(
+            vm.run(),
+            Err(ExecutionError::InvalidOperation { cause: got }) if got == cause
+        ));
+    }
+}
+
+#[test]
+fn math_functions() {
+    let input = r#"
+        let root = sqrt(16);
+        let int_abs = abs(-3);
+        let float_abs = abs(-2.5);
+        let down = floor(2.7);
+        let up = ceil(2.1);
+        let power = pow(2, 10);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    let global = |name| vm.get_global(&state, name).unwrap().to_string();
+    assert_eq!(global("root"), "4");
+    assert_eq!(global("int_abs"), "3");
+    assert_eq!(global("float_abs"), "2.5");
+    assert_eq!(global("down"), "2");
+    assert_eq!(global("up"), "3");
+    assert_eq!(global("power"), "1024");
+
+    // abs keeps ints as ints, the rest always give back floats
+    assert!(matches!(
+        vm.get_global(&state, "int_abs"),
+        Some(VMValue::Literal(literal)) if *literal == types::Literal::Integer(3)
+    ));
+    assert!(matches!(
+        vm.get_global(&state, "down"),
+        Some(VMValue::Literal(literal)) if *literal == types::Literal::Float(2.0)
+    ));
+}
+
+#[test]
+fn sqrt_of_negative_is_nan() {
+    let input = r#"
+        sqrt(-1.0);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let result = vm.run_with_result().unwrap();
+
+    assert!(matches!(
+        result,
+        Some(VMValue::Literal(literal)) if matches!(literal.as_ref(), types::Literal::Float(n) if n.is_nan())
+    ));
+}
+
+#[test]
+fn math_functions_reject_non_numbers() {
+    let input = r#"
+        let x = floor("1.5");
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+
+    assert!(matches!(
+        vm.run(),
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "floor expects a number but got string"
+    ));
+}
+
+#[test]
+fn parse_int_and_parse_float() {
+    let input = r#"
+        let int = parse_int("42");
+        let negative = parse_int("-7");
+        let not_int = parse_int("4.2");
+        let empty_int = parse_int("");
+        let float = parse_float("2.5");
+        let whole_float = parse_float("3");
+        let not_float = parse_float("abc");
+        let empty_float = parse_float("");
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    let global = |name| vm.get_global(&state, name).unwrap();
+    assert!(
+        matches!(global("int"), VMValue::Literal(literal) if *literal == types::Literal::Integer(42))
+    );
+    assert!(
+        matches!(global("negative"), VMValue::Literal(literal) if *literal == types::Literal::Integer(-7))
+    );
+    assert!(matches!(global("not_int"), VMValue::Nil));
+    assert!(matches!(global("empty_int"), VMValue::Nil));
+    assert!(
+        matches!(global("float"), VMValue::Literal(literal) if *literal == types::Literal::Float(2.5))
+    );
+    assert!(
+        matches!(global("whole_float"), VMValue::Literal(literal) if *literal == types::Literal::Float(3.0))
+    );
+    assert!(matches!(global("not_float"), VMValue::Nil));
+    assert!(matches!(global("empty_float"), VMValue::Nil));
+}
+
+#[test]
+fn parse_int_of_non_string_is_an_error() {
+    let input = r#"
+        let x = parse_int(42);
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let vm = VM::new(program);
+
+    assert!(matches!(
+        vm.run(),
+        Err(ExecutionError::InvalidOperation { cause }) if cause == "parse_int expects a string but got int"
+    ));
+}
+
+#[test]
+fn input_reads_lines_from_input() {
+    let input = r#"
+        let name = input("name? ");
+        let crlf = input();
+        let eof = input();
+        "#
+    .to_owned();
+
+    let lexer = Lexer::new(0, &input);
+    let parser = Parser::new(lexer, &input);
+    let compiler = Compiler::new();
+
+    let statements = parser.collect::<Result<Vec<_>, _>>().unwrap();
+    let program = compiler.compile(&statements).unwrap();
+
+    let output = SharedBuffer::default();
+    let vm = VM::new(program)
+        .with_input(Cursor::new("sol\nwindows\r\n"))
+        .with_output(output.clone());
+    let mut state = vm.start();
+    vm.resume(&mut state).unwrap();
+
+    assert_eq!(String::from_utf8(output.0.take()).unwrap(), "name? ");
+    assert_eq!(vm.get_global(&state, "name").unwrap().to_string(), "sol");
+    assert_eq!(
+        vm.get_global(&state, "crlf").unwrap().to_string(),
+        "windows"
+    );
+    assert!(matches!(vm.get_global(&state, "eof"), Some(VMValue::Nil)));
+}